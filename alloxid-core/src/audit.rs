@@ -0,0 +1,26 @@
+use sqlx::PgPool;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::database::insert_audit_event;
+use crate::events::UserEvents;
+use crate::model::audit::{AuditAction, AuditOrigin};
+
+/// Writes an event to the audit log and publishes the user event it announces to `events`. A
+/// failure is logged, but doesn't fail the request, which has already taken effect at this point.
+pub async fn record(
+    pool: &PgPool,
+    events: &UserEvents,
+    origin: &AuditOrigin,
+    action: AuditAction,
+    actor_id: Option<Uuid>,
+    subject_id: Option<Uuid>,
+    details: serde_json::Value,
+) {
+    if let Err(err) = insert_audit_event(pool, origin, action, actor_id, subject_id, details).await
+    {
+        error!("Failed to record audit event action={}: {:?}", action, err);
+    }
+
+    events.publish_audited(action, actor_id, subject_id);
+}
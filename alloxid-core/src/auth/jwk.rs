@@ -0,0 +1,124 @@
+use jsonwebtoken::Algorithm;
+use serde::{Deserialize, Serialize};
+use simple_asn1::ASN1Block;
+
+use crate::error::Error;
+use crate::settings::JwtKey;
+
+/// A public key as published at the JWKS endpoint, see RFC 7517.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Jwk {
+    pub kty: String,
+    pub kid: String,
+    #[serde(rename = "use")]
+    pub key_use: String,
+    pub alg: String,
+    // Modulus and exponent of RSA keys.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub e: Option<String>,
+    // Curve and coordinates of EC keys.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crv: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub y: Option<String>,
+}
+
+/// Turns the public key PEM into a JWK. HMAC keys are secret, so there's nothing to publish.
+pub fn public_jwk(key: &JwtKey, pem: Option<&[u8]>) -> Result<Option<Jwk>, Error> {
+    let pem = match (key.algorithm, pem) {
+        (Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512, _) | (_, None) => return Ok(None),
+        (_, Some(pem)) => pem,
+    };
+
+    let pem = pem::parse(pem).map_err(|err| invalid(key, err))?;
+    let blocks = simple_asn1::from_der(&pem.contents).map_err(|err| invalid(key, err))?;
+
+    let mut jwk = Jwk {
+        kty: String::new(),
+        kid: key.kid.clone(),
+        key_use: "sig".to_string(),
+        alg: format!("{:?}", key.algorithm),
+        n: None,
+        e: None,
+        crv: None,
+        x: None,
+        y: None,
+    };
+
+    match key.algorithm {
+        Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512 => {
+            // Either a PKCS#1 `RSA PUBLIC KEY` or the modulus and exponent wrapped in a
+            // `PUBLIC KEY`.
+            let rsa_key = match pem.tag.as_str() {
+                "RSA PUBLIC KEY" => blocks,
+                _ => simple_asn1::from_der(
+                    subject_public_key(&blocks)
+                        .ok_or_else(|| invalid(key, "not a subject public key"))?,
+                )
+                .map_err(|err| invalid(key, err))?,
+            };
+
+            match rsa_key.as_slice() {
+                [ASN1Block::Sequence(_, parts)] => match parts.as_slice() {
+                    [ASN1Block::Integer(_, n), ASN1Block::Integer(_, e)] => {
+                        jwk.kty = "RSA".to_string();
+                        jwk.n = Some(encode(&n.to_bytes_be().1));
+                        jwk.e = Some(encode(&e.to_bytes_be().1));
+                    }
+                    _ => return Err(invalid(key, "not an RSA public key")),
+                },
+                _ => return Err(invalid(key, "not an RSA public key")),
+            }
+        }
+        Algorithm::ES256 | Algorithm::ES384 => {
+            let (crv, len) = match key.algorithm {
+                Algorithm::ES256 => ("P-256", 32),
+                _ => ("P-384", 48),
+            };
+
+            // An uncompressed point, `0x04 || x || y`.
+            let point = subject_public_key(&blocks)
+                .filter(|point| point.len() == 1 + 2 * len && point[0] == 0x04)
+                .ok_or_else(|| invalid(key, "not an uncompressed EC public key"))?;
+
+            jwk.kty = "EC".to_string();
+            jwk.crv = Some(crv.to_string());
+            jwk.x = Some(encode(&point[1..=len]));
+            jwk.y = Some(encode(&point[len + 1..]));
+        }
+        algorithm => {
+            return Err(Error::Config(format!(
+                "Unsupported JWT algorithm: {:?}",
+                algorithm
+            )))
+        }
+    }
+
+    Ok(Some(jwk))
+}
+
+/// The key bits of a `SubjectPublicKeyInfo`.
+fn subject_public_key(blocks: &[ASN1Block]) -> Option<&[u8]> {
+    match blocks {
+        [ASN1Block::Sequence(_, parts)] => match parts.as_slice() {
+            [ASN1Block::Sequence(_, _), ASN1Block::BitString(_, _, bits)] => Some(bits),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn encode(bytes: &[u8]) -> String {
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+fn invalid(key: &JwtKey, err: impl std::fmt::Display) -> Error {
+    Error::Config(format!(
+        "Invalid public key of JWT key {}: {}",
+        key.kid, err
+    ))
+}
@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use jsonwebtoken::{decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header};
+use jsonwebtoken::{TokenData, Validation};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tracing::error;
+
+use super::jwk::{public_jwk, Jwk};
+use crate::error::Error;
+use crate::settings::{self, JwtKey};
+
+/// The key material used to sign and verify tokens, built once from `Settings.auth`.
+#[derive(Clone)]
+pub struct JwtKeys {
+    kid: String,
+    algorithm: Algorithm,
+    encoding_key: EncodingKey,
+    decoding_keys: HashMap<String, (Algorithm, DecodingKey<'static>)>,
+    // The public keys of all asymmetric keys, for others to verify our tokens.
+    jwks: Vec<Jwk>,
+}
+
+impl JwtKeys {
+    pub fn from_settings(auth: &settings::Auth) -> Result<Self, Error> {
+        let signing_key = &auth.signing_key;
+
+        let mut decoding_keys = HashMap::new();
+        let mut jwks = Vec::new();
+        for key in std::iter::once(signing_key).chain(auth.verification_keys.iter()) {
+            let public_pem = match key.algorithm {
+                Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => None,
+                _ => Some(read_pem(key, key.public_key_path.as_ref())?),
+            };
+
+            let decoding_key = decoding_key(key, public_pem.as_deref())?;
+            jwks.extend(public_jwk(key, public_pem.as_deref())?);
+            if decoding_keys
+                .insert(key.kid.clone(), (key.algorithm, decoding_key))
+                .is_some()
+            {
+                return Err(Error::Config(format!("Duplicate JWT key id: {}", key.kid)));
+            }
+        }
+
+        Ok(Self {
+            kid: signing_key.kid.clone(),
+            algorithm: signing_key.algorithm,
+            encoding_key: encoding_key(signing_key)?,
+            decoding_keys,
+            jwks,
+        })
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    /// The public keys for the JWKS endpoint, empty if only HMAC keys are configured.
+    pub fn jwks(&self) -> &[Jwk] {
+        &self.jwks
+    }
+
+    pub fn encode<T: Serialize>(&self, claims: &T) -> Result<String, Error> {
+        let mut header = Header::new(self.algorithm);
+        header.kid = Some(self.kid.clone());
+
+        encode(&header, claims, &self.encoding_key).map_err(|err| {
+            error!("Failed to encode token: {:?}", err);
+            Error::TokenCreationError
+        })
+    }
+
+    pub fn decode<T: DeserializeOwned>(&self, token: &str) -> Result<TokenData<T>, Error> {
+        let header = decode_header(token).map_err(|err| {
+            error!("Failed to decode token header: {:?}", err);
+            Error::TokenExtractionError
+        })?;
+
+        // Tokens without a `kid` can only have been signed by the current key.
+        let kid = header.kid.as_ref().unwrap_or(&self.kid);
+
+        let (algorithm, decoding_key) = self.decoding_keys.get(kid).ok_or_else(|| {
+            error!("Unknown key id: {}", kid);
+            Error::TokenExtractionError
+        })?;
+
+        decode::<T>(token, decoding_key, &Validation::new(*algorithm)).map_err(|err| {
+            error!("Failed to decode token: {:?}", err);
+            Error::TokenExtractionError
+        })
+    }
+}
+
+impl fmt::Debug for JwtKeys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JwtKeys")
+            .field("kid", &self.kid)
+            .field("algorithm", &self.algorithm)
+            .field("decoding_kids", &self.decoding_keys.keys())
+            .finish()
+    }
+}
+
+fn encoding_key(key: &JwtKey) -> Result<EncodingKey, Error> {
+    match key.algorithm {
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+            Ok(EncodingKey::from_secret(secret(key)?.as_bytes()))
+        }
+        Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512 => {
+            let pem = read_pem(key, key.private_key_path.as_ref())?;
+            EncodingKey::from_rsa_pem(&pem).map_err(Error::from)
+        }
+        Algorithm::ES256 | Algorithm::ES384 => {
+            let pem = read_pem(key, key.private_key_path.as_ref())?;
+            EncodingKey::from_ec_pem(&pem).map_err(Error::from)
+        }
+        algorithm => Err(unsupported(algorithm)),
+    }
+}
+
+fn decoding_key(key: &JwtKey, public_pem: Option<&[u8]>) -> Result<DecodingKey<'static>, Error> {
+    match (key.algorithm, public_pem) {
+        (Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512, _) => {
+            Ok(DecodingKey::from_secret(secret(key)?.as_bytes()).into_static())
+        }
+        (Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512, Some(pem)) => {
+            let decoding_key = DecodingKey::from_rsa_pem(pem)?;
+            Ok(decoding_key.into_static())
+        }
+        (Algorithm::ES256 | Algorithm::ES384, Some(pem)) => {
+            let decoding_key = DecodingKey::from_ec_pem(pem)?;
+            Ok(decoding_key.into_static())
+        }
+        (algorithm, _) => Err(unsupported(algorithm)),
+    }
+}
+
+fn secret(key: &JwtKey) -> Result<&str, Error> {
+    match key.secret.as_deref() {
+        Some(secret) if !secret.is_empty() => Ok(secret),
+        _ => Err(Error::Config(format!(
+            "Missing secret for JWT key {}",
+            key.kid
+        ))),
+    }
+}
+
+fn read_pem(key: &JwtKey, path: Option<&String>) -> Result<Vec<u8>, Error> {
+    let path = path
+        .ok_or_else(|| Error::Config(format!("Missing PEM file path for JWT key {}", key.kid)))?;
+
+    std::fs::read(settings::crate_root().join(path))
+        .map_err(|err| Error::Config(format!("Failed to read PEM file {}: {}", path, err)))
+}
+
+fn unsupported(algorithm: Algorithm) -> Error {
+    Error::Config(format!("Unsupported JWT algorithm: {:?}", algorithm))
+}
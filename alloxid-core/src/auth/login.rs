@@ -0,0 +1,144 @@
+use std::sync::Arc;
+
+use sqlx::PgPool;
+use tracing::error;
+
+use crate::audit;
+use crate::database::{self, LoginUser};
+use crate::error::Error;
+use crate::events::UserEvents;
+use crate::model::audit::{AuditAction, AuditOrigin};
+use crate::password::{self, DummyHash, PasswordHasher};
+
+use super::LoginThrottle;
+
+/// The credentials of a login, or of a restore with `deleted`, as the services receive them.
+#[derive(Debug)]
+pub struct Login<'a> {
+    /// A username or verified email address, normalized with `Validator::login_name`.
+    pub username: &'a str,
+    pub password: String,
+    /// Looks for accounts pending deletion instead of active ones.
+    pub deleted: bool,
+    pub origin: &'a AuditOrigin,
+}
+
+/// Checks the password of a login, counting failures in the throttle and recording them in the
+/// audit log, and turns away accounts that can't log in, see `ensure_allowed`.
+///
+/// The second factor is up to the caller, as is starting the session.
+pub async fn authenticate(
+    pool: &PgPool,
+    events: &UserEvents,
+    hasher: Arc<dyn PasswordHasher>,
+    dummy_hash: &DummyHash,
+    throttle: &LoginThrottle,
+    login: Login<'_>,
+) -> Result<LoginUser, Error> {
+    let Login {
+        username,
+        password,
+        deleted,
+        origin,
+    } = login;
+    let ip = origin.ip;
+
+    throttle.check(username, ip)?;
+
+    let row = database::find_login_user(pool, username, deleted).await?;
+
+    // Unknown users are verified against a dummy hash, so they take as long as wrong passwords.
+    let hash = match &row {
+        Some(row) => row.hashed_password.clone(),
+        None => dummy_hash.get(hasher.clone()).await?,
+    };
+    let is_valid = password::verify_password(hasher.clone(), hash, password.clone()).await?;
+
+    let row = match row {
+        Some(row) if is_valid => row,
+        _ => {
+            error!("Failed login attempt for username={} ip={:?}", username, ip);
+            throttle.record_failure(username, ip);
+            audit::record(
+                pool,
+                events,
+                origin,
+                AuditAction::LoginFailed,
+                None,
+                row.map(|row| row.user_id),
+                serde_json::json!({ "username": username, "reason": "invalid_credentials" }),
+            )
+            .await;
+            return Err(Error::Unauthorized);
+        }
+    };
+
+    throttle.record_success(username);
+
+    ensure_allowed(pool, events, origin, &row).await?;
+
+    if hasher.needs_rehash(&row.hashed_password) {
+        upgrade_password_hash(pool, hasher, &row, password).await;
+    }
+
+    Ok(row)
+}
+
+/// Turns away accounts that are disabled or need a password reset. Only the actual owner of the
+/// account is told why, after proving it with their credentials.
+pub async fn ensure_allowed(
+    pool: &PgPool,
+    events: &UserEvents,
+    origin: &AuditOrigin,
+    user: &LoginUser,
+) -> Result<(), Error> {
+    let reason = if user.disabled_at.is_some() {
+        "disabled"
+    } else if user.password_reset_required {
+        "password_reset_required"
+    } else {
+        return Ok(());
+    };
+
+    error!(
+        "Refused login of user_id={}, reason={}",
+        user.user_id, reason
+    );
+    audit::record(
+        pool,
+        events,
+        origin,
+        AuditAction::LoginFailed,
+        None,
+        Some(user.user_id),
+        serde_json::json!({ "username": user.username, "reason": reason }),
+    )
+    .await;
+
+    Err(Error::Forbidden(reason.to_string()))
+}
+
+/// Rehashes the password with the current parameters. Failing to do so doesn't fail the login,
+/// it's simply tried again next time.
+async fn upgrade_password_hash(
+    pool: &PgPool,
+    hasher: Arc<dyn PasswordHasher>,
+    user: &LoginUser,
+    password: String,
+) {
+    let res = match password::hash_password(hasher, password).await {
+        Ok(new_hash) => {
+            database::upgrade_password_hash(pool, &user.user_id, &user.hashed_password, &new_hash)
+                .await
+                .map_err(Error::from)
+        }
+        Err(err) => Err(err),
+    };
+
+    if let Err(err) = res {
+        error!(
+            "Failed to upgrade password hash of user_id={}: {:?}",
+            user.user_id, err
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use uuid::Uuid;
+
+pub mod jwk;
+pub mod keys;
+pub mod login;
+pub mod session;
+pub mod throttle;
+pub mod tokens;
+pub use keys::JwtKeys;
+pub use login::{authenticate, ensure_allowed, Login};
+pub use throttle::LoginThrottle;
+pub use tokens::*;
+
+use crate::error::Error;
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct UserId(Uuid);
+
+impl UserId {
+    pub fn new(uuid: Uuid) -> Self {
+        Self(uuid)
+    }
+
+    pub fn take(self) -> Uuid {
+        self.0
+    }
+}
+
+/// The claims of the access tokens handed out by all services.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Claims {
+    // Subject of the token
+    pub sub: UserId,
+    // Custom data point.
+    pub role: String,
+    // Expiration date.
+    pub exp: usize,
+    // Token id, the session (refresh token family) the token was issued for.
+    pub jti: Uuid,
+    // Set for tokens that only prove the password and still wait for a second factor.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub mfa_pending: bool,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum Role {
+    Admin,
+    User,
+}
+
+impl Role {
+    // Unknown roles fall back to `User` rather than failing like `FromStr` would.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(role: &str) -> Self {
+        match role {
+            "Admin" => Self::Admin,
+            _ => Self::User,
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Admin => write!(f, "Admin"),
+            Self::User => write!(f, "User"),
+        }
+    }
+}
+
+/// What an API key is allowed to do. Sessions of a user are allowed everything their role is.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum Scope {
+    #[serde(rename = "user:read")]
+    UserRead,
+    #[serde(rename = "user:write")]
+    UserWrite,
+    // Only grants anything if the key's user is an admin.
+    #[serde(rename = "admin")]
+    Admin,
+}
+
+impl Scope {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(scope: &str) -> Option<Self> {
+        match scope {
+            "user:read" => Some(Self::UserRead),
+            "user:write" => Some(Self::UserWrite),
+            "admin" => Some(Self::Admin),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UserRead => write!(f, "user:read"),
+            Self::UserWrite => write!(f, "user:write"),
+            Self::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+pub fn create(
+    keys: &JwtKeys,
+    user_id: UserId,
+    role: Role,
+    session_id: Uuid,
+    ttl: chrono::Duration,
+) -> Result<String, Error> {
+    let exp = Utc::now()
+        .checked_add_signed(ttl)
+        .expect("Failed to create valid timestamp")
+        .timestamp();
+
+    let claims = Claims {
+        sub: user_id,
+        role: role.to_string(),
+        exp: exp as usize,
+        jti: session_id,
+        mfa_pending: false,
+    };
+
+    keys.encode(&claims)
+}
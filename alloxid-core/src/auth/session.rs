@@ -0,0 +1,48 @@
+use chrono::Duration;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{create, JwtKeys, Role, UserId};
+use crate::database::insert_auth_token;
+use crate::error::Error;
+use crate::model::user::UserAuthData;
+use crate::settings;
+
+/// Starts a new session for the user, i.e. a new refresh token family.
+pub async fn start(
+    pool: &PgPool,
+    keys: &JwtKeys,
+    auth: &settings::Auth,
+    user_id: Uuid,
+    role: Role,
+) -> Result<UserAuthData, Error> {
+    let family_id = Uuid::new_v4();
+    let refresh_token =
+        insert_auth_token(pool, &user_id, &family_id, refresh_token_ttl(auth)).await?;
+
+    tokens(keys, auth, user_id, role, family_id, refresh_token)
+}
+
+/// Hands out a new access token of the session along with its current refresh token.
+pub fn tokens(
+    keys: &JwtKeys,
+    auth: &settings::Auth,
+    user_id: Uuid,
+    role: Role,
+    family_id: Uuid,
+    refresh_token: String,
+) -> Result<UserAuthData, Error> {
+    let ttl = Duration::minutes(auth.access_token_minutes);
+    let token = create(keys, UserId::new(user_id), role, family_id, ttl)?;
+
+    Ok(UserAuthData {
+        id: user_id,
+        token,
+        expires_in: ttl.num_seconds(),
+        refresh_token,
+    })
+}
+
+pub fn refresh_token_ttl(auth: &settings::Auth) -> Duration {
+    Duration::days(auth.refresh_token_days)
+}
@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tracing::warn;
+
+use crate::error::Error;
+use crate::settings;
+
+/// Counts failed logins per username and per IP address and locks them out with exponential
+/// backoff once they exceed their free attempts.
+///
+/// Like the `RevocationCache` of `alloxid-http`, the counters are kept per instance.
+#[derive(Debug)]
+pub struct LoginThrottle {
+    settings: settings::LoginThrottle,
+    entries: Mutex<HashMap<Key, Failures>>,
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+enum Key {
+    Username(String),
+    Ip(IpAddr),
+}
+
+#[derive(Debug)]
+struct Failures {
+    count: u32,
+    last_failure: Instant,
+    locked_until: Option<Instant>,
+}
+
+impl LoginThrottle {
+    pub fn new(settings: settings::LoginThrottle) -> Self {
+        Self {
+            settings,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Rejects the login attempt with `TooManyRequests` if the username or the IP is locked out.
+    pub fn check(&self, username: &str, ip: Option<IpAddr>) -> Result<(), Error> {
+        let entries = self.entries.lock().expect("Login throttle poisoned");
+        let now = Instant::now();
+
+        let retry_after = keys(username, ip)
+            .filter_map(|key| entries.get(&key))
+            .filter_map(|failures| failures.locked_until)
+            .filter(|locked_until| *locked_until > now)
+            .map(|locked_until| locked_until - now)
+            .max();
+
+        match retry_after {
+            Some(retry_after) => {
+                warn!("Login of username={} ip={:?} is locked out", username, ip);
+                Err(Error::TooManyRequests {
+                    // Round up, so that clients don't retry a moment too early.
+                    retry_after_seconds: retry_after.as_secs() + 1,
+                })
+            }
+            None => Ok(()),
+        }
+    }
+
+    pub fn record_failure(&self, username: &str, ip: Option<IpAddr>) {
+        let mut entries = self.entries.lock().expect("Login throttle poisoned");
+        let now = Instant::now();
+
+        if entries.len() >= self.settings.capacity {
+            let forget_after = self.forget_after();
+            entries.retain(|_, failures| failures.last_failure.elapsed() < forget_after);
+        }
+
+        for key in keys(username, ip) {
+            if entries.len() >= self.settings.capacity && !entries.contains_key(&key) {
+                evict(&mut entries, now);
+            }
+
+            let free_attempts = match key {
+                Key::Username(_) => self.settings.username_free_attempts,
+                Key::Ip(_) => self.settings.ip_free_attempts,
+            };
+
+            let failures = entries.entry(key).or_insert(Failures {
+                count: 0,
+                last_failure: now,
+                locked_until: None,
+            });
+
+            if failures.last_failure.elapsed() >= self.forget_after() {
+                failures.count = 0;
+            }
+
+            failures.count += 1;
+            failures.last_failure = now;
+
+            if failures.count >= free_attempts {
+                failures.locked_until = Some(now + self.lockout(failures.count - free_attempts));
+            }
+        }
+    }
+
+    /// Forgets the failures of the username, but not of the IP, whose other attempts might
+    /// have been made against other accounts.
+    pub fn record_success(&self, username: &str) {
+        let mut entries = self.entries.lock().expect("Login throttle poisoned");
+        entries.remove(&Key::Username(username.to_lowercase()));
+    }
+
+    /// The lockout doubles with every failure after the free attempts, up to the maximum.
+    fn lockout(&self, exceeded: u32) -> Duration {
+        let seconds = self
+            .settings
+            .backoff_seconds
+            .saturating_mul(2u64.saturating_pow(exceeded))
+            .min(self.settings.max_lockout_seconds);
+
+        Duration::from_secs(seconds)
+    }
+
+    fn forget_after(&self) -> Duration {
+        Duration::from_secs(self.settings.forget_after_seconds)
+    }
+}
+
+/// Makes room for a new entry. Entries that aren't locked out go first, oldest failure first, so
+/// that flooding the throttle with new keys doesn't lift running lockouts.
+fn evict(entries: &mut HashMap<Key, Failures>, now: Instant) {
+    let oldest = entries
+        .iter()
+        .min_by_key(|(_, failures)| {
+            let locked = failures
+                .locked_until
+                .is_some_and(|locked_until| locked_until > now);
+            (locked, failures.last_failure)
+        })
+        .map(|(key, _)| key.clone());
+
+    if let Some(key) = oldest {
+        warn!("Login throttle is full, evicting {:?}", key);
+        entries.remove(&key);
+    }
+}
+
+fn keys(username: &str, ip: Option<IpAddr>) -> impl Iterator<Item = Key> {
+    std::iter::once(Key::Username(username.to_lowercase())).chain(ip.map(Key::Ip))
+}
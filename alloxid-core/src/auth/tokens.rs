@@ -0,0 +1,17 @@
+use rand::{thread_rng, RngCore};
+use sha2::{Digest, Sha256};
+
+/// Generates a random opaque token, e.g. for refresh tokens.
+///
+/// Only the hash of the token should ever be stored, see `hash_token`.
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    thread_rng().fill_bytes(&mut bytes);
+
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+/// Opaque tokens carry enough entropy that a fast hash is sufficient to store them.
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
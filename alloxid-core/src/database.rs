@@ -0,0 +1,2030 @@
+use std::sync::Arc;
+
+use chrono::prelude::*;
+use chrono::Duration;
+use sqlx::{Done, Executor, PgPool, Postgres, Transaction};
+use tracing::{debug, debug_span, error, warn, Instrument};
+use uuid::Uuid;
+
+use crate::auth::{generate_token, hash_token, Role};
+use crate::error::Error;
+use crate::model::admin::AdminUserData;
+use crate::model::api_key::{ApiKeyData, ApiKeyEntry};
+use crate::model::audit::{AuditAction, AuditEventData, AuditOrigin};
+use crate::model::export::{
+    DataExportEntry, DataExportStatus, EmailVerificationExport, OAuthAuthorizationExport,
+    PasswordResetExport, SessionExport, UserExport, UserExportEntry,
+};
+use crate::model::oauth::OAuthClientEntry;
+use crate::model::user::{UserCreateRaw, UserData, UserEntry, ValidUserData, ValidUserPatch};
+use crate::password::{hash_password, PasswordHasher};
+
+pub async fn insert_new_user(
+    pool: &PgPool,
+    user_data: ValidUserData,
+    hasher: Arc<dyn PasswordHasher>,
+) -> Result<UserEntry, Error> {
+    let id = Uuid::new_v4();
+    let date = Utc::now();
+
+    let ValidUserData(UserCreateRaw {
+        username,
+        password,
+        email,
+    }) = user_data;
+
+    let hash = hash_password(hasher, password).await?;
+
+    let user_span = debug_span!("user_span");
+    let res = sqlx::query_as!(
+        UserEntry,
+        r#"
+            INSERT INTO users (
+                id,
+                username,
+                hashed_password,
+                created_at,
+                updated_at,
+                email
+            ) VALUES ( $1, $2, $3, $4, $5, $6)
+            RETURNING *
+        "#,
+        id,
+        username,
+        hash,
+        date,
+        date,
+        email,
+    )
+    .fetch_one(pool)
+    .instrument(user_span)
+    .await
+    .map_err(|err| {
+        error!("Err: {:?}", err);
+        Error::from_unique_violation(err, "username")
+    })?;
+
+    debug!("Inserted user into DB for user_id={}.", id);
+    Ok(res)
+}
+
+/// The user logging in with a username or verified email address.
+#[derive(Debug)]
+pub struct LoginUser {
+    pub user_id: Uuid,
+    pub username: String,
+    pub hashed_password: String,
+    pub role: String,
+    pub disabled_at: Option<DateTime<Utc>>,
+    pub password_reset_required: bool,
+    pub totp_enabled_at: Option<DateTime<Utc>>,
+}
+
+/// Finds active users, or with `deleted` those whose account is pending deletion, so that they
+/// can restore it with the same credentials.
+pub async fn find_login_user(
+    pool: &PgPool,
+    username: &str,
+    deleted: bool,
+) -> Result<Option<LoginUser>, sqlx::Error> {
+    sqlx::query_as!(
+        LoginUser,
+        r#"
+            select
+                id as user_id,
+                username,
+                hashed_password,
+                role,
+                disabled_at,
+                password_reset_required,
+                totp_enabled_at
+            from users
+            where case
+                -- Usernames can't contain an @, addresses only count once they're verified.
+                when strpos($1, '@') > 0 then lower(email) = lower($1) and email_verified_at is not null
+                else lower(username) = lower($1)
+            end
+            and (deleted_at is not null) = $2
+        "#,
+        username,
+        deleted,
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Looks up a user in the middle of logging in again, e.g. after the second factor, in case the
+/// account has been disabled or deleted since the password was checked.
+pub async fn get_login_user(
+    pool: &PgPool,
+    user_id: &Uuid,
+) -> Result<Option<LoginUser>, sqlx::Error> {
+    sqlx::query_as!(
+        LoginUser,
+        r#"
+            select
+                id as user_id,
+                username,
+                hashed_password,
+                role,
+                disabled_at,
+                password_reset_required,
+                totp_enabled_at
+            from users
+            where id = $1 and deleted_at is null
+        "#,
+        user_id,
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Inserts a new refresh token into the given token family and returns the token itself.
+pub async fn insert_auth_token<'e, E>(
+    executor: E,
+    user_id: &Uuid,
+    family_id: &Uuid,
+    ttl: Duration,
+) -> Result<String, sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let token_id = Uuid::new_v4();
+    let token = generate_token();
+    let date = Utc::now();
+
+    let token_span = debug_span!("token_span");
+    sqlx::query!(
+        r#"
+            INSERT INTO auth_tokens (
+                id,
+                user_id,
+                token_hash,
+                family_id,
+                created_at,
+                expires_at
+            ) VALUES ( $1, $2, $3, $4, $5, $6)
+        "#,
+        token_id,
+        user_id,
+        hash_token(&token),
+        family_id,
+        date,
+        date + ttl,
+    )
+    .execute(executor)
+    .instrument(token_span)
+    .await
+    .map_err(|err| {
+        error!("Err: {:?}", err);
+        err
+    })?;
+
+    debug!("Inserted token into DB for user_id={}", user_id);
+    Ok(token)
+}
+
+/// A refresh token that has been exchanged for a new one.
+pub struct RotatedToken {
+    pub user_id: Uuid,
+    pub role: Role,
+    pub family_id: Uuid,
+    pub token: String,
+}
+
+pub enum Rotation {
+    Rotated(RotatedToken),
+    // An already rotated token has been presented again, its family has been revoked.
+    Reused { user_id: Uuid, family_id: Uuid },
+    // The token is unknown, has expired or its user has been disabled.
+    Invalid,
+}
+
+/// Exchanges a refresh token for a new one of the same family.
+///
+/// Presenting a token that has already been rotated means it has leaked, in which case the whole
+/// family is revoked.
+pub async fn rotate_auth_token(
+    pool: &PgPool,
+    token: &str,
+    ttl: Duration,
+) -> Result<Rotation, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let row = sqlx::query!(
+        r#"
+            select
+                auth_tokens.id,
+                auth_tokens.user_id,
+                auth_tokens.family_id,
+                auth_tokens.expires_at,
+                auth_tokens.revoked_at,
+                users.role,
+                users.disabled_at,
+                users.deleted_at
+            from auth_tokens
+            join users on users.id = auth_tokens.user_id
+            where auth_tokens.token_hash = $1
+            for update of auth_tokens
+        "#,
+        hash_token(token),
+    )
+    .fetch_optional(&mut tx)
+    .await?;
+
+    let row = match row {
+        Some(row) => row,
+        None => {
+            debug!("Unknown refresh token");
+            return Ok(Rotation::Invalid);
+        }
+    };
+
+    if row.revoked_at.is_some() {
+        warn!(
+            "Revoked refresh token reused, revoking family_id={} for user_id={}",
+            row.family_id, row.user_id
+        );
+        revoke_auth_token_family(&mut tx, &row.family_id).await?;
+        tx.commit().await?;
+        return Ok(Rotation::Reused {
+            user_id: row.user_id,
+            family_id: row.family_id,
+        });
+    }
+
+    if row.expires_at < Utc::now() {
+        debug!("Expired refresh token for user_id={}", row.user_id);
+        return Ok(Rotation::Invalid);
+    }
+
+    if row.disabled_at.is_some() || row.deleted_at.is_some() {
+        debug!(
+            "Refresh token of disabled or deleted user_id={}",
+            row.user_id
+        );
+        return Ok(Rotation::Invalid);
+    }
+
+    sqlx::query!(
+        r#" update auth_tokens set revoked_at = $2 where id = $1; "#,
+        row.id,
+        Utc::now(),
+    )
+    .execute(&mut tx)
+    .await?;
+
+    let token = insert_auth_token(&mut tx, &row.user_id, &row.family_id, ttl).await?;
+
+    tx.commit().await?;
+
+    Ok(Rotation::Rotated(RotatedToken {
+        user_id: row.user_id,
+        role: Role::from_str(&row.role),
+        family_id: row.family_id,
+        token,
+    }))
+}
+
+/// Revokes all tokens of the family the given refresh token belongs to.
+///
+/// Returns the user and family id, or `None` if the token is unknown.
+pub async fn revoke_auth_token(
+    pool: &PgPool,
+    token: &str,
+) -> Result<Option<(Uuid, Uuid)>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#" select user_id, family_id from auth_tokens where token_hash = $1; "#,
+        hash_token(token),
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    match row {
+        Some(row) => {
+            revoke_auth_token_family(pool, &row.family_id).await?;
+            Ok(Some((row.user_id, row.family_id)))
+        }
+        None => Ok(None),
+    }
+}
+
+/// The family, i.e. the session, a refresh token belongs to, whether it's still valid or not.
+pub async fn find_auth_token_family(
+    pool: &PgPool,
+    token: &str,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#" select family_id from auth_tokens where token_hash = $1; "#,
+        hash_token(token),
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|row| row.family_id))
+}
+
+/// A session is active as long as its family holds a token that is neither revoked nor expired,
+/// and its user hasn't been disabled or deleted.
+pub async fn is_session_active(
+    pool: &PgPool,
+    family_id: &Uuid,
+    user_id: &Uuid,
+) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+            select exists (
+                select 1 from auth_tokens
+                join users on users.id = auth_tokens.user_id
+                where auth_tokens.family_id = $1
+                and auth_tokens.user_id = $2
+                and auth_tokens.revoked_at is null
+                and auth_tokens.expires_at > now()
+                and users.disabled_at is null
+                and users.deleted_at is null
+            ) as "active!"
+        "#,
+        family_id,
+        user_id,
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.active)
+}
+
+async fn revoke_auth_token_family<'e, E>(executor: E, family_id: &Uuid) -> Result<(), sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+            update auth_tokens set revoked_at = $2
+            where family_id = $1 and revoked_at is null
+        "#,
+        family_id,
+        Utc::now(),
+    )
+    .execute(executor)
+    .await?;
+
+    debug!("Revoked token family_id={}", family_id);
+    Ok(())
+}
+
+/// Revokes all sessions of a user, e.g. after a change to the account.
+pub async fn revoke_user_auth_tokens<'e, E>(executor: E, user_id: &Uuid) -> Result<(), sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+            update auth_tokens set revoked_at = $2
+            where user_id = $1 and revoked_at is null
+        "#,
+        user_id,
+        Utc::now(),
+    )
+    .execute(executor)
+    .await?;
+
+    debug!("Revoked all tokens of user_id={}", user_id);
+    Ok(())
+}
+
+/// Revokes all sessions of a user but the given one, e.g. after the user changed their password.
+pub async fn revoke_other_auth_tokens(
+    pool: &PgPool,
+    user_id: &Uuid,
+    family_id: &Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+            update auth_tokens set revoked_at = $3
+            where user_id = $1 and family_id <> $2 and revoked_at is null
+        "#,
+        user_id,
+        family_id,
+        Utc::now(),
+    )
+    .execute(pool)
+    .await?;
+
+    debug!(
+        "Revoked all tokens of user_id={} except family_id={}",
+        user_id, family_id
+    );
+    Ok(())
+}
+
+/// Stores a new hash for the user's password, which also lifts a required password reset.
+pub async fn set_password<'e, E>(
+    executor: E,
+    user_id: &Uuid,
+    hashed_password: &str,
+) -> Result<(), sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+            update users
+            set hashed_password = $2, password_reset_required = false, updated_at = $3
+            where id = $1
+        "#,
+        user_id,
+        hashed_password,
+        Utc::now(),
+    )
+    .execute(executor)
+    .await?;
+
+    debug!("Set new password of user_id={}", user_id);
+    Ok(())
+}
+
+/// Replaces a hash made with outdated parameters, unless the password has changed meanwhile.
+pub async fn upgrade_password_hash(
+    pool: &PgPool,
+    user_id: &Uuid,
+    old_hash: &str,
+    new_hash: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+            update users set hashed_password = $3
+            where id = $1 and hashed_password = $2
+        "#,
+        user_id,
+        old_hash,
+        new_hash,
+    )
+    .execute(pool)
+    .await?;
+
+    debug!("Upgraded password hash of user_id={}", user_id);
+    Ok(())
+}
+
+/// The two-factor authentication state of a user.
+pub struct TotpState {
+    pub username: String,
+    pub totp_secret: Option<String>,
+    pub totp_enabled_at: Option<DateTime<Utc>>,
+}
+
+pub async fn get_totp_state(
+    pool: &PgPool,
+    user_id: &Uuid,
+) -> Result<Option<TotpState>, sqlx::Error> {
+    sqlx::query_as!(
+        TotpState,
+        r#" select username, totp_secret, totp_enabled_at from users where id = $1; "#,
+        user_id,
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Stores a new secret for a user that hasn't enabled TOTP yet.
+///
+/// Returns `false` if TOTP has already been enabled.
+pub async fn set_totp_secret(
+    pool: &PgPool,
+    user_id: &Uuid,
+    secret: &str,
+) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query!(
+        r#"
+            update users set totp_secret = $2, totp_last_step = null
+            where id = $1 and totp_enabled_at is null
+        "#,
+        user_id,
+        secret,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(res.rows_affected() == 1)
+}
+
+/// Enables TOTP with the secret from the enrollment, the confirming code's step counts as used.
+pub async fn enable_totp(
+    pool: &PgPool,
+    user_id: &Uuid,
+    step: i64,
+    recovery_code_hashes: &[String],
+) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query!(
+        r#"
+            update users
+            set totp_enabled_at = $2, totp_last_step = $3, totp_recovery_codes = $4
+            where id = $1 and totp_enabled_at is null and totp_secret is not null
+        "#,
+        user_id,
+        Utc::now(),
+        step,
+        recovery_code_hashes,
+    )
+    .execute(pool)
+    .await?;
+
+    debug!("Enabled TOTP for user_id={}", user_id);
+    Ok(res.rows_affected() == 1)
+}
+
+pub async fn disable_totp(pool: &PgPool, user_id: &Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+            update users
+            set totp_secret = null,
+                totp_enabled_at = null,
+                totp_last_step = null,
+                totp_recovery_codes = '{}'
+            where id = $1
+        "#,
+        user_id,
+    )
+    .execute(pool)
+    .await?;
+
+    debug!("Disabled TOTP for user_id={}", user_id);
+    Ok(())
+}
+
+/// Marks the time step of a TOTP code as used.
+///
+/// Returns `false` if a code of the same or a later step has been used before.
+pub async fn use_totp_step(pool: &PgPool, user_id: &Uuid, step: i64) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query!(
+        r#"
+            update users set totp_last_step = $2
+            where id = $1 and (totp_last_step is null or totp_last_step < $2)
+        "#,
+        user_id,
+        step,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(res.rows_affected() == 1)
+}
+
+/// Removes the recovery code with the given hash, returns `false` if the user doesn't have it.
+pub async fn use_recovery_code(
+    pool: &PgPool,
+    user_id: &Uuid,
+    code_hash: &str,
+) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query!(
+        r#"
+            update users set totp_recovery_codes = array_remove(totp_recovery_codes, $2)
+            where id = $1 and $2 = any(totp_recovery_codes)
+        "#,
+        user_id,
+        code_hash,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(res.rows_affected() == 1)
+}
+
+/// Whether the token of the second login step has been exchanged for a session already.
+pub async fn is_mfa_token_used(pool: &PgPool, jti: &Uuid) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query!(r#" select jti from used_mfa_tokens where jti = $1; "#, jti)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(row.is_some())
+}
+
+/// Marks the token of the second login step as used, returns `false` if it has been used before.
+pub async fn use_mfa_token(
+    pool: &PgPool,
+    jti: &Uuid,
+    expires_at: DateTime<Utc>,
+) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query!(
+        r#"
+            insert into used_mfa_tokens (jti, expires_at) values ($1, $2)
+            on conflict (jti) do nothing
+        "#,
+        jti,
+        expires_at,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(res.rows_affected() == 1)
+}
+
+/// Forgets used tokens of the second login step once they have expired, returns how many.
+pub async fn delete_expired_mfa_tokens(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let res = sqlx::query!(r#" delete from used_mfa_tokens where expires_at <= now(); "#)
+        .execute(pool)
+        .await?;
+
+    Ok(res.rows_affected())
+}
+
+/// The user asking to reset their password, who needs a verified address to get the token.
+#[derive(Debug)]
+pub struct PasswordResetUser {
+    pub id: Uuid,
+    pub username: String,
+    pub email: String,
+}
+
+/// Finds the active user with the username or verified email address, like the login does.
+pub async fn find_password_reset_user(
+    pool: &PgPool,
+    username: &str,
+) -> Result<Option<PasswordResetUser>, sqlx::Error> {
+    sqlx::query_as!(
+        PasswordResetUser,
+        r#"
+            select id, username, email as "email!" from users
+            where case
+                when strpos($1, '@') > 0 then lower(email) = lower($1)
+                else lower(username) = lower($1)
+            end
+            and email_verified_at is not null and disabled_at is null and deleted_at is null
+        "#,
+        username,
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Creates a single-use password reset token, replacing any unused ones of the user.
+///
+/// Returns the token itself and when it expires.
+pub async fn insert_password_reset_token(
+    pool: &PgPool,
+    user_id: &Uuid,
+    ttl: Duration,
+) -> Result<(String, DateTime<Utc>), sqlx::Error> {
+    let token = generate_token();
+    let date = Utc::now();
+    let expires_at = date + ttl;
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        r#" delete from password_reset_tokens where user_id = $1 and used_at is null; "#,
+        user_id,
+    )
+    .execute(&mut tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+            INSERT INTO password_reset_tokens (
+                id,
+                user_id,
+                token_hash,
+                created_at,
+                expires_at
+            ) VALUES ( $1, $2, $3, $4, $5)
+        "#,
+        Uuid::new_v4(),
+        user_id,
+        hash_token(&token),
+        date,
+        expires_at,
+    )
+    .execute(&mut tx)
+    .await?;
+
+    tx.commit().await?;
+
+    debug!("Inserted password reset token for user_id={}", user_id);
+    Ok((token, expires_at))
+}
+
+/// A password reset token that can still be used.
+pub struct PasswordResetToken {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub username: String,
+}
+
+pub async fn find_password_reset_token(
+    pool: &PgPool,
+    token: &str,
+) -> Result<Option<PasswordResetToken>, sqlx::Error> {
+    sqlx::query_as!(
+        PasswordResetToken,
+        r#"
+            select
+                password_reset_tokens.id,
+                password_reset_tokens.user_id,
+                users.username
+            from password_reset_tokens
+            join users on users.id = password_reset_tokens.user_id
+            where password_reset_tokens.token_hash = $1
+            and password_reset_tokens.used_at is null
+            and password_reset_tokens.expires_at > now()
+            and users.disabled_at is null
+            and users.deleted_at is null
+        "#,
+        hash_token(token),
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Uses up the reset token to set a new password and logs the user out everywhere.
+///
+/// Returns `false` if the token has been used in the meantime.
+pub async fn reset_password(
+    pool: &PgPool,
+    reset_token: &PasswordResetToken,
+    hashed_password: &str,
+) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let used = sqlx::query!(
+        r#"
+            update password_reset_tokens set used_at = $2
+            where id = $1 and used_at is null
+        "#,
+        reset_token.id,
+        Utc::now(),
+    )
+    .execute(&mut tx)
+    .await?;
+
+    if used.rows_affected() == 0 {
+        return Ok(false);
+    }
+
+    set_password(&mut tx, &reset_token.user_id, hashed_password).await?;
+    revoke_user_auth_tokens(&mut tx, &reset_token.user_id).await?;
+
+    tx.commit().await?;
+
+    debug!("Reset password of user_id={}", reset_token.user_id);
+    Ok(true)
+}
+
+pub async fn get_user_data(pool: &PgPool, user_id: &Uuid) -> Result<Option<UserData>, sqlx::Error> {
+    sqlx::query_as!(
+        UserData,
+        r#"
+            select
+                id,
+                username,
+                email,
+                email_verified_at is not null as "email_verified!",
+                display_name,
+                bio,
+                avatar_url,
+                locale,
+                timezone
+            from users
+            where id = $1 and deleted_at is null
+        "#,
+        user_id,
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn update_username(
+    pool: &PgPool,
+    user_id: &Uuid,
+    username: &str,
+) -> Result<UserData, Error> {
+    sqlx::query_as!(
+        UserData,
+        r#"
+            update users
+            set username = $2
+            where id = $1 and deleted_at is null
+            returning
+                id,
+                username,
+                email,
+                email_verified_at is not null as "email_verified!",
+                display_name,
+                bio,
+                avatar_url,
+                locale,
+                timezone
+        "#,
+        user_id,
+        username,
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|err| Error::from_unique_violation(err, "username"))
+}
+
+/// Applies a merge patch to the user. Changing the email address resets its verification.
+///
+/// Returns the patched user and whether the email address has changed.
+pub async fn patch_user(
+    pool: &PgPool,
+    user_id: &Uuid,
+    patch: ValidUserPatch,
+) -> Result<Option<(UserData, bool)>, Error> {
+    let ValidUserPatch(patch) = patch;
+    let mut tx = pool.begin().await?;
+
+    let old_email = match sqlx::query!(
+        r#" select email from users where id = $1 and deleted_at is null for update; "#,
+        user_id,
+    )
+    .fetch_optional(&mut tx)
+    .await?
+    {
+        Some(row) => row.email,
+        None => return Ok(None),
+    };
+
+    let email_changed = match &patch.email {
+        Some(email) => {
+            email.as_deref().map(str::to_lowercase) != old_email.as_deref().map(str::to_lowercase)
+        }
+        None => false,
+    };
+
+    // Every field comes with a flag whether it's part of the patch at all.
+    let user = sqlx::query_as!(
+        UserData,
+        r#"
+            update users set
+                username = case when $2 then $3 else username end,
+                email = case when $4 then $5 else email end,
+                email_verified_at = case when $6 then null else email_verified_at end,
+                display_name = case when $7 then $8 else display_name end,
+                bio = case when $9 then $10 else bio end,
+                avatar_url = case when $11 then $12 else avatar_url end,
+                locale = case when $13 then $14 else locale end,
+                timezone = case when $15 then $16 else timezone end,
+                updated_at = $17
+            where id = $1
+            returning
+                id,
+                username,
+                email,
+                email_verified_at is not null as "email_verified!",
+                display_name,
+                bio,
+                avatar_url,
+                locale,
+                timezone
+        "#,
+        user_id,
+        patch.username.is_some(),
+        patch.username.flatten(),
+        patch.email.is_some(),
+        patch.email.clone().flatten(),
+        email_changed,
+        patch.display_name.is_some(),
+        patch.display_name.flatten(),
+        patch.bio.is_some(),
+        patch.bio.flatten(),
+        patch.avatar_url.is_some(),
+        patch.avatar_url.flatten(),
+        patch.locale.is_some(),
+        patch.locale.flatten(),
+        patch.timezone.is_some(),
+        patch.timezone.flatten(),
+        Utc::now(),
+    )
+    .fetch_one(&mut tx)
+    .await
+    .map_err(|err| {
+        error!("Err: {:?}", err);
+        Error::from_unique_violation(err, "username")
+    })?;
+
+    // Links sent to the old address must not verify the new one.
+    if email_changed {
+        sqlx::query!(
+            r#" delete from email_verifications where user_id = $1 and used_at is null; "#,
+            user_id,
+        )
+        .execute(&mut tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    debug!("Patched user_id={}", user_id);
+    Ok(Some((user, email_changed)))
+}
+
+/// Creates a verification of the given address of the user, replacing any unused ones.
+///
+/// Returns its id, which the link token refers to, and when it expires.
+pub async fn insert_email_verification(
+    pool: &PgPool,
+    user_id: &Uuid,
+    email: &str,
+    ttl: Duration,
+) -> Result<(Uuid, DateTime<Utc>), sqlx::Error> {
+    let id = Uuid::new_v4();
+    let date = Utc::now();
+    let expires_at = date + ttl;
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        r#" delete from email_verifications where user_id = $1 and used_at is null; "#,
+        user_id,
+    )
+    .execute(&mut tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+            INSERT INTO email_verifications (
+                id,
+                user_id,
+                email,
+                created_at,
+                expires_at
+            ) VALUES ( $1, $2, $3, $4, $5)
+        "#,
+        id,
+        user_id,
+        email,
+        date,
+        expires_at,
+    )
+    .execute(&mut tx)
+    .await?;
+
+    tx.commit().await?;
+
+    debug!("Inserted email verification for user_id={}", user_id);
+    Ok((id, expires_at))
+}
+
+/// When the last verification mail has been sent to the user, used to rate limit resending.
+pub async fn last_email_verification_at(
+    pool: &PgPool,
+    user_id: &Uuid,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#" select max(created_at) as last_sent_at from email_verifications where user_id = $1; "#,
+        user_id,
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.last_sent_at)
+}
+
+/// Uses up the verification and marks the address as verified. Fails if the verification is
+/// unknown, used or expired, or if the user's address has changed since. Returns a `Conflict` if
+/// another user has verified the address first.
+pub async fn verify_email(
+    pool: &PgPool,
+    verification_id: &Uuid,
+    user_id: &Uuid,
+    email: &str,
+) -> Result<bool, Error> {
+    let date = Utc::now();
+    let mut tx = pool.begin().await?;
+
+    let used = sqlx::query!(
+        r#"
+            update email_verifications
+            set used_at = $4
+            where id = $1 and user_id = $2 and email = $3 and used_at is null and expires_at > $4
+        "#,
+        verification_id,
+        user_id,
+        email,
+        date,
+    )
+    .execute(&mut tx)
+    .await?;
+
+    if used.rows_affected() == 0 {
+        return Ok(false);
+    }
+
+    // Addresses are only unique once verified, somebody else might have been first.
+    let verified = sqlx::query!(
+        r#"
+            update users
+            set email_verified_at = $3, updated_at = $3
+            where id = $1 and lower(email) = lower($2) and deleted_at is null
+        "#,
+        user_id,
+        email,
+        date,
+    )
+    .execute(&mut tx)
+    .await
+    .map_err(|err| Error::from_unique_violation(err, "email"))?;
+
+    if verified.rows_affected() == 0 {
+        return Ok(false);
+    }
+
+    tx.commit().await?;
+
+    debug!("Verified email of user_id={}", user_id);
+    Ok(true)
+}
+
+/// Marks the user as deleted and logs them out everywhere. Their data is kept until it's purged
+/// after the grace period, see `Settings.deletion`.
+///
+/// Returns `false` if the user doesn't exist or has been deleted before.
+pub async fn soft_delete_user(pool: &PgPool, user_id: &Uuid) -> Result<bool, sqlx::Error> {
+    let date = Utc::now();
+    let mut tx = pool.begin().await?;
+
+    let deleted = sqlx::query!(
+        r#"
+            update users set deleted_at = $2, updated_at = $2
+            where id = $1 and deleted_at is null
+        "#,
+        user_id,
+        date,
+    )
+    .execute(&mut tx)
+    .await?;
+
+    if deleted.rows_affected() == 0 {
+        return Ok(false);
+    }
+
+    revoke_user_auth_tokens(&mut tx, user_id).await?;
+
+    tx.commit().await?;
+
+    debug!("Soft deleted user_id={}", user_id);
+    Ok(true)
+}
+
+/// Undoes the deletion of a user, as long as it happened after `deleted_after`.
+///
+/// Returns `false` if the user isn't deleted or the grace period has passed.
+pub async fn restore_user(
+    pool: &PgPool,
+    user_id: &Uuid,
+    deleted_after: DateTime<Utc>,
+) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query!(
+        r#"
+            update users set deleted_at = null, updated_at = $3
+            where id = $1 and deleted_at > $2
+        "#,
+        user_id,
+        deleted_after,
+        Utc::now(),
+    )
+    .execute(pool)
+    .await?;
+
+    if res.rows_affected() == 0 {
+        return Ok(false);
+    }
+
+    debug!("Restored user_id={}", user_id);
+    Ok(true)
+}
+
+// Deletes the user with everything that refers to it.
+async fn delete_user_rows(
+    tx: &mut Transaction<'_, Postgres>,
+    user_id: &Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(r#" delete from auth_tokens where user_id = $1; "#, user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query!(
+        r#" delete from password_reset_tokens where user_id = $1; "#,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(r#" delete from api_keys where user_id = $1; "#, user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query!(
+        r#" delete from email_verifications where user_id = $1; "#,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#" delete from oauth_authorization_codes where user_id = $1; "#,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(r#" delete from data_exports where user_id = $1; "#, user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query!(r#" delete from users where id = $1; "#, user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    // Audit events outlive the account, but only under its id.
+    sqlx::query!(
+        r#"
+            update audit_events set details = details - 'username' - 'email'
+            where subject_id = $1 and (details ? 'username' or details ? 'email');
+        "#,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    Ok(())
+}
+
+/// Deletes the user right away, whether soft deleted or not.
+///
+/// Returns `false` if the user doesn't exist.
+pub async fn delete_user(pool: &PgPool, user_id: &Uuid) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let exists = sqlx::query!(
+        r#" select id from users where id = $1 for update; "#,
+        user_id
+    )
+    .fetch_optional(&mut tx)
+    .await?
+    .is_some();
+
+    if !exists {
+        return Ok(false);
+    }
+
+    delete_user_rows(&mut tx, user_id).await?;
+
+    tx.commit().await?;
+
+    debug!("Deleted user_id={}", user_id);
+    Ok(true)
+}
+
+/// Deletes all users that were soft deleted before `deleted_before`, each in its own transaction.
+///
+/// Returns the number of purged users.
+pub async fn purge_deleted_users(
+    pool: &PgPool,
+    deleted_before: DateTime<Utc>,
+) -> Result<u64, sqlx::Error> {
+    let ids = sqlx::query!(
+        r#" select id from users where deleted_at <= $1; "#,
+        deleted_before
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut purged = 0;
+    for row in ids {
+        let mut tx = pool.begin().await?;
+
+        // The user might have been restored in the meantime.
+        let expired = sqlx::query!(
+            r#" select id from users where id = $1 and deleted_at <= $2 for update; "#,
+            row.id,
+            deleted_before,
+        )
+        .fetch_optional(&mut tx)
+        .await?
+        .is_some();
+
+        if !expired {
+            continue;
+        }
+
+        delete_user_rows(&mut tx, &row.id).await?;
+        tx.commit().await?;
+
+        debug!("Purged user_id={}", row.id);
+        purged += 1;
+    }
+
+    Ok(purged)
+}
+
+pub async fn list_users(
+    pool: &PgPool,
+    limit: i64,
+    offset: i64,
+) -> Result<(Vec<AdminUserData>, i64), sqlx::Error> {
+    let users = sqlx::query_as!(
+        AdminUserData,
+        r#"
+            select id, username, role, created_at, updated_at, disabled_at, password_reset_required
+            from users
+            where deleted_at is null
+            order by created_at, id
+            limit $1 offset $2
+        "#,
+        limit,
+        offset,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let count =
+        sqlx::query!(r#" select count(*) as "count!" from users where deleted_at is null; "#)
+            .fetch_one(pool)
+            .await?;
+
+    Ok((users, count.count))
+}
+
+pub async fn get_admin_user_data(
+    pool: &PgPool,
+    user_id: &Uuid,
+) -> Result<Option<AdminUserData>, sqlx::Error> {
+    sqlx::query_as!(
+        AdminUserData,
+        r#"
+            select id, username, role, created_at, updated_at, disabled_at, password_reset_required
+            from users
+            where id = $1 and deleted_at is null
+        "#,
+        user_id,
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn set_user_disabled(
+    pool: &PgPool,
+    user_id: &Uuid,
+    disabled: bool,
+) -> Result<Option<AdminUserData>, sqlx::Error> {
+    let date = Utc::now();
+    let disabled_at = if disabled { Some(date) } else { None };
+
+    sqlx::query_as!(
+        AdminUserData,
+        r#"
+            update users
+            set disabled_at = $2, updated_at = $3
+            where id = $1 and deleted_at is null
+            returning id, username, role, created_at, updated_at, disabled_at, password_reset_required
+        "#,
+        user_id,
+        disabled_at,
+        date,
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// The current role of the user, which may differ from the one in their token.
+pub async fn get_user_role(pool: &PgPool, user_id: &Uuid) -> Result<Option<Role>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#" select role from users where id = $1 and deleted_at is null; "#,
+        user_id,
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|row| Role::from_str(&row.role)))
+}
+
+pub async fn set_user_role(
+    pool: &PgPool,
+    user_id: &Uuid,
+    role: Role,
+) -> Result<Option<AdminUserData>, sqlx::Error> {
+    sqlx::query_as!(
+        AdminUserData,
+        r#"
+            update users
+            set role = $2, updated_at = $3
+            where id = $1 and deleted_at is null
+            returning id, username, role, created_at, updated_at, disabled_at, password_reset_required
+        "#,
+        user_id,
+        role.to_string(),
+        Utc::now(),
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn set_password_reset_required(
+    pool: &PgPool,
+    user_id: &Uuid,
+    required: bool,
+) -> Result<Option<AdminUserData>, sqlx::Error> {
+    sqlx::query_as!(
+        AdminUserData,
+        r#"
+            update users
+            set password_reset_required = $2, updated_at = $3
+            where id = $1 and deleted_at is null
+            returning id, username, role, created_at, updated_at, disabled_at, password_reset_required
+        "#,
+        user_id,
+        required,
+        Utc::now(),
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+// Marks keys so that they can be told apart from other secrets, e.g. by secret scanners.
+const API_KEY_PREFIX: &str = "alx_";
+
+/// Creates an API key, returning the key itself, of which only the hash is stored.
+pub async fn insert_api_key(
+    pool: &PgPool,
+    user_id: &Uuid,
+    name: &str,
+    scopes: &[String],
+    expires_at: Option<DateTime<Utc>>,
+) -> Result<(String, ApiKeyEntry), sqlx::Error> {
+    let key = format!("{}{}", API_KEY_PREFIX, generate_token());
+    let prefix = &key[..API_KEY_PREFIX.len() + 8];
+
+    let entry = sqlx::query_as!(
+        ApiKeyEntry,
+        r#"
+            insert into api_keys (id, user_id, name, prefix, key_hash, scopes, created_at, expires_at)
+            values ($1, $2, $3, $4, $5, $6, $7, $8)
+            returning id, name, prefix, scopes, created_at, expires_at, last_used_at
+        "#,
+        Uuid::new_v4(),
+        user_id,
+        name,
+        prefix,
+        hash_token(&key),
+        scopes,
+        Utc::now(),
+        expires_at,
+    )
+    .fetch_one(pool)
+    .await?;
+
+    debug!("Created api_key_id={} of user_id={}", entry.id, user_id);
+    Ok((key, entry))
+}
+
+/// Lists the API keys of a user which are neither revoked nor expired.
+pub async fn list_api_keys(pool: &PgPool, user_id: &Uuid) -> Result<Vec<ApiKeyEntry>, sqlx::Error> {
+    sqlx::query_as!(
+        ApiKeyEntry,
+        r#"
+            select id, name, prefix, scopes, created_at, expires_at, last_used_at
+            from api_keys
+            where user_id = $1
+            and revoked_at is null
+            and (expires_at is null or expires_at > now())
+            order by created_at, id
+        "#,
+        user_id,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Returns whether the key existed and wasn't revoked before.
+pub async fn revoke_api_key(
+    pool: &PgPool,
+    user_id: &Uuid,
+    key_id: &Uuid,
+) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query!(
+        r#"
+            update api_keys set revoked_at = $3
+            where id = $1 and user_id = $2 and revoked_at is null
+        "#,
+        key_id,
+        user_id,
+        Utc::now(),
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(res.rows_affected() > 0)
+}
+
+// The owner of a valid API key, as needed to authenticate a request.
+#[derive(Debug)]
+pub struct ApiKeyOwner {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub role: String,
+    pub scopes: Vec<String>,
+}
+
+/// Looks up a key that is neither revoked nor expired and whose user isn't disabled or deleted,
+/// marking it as used.
+pub async fn authenticate_api_key(
+    pool: &PgPool,
+    key: &str,
+) -> Result<Option<ApiKeyOwner>, sqlx::Error> {
+    sqlx::query_as!(
+        ApiKeyOwner,
+        r#"
+            update api_keys
+            set last_used_at = now()
+            from users
+            where users.id = api_keys.user_id
+            and api_keys.key_hash = $1
+            and api_keys.revoked_at is null
+            and (api_keys.expires_at is null or api_keys.expires_at > now())
+            and users.disabled_at is null
+            and users.deleted_at is null
+            returning api_keys.id, api_keys.user_id, users.role, api_keys.scopes
+        "#,
+        hash_token(key),
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Registers an OAuth client, with a secret for confidential clients of which only the hash is
+/// stored.
+pub async fn insert_oauth_client(
+    pool: &PgPool,
+    name: &str,
+    redirect_uris: &[String],
+    confidential: bool,
+) -> Result<(Option<String>, OAuthClientEntry), sqlx::Error> {
+    let secret = if confidential {
+        Some(generate_token())
+    } else {
+        None
+    };
+
+    let entry = sqlx::query_as!(
+        OAuthClientEntry,
+        r#"
+            insert into oauth_clients (id, name, secret_hash, redirect_uris, created_at)
+            values ($1, $2, $3, $4, $5)
+            returning id, name, secret_hash, redirect_uris, created_at
+        "#,
+        Uuid::new_v4().to_simple().to_string(),
+        name,
+        secret.as_deref().map(hash_token),
+        redirect_uris,
+        Utc::now(),
+    )
+    .fetch_one(pool)
+    .await?;
+
+    debug!("Registered oauth client_id={}", entry.id);
+    Ok((secret, entry))
+}
+
+pub async fn get_oauth_client(
+    pool: &PgPool,
+    client_id: &str,
+) -> Result<Option<OAuthClientEntry>, sqlx::Error> {
+    sqlx::query_as!(
+        OAuthClientEntry,
+        r#"
+            select id, name, secret_hash, redirect_uris, created_at
+            from oauth_clients
+            where id = $1
+        "#,
+        client_id,
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn list_oauth_clients(pool: &PgPool) -> Result<Vec<OAuthClientEntry>, sqlx::Error> {
+    sqlx::query_as!(
+        OAuthClientEntry,
+        r#"
+            select id, name, secret_hash, redirect_uris, created_at
+            from oauth_clients
+            order by created_at, id
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Returns whether the client existed. Tokens already handed out stay valid until they expire.
+pub async fn delete_oauth_client(pool: &PgPool, client_id: &str) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        r#" delete from oauth_authorization_codes where client_id = $1; "#,
+        client_id
+    )
+    .execute(&mut tx)
+    .await?;
+
+    let res = sqlx::query!(r#" delete from oauth_clients where id = $1; "#, client_id)
+        .execute(&mut tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(res.rows_affected() > 0)
+}
+
+// What an authorization code has been issued for.
+#[derive(Debug)]
+pub struct AuthorizationCode {
+    pub client_id: String,
+    pub user_id: Uuid,
+    pub redirect_uri: String,
+    pub scope: String,
+    pub nonce: Option<String>,
+    pub code_challenge: String,
+}
+
+/// Creates a single-use authorization code, returning the code, of which only the hash is stored.
+pub async fn insert_authorization_code(
+    pool: &PgPool,
+    grant: &AuthorizationCode,
+    ttl: Duration,
+) -> Result<String, sqlx::Error> {
+    let code = generate_token();
+    let now = Utc::now();
+
+    sqlx::query!(
+        r#"
+            insert into oauth_authorization_codes (
+                id, code_hash, client_id, user_id, redirect_uri, scope, nonce, code_challenge,
+                created_at, expires_at
+            )
+            values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        "#,
+        Uuid::new_v4(),
+        hash_token(&code),
+        grant.client_id,
+        grant.user_id,
+        grant.redirect_uri,
+        grant.scope,
+        grant.nonce,
+        grant.code_challenge,
+        now,
+        now + ttl,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(code)
+}
+
+/// Marks an unexpired authorization code as used, returning what it has been issued for if it
+/// hasn't been used before.
+pub async fn use_authorization_code(
+    pool: &PgPool,
+    code: &str,
+) -> Result<Option<AuthorizationCode>, sqlx::Error> {
+    sqlx::query_as!(
+        AuthorizationCode,
+        r#"
+            update oauth_authorization_codes
+            set used_at = $2
+            where code_hash = $1 and used_at is null and expires_at > $2
+            returning client_id, user_id, redirect_uri, scope, nonce, code_challenge
+        "#,
+        hash_token(code),
+        Utc::now(),
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Collects everything stored about the user, leaving out password hashes, secrets and tokens.
+pub async fn get_user_export(
+    pool: &PgPool,
+    user_id: &Uuid,
+) -> Result<Option<UserExport>, sqlx::Error> {
+    let user = match sqlx::query_as!(
+        UserExportEntry,
+        r#"
+            select
+                id,
+                username,
+                created_at,
+                updated_at,
+                role,
+                disabled_at,
+                password_reset_required,
+                totp_enabled_at,
+                email,
+                email_verified_at,
+                display_name,
+                bio,
+                avatar_url,
+                locale,
+                timezone,
+                deleted_at
+            from users
+            where id = $1 and deleted_at is null
+        "#,
+        user_id,
+    )
+    .fetch_optional(pool)
+    .await?
+    {
+        Some(user) => user,
+        None => return Ok(None),
+    };
+
+    let sessions = sqlx::query_as!(
+        SessionExport,
+        r#"
+            select id, family_id, created_at, expires_at, revoked_at
+            from auth_tokens
+            where user_id = $1
+            order by created_at, id
+        "#,
+        user_id,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let api_keys = sqlx::query_as!(
+        ApiKeyEntry,
+        r#"
+            select id, name, prefix, scopes, created_at, expires_at, last_used_at
+            from api_keys
+            where user_id = $1
+            order by created_at, id
+        "#,
+        user_id,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let email_verifications = sqlx::query_as!(
+        EmailVerificationExport,
+        r#"
+            select email, created_at, expires_at, used_at
+            from email_verifications
+            where user_id = $1
+            order by created_at
+        "#,
+        user_id,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let password_resets = sqlx::query_as!(
+        PasswordResetExport,
+        r#"
+            select created_at, expires_at, used_at
+            from password_reset_tokens
+            where user_id = $1
+            order by created_at
+        "#,
+        user_id,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let oauth_authorizations = sqlx::query_as!(
+        OAuthAuthorizationExport,
+        r#"
+            select
+                oauth_authorization_codes.client_id,
+                oauth_clients.name as client_name,
+                oauth_authorization_codes.scope,
+                oauth_authorization_codes.created_at,
+                oauth_authorization_codes.used_at
+            from oauth_authorization_codes
+            join oauth_clients on oauth_clients.id = oauth_authorization_codes.client_id
+            where oauth_authorization_codes.user_id = $1
+            order by oauth_authorization_codes.created_at
+        "#,
+        user_id,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    // Events caused by somebody else, e.g. admins or failed logins, don't reveal where they came
+    // from.
+    let audit_events = sqlx::query_as!(
+        AuditEventData,
+        r#"
+            select
+                id,
+                actor_id,
+                subject_id,
+                action,
+                case when actor_id is distinct from subject_id then null else ip end as ip,
+                case when actor_id is distinct from subject_id then null else user_agent end
+                    as user_agent,
+                request_id,
+                details,
+                created_at
+            from audit_events
+            where actor_id = $1 or subject_id = $1
+            order by created_at, id
+        "#,
+        user_id,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(Some(UserExport {
+        exported_at: Utc::now(),
+        user,
+        sessions,
+        api_keys: api_keys.into_iter().map(ApiKeyData::from).collect(),
+        email_verifications,
+        password_resets,
+        oauth_authorizations,
+        audit_events,
+    }))
+}
+
+/// The number of rows an export of the user would contain, used to decide whether to run it in
+/// the background.
+pub async fn count_user_export_records(pool: &PgPool, user_id: &Uuid) -> Result<i64, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+            select
+                (select count(*) from auth_tokens where user_id = $1)
+                + (select count(*) from api_keys where user_id = $1)
+                + (select count(*) from email_verifications where user_id = $1)
+                + (select count(*) from password_reset_tokens where user_id = $1)
+                + (select count(*) from oauth_authorization_codes where user_id = $1)
+                + (select count(*) from audit_events where actor_id = $1 or subject_id = $1)
+                as "count!"
+        "#,
+        user_id,
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.count)
+}
+
+/// Creates a pending export of the user, unless one has been started after `stale_before`, which
+/// is returned instead. Older pending exports are marked as failed.
+///
+/// Returns the export and whether it has just been created.
+pub async fn insert_data_export(
+    pool: &PgPool,
+    user_id: &Uuid,
+    ttl: Duration,
+    stale_before: DateTime<Utc>,
+) -> Result<(DataExportEntry, bool), sqlx::Error> {
+    let date = Utc::now();
+    let mut tx = pool.begin().await?;
+
+    // Serializes concurrent requests of the same user.
+    sqlx::query!(
+        r#" select id from users where id = $1 for update; "#,
+        user_id
+    )
+    .fetch_one(&mut tx)
+    .await?;
+
+    fail_stale_data_exports(&mut tx, Some(user_id), stale_before).await?;
+
+    let pending = sqlx::query_as!(
+        DataExportEntry,
+        r#"
+            select id, user_id, status, created_at, completed_at, expires_at
+            from data_exports
+            where user_id = $1 and status = $2 and expires_at > $3
+        "#,
+        user_id,
+        DataExportStatus::Pending.as_str(),
+        date,
+    )
+    .fetch_optional(&mut tx)
+    .await?;
+
+    if let Some(pending) = pending {
+        return Ok((pending, false));
+    }
+
+    let entry = sqlx::query_as!(
+        DataExportEntry,
+        r#"
+            insert into data_exports (id, user_id, status, created_at, expires_at)
+            values ($1, $2, $3, $4, $5)
+            returning id, user_id, status, created_at, completed_at, expires_at
+        "#,
+        Uuid::new_v4(),
+        user_id,
+        DataExportStatus::Pending.as_str(),
+        date,
+        date + ttl,
+    )
+    .fetch_one(&mut tx)
+    .await?;
+
+    tx.commit().await?;
+
+    debug!(
+        "Inserted data_export_id={} of user_id={}",
+        entry.id, user_id
+    );
+    Ok((entry, true))
+}
+
+/// Stores the data of a finished export, which can be downloaded for `ttl` from now on.
+pub async fn complete_data_export(
+    pool: &PgPool,
+    export_id: &Uuid,
+    data: serde_json::Value,
+    ttl: Duration,
+) -> Result<(), sqlx::Error> {
+    let date = Utc::now();
+
+    sqlx::query!(
+        r#"
+            update data_exports
+            set status = $2, data = $3, completed_at = $4, expires_at = $5
+            where id = $1
+        "#,
+        export_id,
+        DataExportStatus::Ready.as_str(),
+        data,
+        date,
+        date + ttl,
+    )
+    .execute(pool)
+    .await?;
+
+    debug!("Completed data_export_id={}", export_id);
+    Ok(())
+}
+
+pub async fn fail_data_export(pool: &PgPool, export_id: &Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#" update data_exports set status = $2, completed_at = $3 where id = $1; "#,
+        export_id,
+        DataExportStatus::Failed.as_str(),
+        Utc::now(),
+    )
+    .execute(pool)
+    .await?;
+
+    debug!("Failed data_export_id={}", export_id);
+    Ok(())
+}
+
+/// Marks exports that are still pending since before `stale_before` as failed, of the given user
+/// or of everyone. Their tasks are either stuck or have been lost when the app stopped.
+pub async fn fail_stale_data_exports<'e, E>(
+    executor: E,
+    user_id: Option<&Uuid>,
+    stale_before: DateTime<Utc>,
+) -> Result<u64, sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let res = sqlx::query!(
+        r#"
+            update data_exports
+            set status = $3, completed_at = now()
+            where status = $2 and created_at < $4
+            and ($1::uuid is null or user_id = $1)
+        "#,
+        user_id,
+        DataExportStatus::Pending.as_str(),
+        DataExportStatus::Failed.as_str(),
+        stale_before,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(res.rows_affected())
+}
+
+/// Looks up an unexpired export of the user.
+pub async fn get_data_export(
+    pool: &PgPool,
+    user_id: &Uuid,
+    export_id: &Uuid,
+) -> Result<Option<DataExportEntry>, sqlx::Error> {
+    sqlx::query_as!(
+        DataExportEntry,
+        r#"
+            select id, user_id, status, created_at, completed_at, expires_at
+            from data_exports
+            where id = $1 and user_id = $2 and expires_at > now()
+        "#,
+        export_id,
+        user_id,
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// The data of a finished, unexpired export of a user that hasn't been deleted.
+pub async fn get_data_export_data(
+    pool: &PgPool,
+    user_id: &Uuid,
+    export_id: &Uuid,
+) -> Result<Option<serde_json::Value>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+            select data_exports.data
+            from data_exports
+            join users on users.id = data_exports.user_id
+            where data_exports.id = $1
+            and data_exports.user_id = $2
+            and data_exports.status = $3
+            and data_exports.expires_at > now()
+            and users.deleted_at is null
+        "#,
+        export_id,
+        user_id,
+        DataExportStatus::Ready.as_str(),
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.and_then(|row| row.data))
+}
+
+/// Removes expired exports along with their data, returns how many there were.
+pub async fn delete_expired_data_exports(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let res = sqlx::query!(r#" delete from data_exports where expires_at <= now(); "#)
+        .execute(pool)
+        .await?;
+
+    Ok(res.rows_affected())
+}
+
+pub async fn insert_audit_event(
+    pool: &PgPool,
+    origin: &AuditOrigin,
+    action: AuditAction,
+    actor_id: Option<Uuid>,
+    subject_id: Option<Uuid>,
+    details: serde_json::Value,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+            insert into audit_events (
+                id, actor_id, subject_id, action, ip, user_agent, request_id, details, created_at
+            )
+            values ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        "#,
+        Uuid::new_v4(),
+        actor_id,
+        subject_id,
+        action.as_str(),
+        origin.ip.map(|ip| ip.to_string()),
+        origin.user_agent,
+        origin.request_id,
+        details,
+        Utc::now(),
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Lists audit events newest first, starting after the event at `after`.
+pub async fn list_audit_events(
+    pool: &PgPool,
+    user_id: Option<Uuid>,
+    action: Option<AuditAction>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    after: Option<(DateTime<Utc>, Uuid)>,
+    limit: i64,
+) -> Result<Vec<AuditEventData>, sqlx::Error> {
+    let (after_created_at, after_id) = after.unzip();
+
+    sqlx::query_as!(
+        AuditEventData,
+        r#"
+            select
+                id, actor_id, subject_id, action, ip, user_agent, request_id, details, created_at
+            from audit_events
+            where ($1::uuid is null or actor_id = $1 or subject_id = $1)
+            and ($2::varchar is null or action = $2)
+            and ($3::timestamptz is null or created_at >= $3)
+            and ($4::timestamptz is null or created_at < $4)
+            and ($5::timestamptz is null or (created_at, id) < ($5, $6))
+            order by created_at desc, id desc
+            limit $7
+        "#,
+        user_id,
+        action.map(|action| action.as_str()),
+        from,
+        to,
+        after_created_at,
+        after_id,
+        limit,
+    )
+    .fetch_all(pool)
+    .await
+}
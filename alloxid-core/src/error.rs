@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+
+/// Errors of the domain logic. Every service maps them onto the errors of its protocol.
+#[derive(Clone, Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Failed to create token")]
+    TokenCreationError,
+    #[error("Failed to extract token")]
+    TokenExtractionError,
+
+    #[error("Not found")]
+    NotFound,
+
+    #[error("Unauthorized")]
+    Unauthorized,
+    // The reason is only told to whoever proved to own the account.
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    #[error("Validation failed")]
+    Validation(Vec<FieldError>),
+
+    #[error("Conflict")]
+    Conflict(FieldError),
+
+    #[error("Too many requests")]
+    TooManyRequests { retry_after_seconds: u64 },
+
+    #[error("Service unavailable: {0}")]
+    Unavailable(String),
+
+    #[error("Configuration error: {0}")]
+    Config(String),
+    #[error("Database error: {0}")]
+    Database(String),
+    #[error("Hashing error: {0}")]
+    Hashing(String),
+    #[error("Token library error: {0}")]
+    Jwt(String),
+}
+
+/// A single rule violation of a request field.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    // Machine readable reason, e.g. `too_short`.
+    pub code: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, code: &str, message: impl Into<String>) -> Self {
+        Self {
+            field: field.to_string(),
+            code: code.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl Error {
+    /// Turns a violated unique constraint into a `Conflict` on the given field.
+    pub fn from_unique_violation(err: sqlx::Error, field: &str) -> Self {
+        match &err {
+            sqlx::Error::Database(db_err) if db_err.code().as_deref() == Some(UNIQUE_VIOLATION) => {
+                Self::Conflict(FieldError::new(
+                    field,
+                    "taken",
+                    format!("This {} is already taken.", field),
+                ))
+            }
+            _ => err.into(),
+        }
+    }
+}
+
+// Postgres error code for `unique_violation`.
+const UNIQUE_VIOLATION: &str = "23505";
+
+impl From<argonautica::Error> for Error {
+    fn from(err: argonautica::Error) -> Self {
+        Self::Hashing(err.to_string())
+    }
+}
+
+impl From<config::ConfigError> for Error {
+    fn from(err: config::ConfigError) -> Self {
+        Self::Config(err.to_string())
+    }
+}
+
+impl From<jsonwebtoken::errors::Error> for Error {
+    fn from(err: jsonwebtoken::errors::Error) -> Self {
+        Self::Jwt(err.to_string())
+    }
+}
+
+impl From<sqlx::Error> for Error {
+    fn from(err: sqlx::Error) -> Self {
+        match err {
+            sqlx::Error::RowNotFound => Self::NotFound,
+            sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed | sqlx::Error::Io(_) => {
+                Self::Unavailable(err.to_string())
+            }
+            _ => Self::Database(err.to_string()),
+        }
+    }
+}
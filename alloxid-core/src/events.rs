@@ -0,0 +1,78 @@
+use chrono::prelude::*;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::model::audit::AuditAction;
+
+/// The lifecycle of a user, as far as it's announced to watchers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UserEventKind {
+    Created,
+    Updated,
+    Deleted,
+    LoggedIn,
+}
+
+impl UserEventKind {
+    /// The kind of event an audited action announces, if any.
+    pub fn from_audit(action: AuditAction) -> Option<Self> {
+        match action {
+            AuditAction::UserCreated => Some(Self::Created),
+            AuditAction::UsernameChanged | AuditAction::ProfileUpdated => Some(Self::Updated),
+            AuditAction::UserDeleted | AuditAction::UserPurged => Some(Self::Deleted),
+            AuditAction::Login => Some(Self::LoggedIn),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct UserEvent {
+    pub kind: UserEventKind,
+    pub user_id: Uuid,
+    // Who caused the event, not necessarily the user themselves.
+    pub actor_id: Option<Uuid>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+/// Broadcasts user events to everyone subscribed within this process.
+///
+/// Subscribers that fall behind by more than `Settings.grpc.event_capacity` events miss the
+/// oldest ones. Nothing is kept for later, events published without subscribers are dropped.
+#[derive(Clone, Debug)]
+pub struct UserEvents {
+    sender: broadcast::Sender<UserEvent>,
+}
+
+impl UserEvents {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender }
+    }
+
+    pub fn publish(&self, event: UserEvent) {
+        // Only fails if nobody is subscribed, which is fine.
+        let _ = self.sender.send(event);
+    }
+
+    /// Publishes the event announced by an audited action, if there is one.
+    pub fn publish_audited(
+        &self,
+        action: AuditAction,
+        actor_id: Option<Uuid>,
+        subject_id: Option<Uuid>,
+    ) {
+        if let (Some(kind), Some(user_id)) = (UserEventKind::from_audit(action), subject_id) {
+            self.publish(UserEvent {
+                kind,
+                user_id,
+                actor_id,
+                occurred_at: Utc::now(),
+            });
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<UserEvent> {
+        self.sender.subscribe()
+    }
+}
@@ -0,0 +1,16 @@
+//! The domain logic shared by the `alloxid` services: settings, models, the database, password
+//! hashing and the JWTs users authenticate with.
+
+pub mod audit;
+pub mod auth;
+pub mod database;
+pub mod error;
+pub mod events;
+pub mod model;
+pub mod password;
+pub mod settings;
+pub mod validation;
+
+pub use error::{Error, FieldError};
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
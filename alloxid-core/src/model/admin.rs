@@ -0,0 +1,63 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::auth::Role;
+
+const DEFAULT_PER_PAGE: i64 = 20;
+const MAX_PER_PAGE: i64 = 100;
+// Far beyond any real list, keeps the offset well within an i64.
+const MAX_PAGE: i64 = 1_000_000;
+
+// Query parameters of paginated endpoints.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Pagination {
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
+
+impl Pagination {
+    pub fn page(&self) -> i64 {
+        self.page.unwrap_or(1).max(1)
+    }
+
+    pub fn per_page(&self) -> i64 {
+        self.per_page
+            .unwrap_or(DEFAULT_PER_PAGE)
+            .clamp(1, MAX_PER_PAGE)
+    }
+
+    /// The number of rows to skip, `None` for pages beyond `MAX_PAGE`.
+    pub fn offset(&self) -> Option<i64> {
+        Some(self.page())
+            .filter(|page| *page <= MAX_PAGE)
+            .and_then(|page| (page - 1).checked_mul(self.per_page()))
+    }
+}
+
+// A single page of a paginated list.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub page: i64,
+    pub per_page: i64,
+    pub total: i64,
+}
+
+// The user data as seen by admins.
+#[derive(sqlx::FromRow, Debug, Deserialize, Serialize)]
+pub struct AdminUserData {
+    pub id: Uuid,
+    pub username: String,
+    pub role: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub disabled_at: Option<DateTime<Utc>>,
+    pub password_reset_required: bool,
+}
+
+// Input to the role endpoint.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RoleUpdateRaw {
+    pub role: Role,
+}
@@ -0,0 +1,65 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::auth::Scope;
+
+// Input to the API key create endpoint.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ApiKeyCreateRaw {
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    // Keys without expiry stay valid until they are revoked.
+    pub expires_in_days: Option<i64>,
+}
+
+// An API key as listed to its user, without the key itself.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ApiKeyData {
+    pub id: Uuid,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<Scope>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+// Returned by the API key create endpoint, the key is shown only once.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ApiKeyCreatedData {
+    pub key: String,
+    #[serde(flatten)]
+    pub api_key: ApiKeyData,
+}
+
+// A row of the api_keys table.
+#[derive(sqlx::FromRow, Debug)]
+pub struct ApiKeyEntry {
+    pub id: Uuid,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+impl From<ApiKeyEntry> for ApiKeyData {
+    fn from(entry: ApiKeyEntry) -> Self {
+        Self {
+            id: entry.id,
+            name: entry.name,
+            prefix: entry.prefix,
+            // Unknown scopes can only stem from a removed scope, which doesn't grant anything anymore.
+            scopes: entry
+                .scopes
+                .iter()
+                .filter_map(|scope| Scope::from_str(scope))
+                .collect(),
+            created_at: entry.created_at,
+            expires_at: entry.expires_at,
+            last_used_at: entry.last_used_at,
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use std::fmt;
+use std::net::IpAddr;
+
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+const DEFAULT_LIMIT: i64 = 50;
+const MAX_LIMIT: i64 = 200;
+// Longer user agents are cut off, they are only kept for reference.
+const USER_AGENT_MAX_LENGTH: usize = 512;
+
+// Declares `AuditAction` along with the name of each action, which is used both in the API and
+// in the `action` column.
+macro_rules! audit_actions {
+    ($($variant:ident => $name:literal,)*) => {
+        /// What happened in an audit event.
+        #[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+        pub enum AuditAction {
+            $(
+                #[serde(rename = $name)]
+                $variant,
+            )*
+        }
+
+        impl AuditAction {
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $(Self::$variant => $name,)*
+                }
+            }
+        }
+    };
+}
+
+audit_actions! {
+    UserCreated => "user.created",
+    Login => "user.login",
+    LoginFailed => "user.login_failed",
+    MfaFailed => "user.mfa_failed",
+    Logout => "user.logout",
+    RefreshTokenReused => "user.refresh_token_reused",
+    UsernameChanged => "user.username_changed",
+    ProfileUpdated => "user.profile_updated",
+    UserDeleted => "user.deleted",
+    UserRestored => "user.restored",
+    PasswordChanged => "user.password_changed",
+    PasswordResetRequested => "user.password_reset_requested",
+    PasswordReset => "user.password_reset",
+    EmailVerificationSent => "user.email_verification_sent",
+    EmailVerified => "user.email_verified",
+    TotpEnrollmentStarted => "user.totp_enrollment_started",
+    TotpEnabled => "user.totp_enabled",
+    TotpDisabled => "user.totp_disabled",
+    ApiKeyCreated => "user.api_key_created",
+    ApiKeyRevoked => "user.api_key_revoked",
+    DataExported => "user.data_exported",
+    DataExportDownloaded => "user.data_export_downloaded",
+    OAuthAuthorized => "oauth.authorized",
+    UserDisabled => "admin.user_disabled",
+    UserEnabled => "admin.user_enabled",
+    RoleChanged => "admin.role_changed",
+    PasswordResetRequired => "admin.password_reset_required",
+    UserPurged => "admin.user_purged",
+    OAuthClientCreated => "admin.oauth_client_created",
+    OAuthClientDeleted => "admin.oauth_client_deleted",
+}
+
+impl fmt::Display for AuditAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+// Where a request came from, recorded along with every audit event.
+#[derive(Clone, Debug, Default)]
+pub struct AuditOrigin {
+    pub ip: Option<IpAddr>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<Uuid>,
+}
+
+impl AuditOrigin {
+    pub fn new(ip: Option<IpAddr>, user_agent: Option<&str>, request_id: Option<Uuid>) -> Self {
+        Self {
+            ip,
+            user_agent: user_agent.map(|agent| agent.chars().take(USER_AGENT_MAX_LENGTH).collect()),
+            request_id,
+        }
+    }
+}
+
+// A row of the audit_events table.
+#[derive(sqlx::FromRow, Debug, Deserialize, Serialize)]
+pub struct AuditEventData {
+    pub id: Uuid,
+    pub actor_id: Option<Uuid>,
+    pub subject_id: Option<Uuid>,
+    pub action: String,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<Uuid>,
+    pub details: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+// Query parameters of the audit log endpoint, all filters are optional.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct AuditQuery {
+    // Events the user either did or that have been done to them.
+    pub user_id: Option<Uuid>,
+    pub action: Option<AuditAction>,
+    // Inclusive start and exclusive end of the time range.
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    // The `next_cursor` of the previous page.
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+impl AuditQuery {
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+    }
+}
+
+// A page of audit events, newest first.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AuditPage {
+    pub items: Vec<AuditEventData>,
+    // Passed as `cursor` to get the next page, missing on the last page.
+    pub next_cursor: Option<String>,
+}
@@ -0,0 +1,131 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::model::api_key::ApiKeyData;
+use crate::model::audit::AuditEventData;
+
+// Everything stored about a user, as handed out by the export endpoints.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UserExport {
+    pub exported_at: DateTime<Utc>,
+    pub user: UserExportEntry,
+    pub sessions: Vec<SessionExport>,
+    pub api_keys: Vec<ApiKeyData>,
+    pub email_verifications: Vec<EmailVerificationExport>,
+    pub password_resets: Vec<PasswordResetExport>,
+    pub oauth_authorizations: Vec<OAuthAuthorizationExport>,
+    // Events the user either did or that have been done to them.
+    pub audit_events: Vec<AuditEventData>,
+}
+
+// The row of the users table, without the password hash and TOTP secrets.
+#[derive(sqlx::FromRow, Debug, Deserialize, Serialize)]
+pub struct UserExportEntry {
+    pub id: Uuid,
+    pub username: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub role: String,
+    pub disabled_at: Option<DateTime<Utc>>,
+    pub password_reset_required: bool,
+    pub totp_enabled_at: Option<DateTime<Utc>>,
+    pub email: Option<String>,
+    pub email_verified_at: Option<DateTime<Utc>>,
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub avatar_url: Option<String>,
+    pub locale: Option<String>,
+    pub timezone: Option<String>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+// A refresh token, without the token itself.
+#[derive(sqlx::FromRow, Debug, Deserialize, Serialize)]
+pub struct SessionExport {
+    pub id: Uuid,
+    pub family_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(sqlx::FromRow, Debug, Deserialize, Serialize)]
+pub struct EmailVerificationExport {
+    pub email: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+}
+
+#[derive(sqlx::FromRow, Debug, Deserialize, Serialize)]
+pub struct PasswordResetExport {
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+}
+
+// An authorization code handed out to an OAuth client on behalf of the user.
+#[derive(sqlx::FromRow, Debug, Deserialize, Serialize)]
+pub struct OAuthAuthorizationExport {
+    pub client_id: String,
+    pub client_name: String,
+    pub scope: String,
+    pub created_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DataExportStatus {
+    Pending,
+    Ready,
+    Failed,
+}
+
+impl DataExportStatus {
+    pub fn parse(status: &str) -> Self {
+        match status {
+            "pending" => Self::Pending,
+            "ready" => Self::Ready,
+            _ => Self::Failed,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Ready => "ready",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+// A row of the data_exports table, without the data.
+#[derive(sqlx::FromRow, Debug)]
+pub struct DataExportEntry {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+}
+
+// Returned by the export endpoints for exports that run in the background.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DataExportData {
+    pub id: Uuid,
+    pub status: DataExportStatus,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+    // Only set once the export is ready, valid until it expires.
+    pub download_url: Option<String>,
+}
+
+// Query parameters of the download link.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DataExportDownloadParams {
+    pub token: String,
+}
@@ -0,0 +1,6 @@
+pub mod admin;
+pub mod api_key;
+pub mod audit;
+pub mod export;
+pub mod oauth;
+pub mod user;
@@ -0,0 +1,127 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// Input to the admin endpoint registering OAuth clients.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct OAuthClientCreateRaw {
+    pub name: String,
+    // Authorization codes are only ever sent to one of these exact URIs.
+    pub redirect_uris: Vec<String>,
+    // Confidential clients get a secret, public clients like single page apps rely on PKCE alone.
+    pub confidential: bool,
+}
+
+// A registered OAuth client, without its secret.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct OAuthClientData {
+    pub client_id: String,
+    pub name: String,
+    pub redirect_uris: Vec<String>,
+    pub confidential: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+// Returned when registering a client, the secret is shown only once.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct OAuthClientCreatedData {
+    pub client_secret: Option<String>,
+    #[serde(flatten)]
+    pub client: OAuthClientData,
+}
+
+// A row of the oauth_clients table.
+#[derive(sqlx::FromRow, Debug)]
+pub struct OAuthClientEntry {
+    pub id: String,
+    pub name: String,
+    pub secret_hash: Option<String>,
+    pub redirect_uris: Vec<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<OAuthClientEntry> for OAuthClientData {
+    fn from(entry: OAuthClientEntry) -> Self {
+        Self {
+            client_id: entry.id,
+            name: entry.name,
+            redirect_uris: entry.redirect_uris,
+            confidential: entry.secret_hash.is_some(),
+            created_at: entry.created_at,
+        }
+    }
+}
+
+// Query parameters of the authorize endpoint, see RFC 6749, section 4.1.1 and RFC 7636.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AuthorizeParams {
+    pub client_id: String,
+    pub redirect_uri: String,
+    // Everything else is checked after the redirect URI, so that errors can be sent there.
+    pub response_type: Option<String>,
+    pub scope: Option<String>,
+    pub state: Option<String>,
+    pub nonce: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+}
+
+// Form body of the token endpoint, see RFC 6749, section 4.1.3. All fields are optional, so that
+// missing ones are reported in the OAuth error format.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TokenRequest {
+    pub grant_type: Option<String>,
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub code_verifier: Option<String>,
+    // Only if the client doesn't authenticate with HTTP Basic auth.
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+// Returned by the token endpoint.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+    pub id_token: String,
+    pub scope: String,
+}
+
+// Claims of the ID tokens handed out to clients, see OpenID Connect Core 1.0, section 2.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: Uuid,
+    // The client id.
+    pub aud: String,
+    pub exp: usize,
+    pub iat: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    // Only with the `profile` scope.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preferred_username: Option<String>,
+}
+
+// Claims of the access tokens handed out to clients. They lack the role and session of our own
+// tokens, so they are only good for the userinfo endpoint.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AccessTokenClaims {
+    pub iss: String,
+    pub sub: Uuid,
+    pub client_id: String,
+    pub scope: String,
+    pub exp: usize,
+    pub iat: usize,
+}
+
+// Returned by the userinfo endpoint.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UserInfoData {
+    pub sub: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preferred_username: Option<String>,
+}
@@ -0,0 +1,222 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Deserializer, Serialize};
+use uuid::Uuid;
+
+use crate::error::Error;
+use crate::validation::Validator;
+
+// Input to the create endpoint.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UserCreateRaw {
+    pub username: String,
+    pub password: String,
+    // Optional, a verification link is sent to it.
+    #[serde(default)]
+    pub email: Option<String>,
+}
+
+// Input to the login endpoint.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LoginRaw {
+    // Either the username or a verified email address.
+    #[serde(alias = "email")]
+    pub username: String,
+    pub password: String,
+}
+
+// Input to the restore endpoint, which takes the same credentials as the login.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RestoreRaw {
+    #[serde(alias = "email")]
+    pub username: String,
+    pub password: String,
+    // A TOTP or recovery code, required if the user has enabled TOTP.
+    #[serde(default)]
+    pub code: Option<String>,
+}
+
+// Returned by the create, login and refresh endpoints.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UserAuthData {
+    pub id: Uuid,
+    // Short-lived access token (JWT).
+    pub token: String,
+    // Seconds until the access token expires.
+    pub expires_in: i64,
+    // Opaque token to obtain a new token pair from the refresh endpoint.
+    pub refresh_token: String,
+}
+
+// Input to the refresh and logout endpoints.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RefreshTokenRaw {
+    pub refresh_token: String,
+}
+
+// Newtype pattern.
+pub struct ValidUserData(pub UserCreateRaw);
+
+impl ValidUserData {
+    pub fn parse(value: UserCreateRaw, validator: &Validator) -> Result<Self, Error> {
+        let UserCreateRaw {
+            username,
+            password,
+            email,
+        } = value;
+        let (username, email) = validator.credentials(&username, &password, email.as_deref())?;
+
+        Ok(Self(UserCreateRaw {
+            username,
+            password,
+            email,
+        }))
+    }
+}
+
+// The full user as it is stored in the db.
+#[derive(sqlx::FromRow, Debug, Deserialize, Serialize)]
+pub struct UserEntry {
+    pub id: Uuid,
+    pub username: String,
+    pub hashed_password: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub role: String,
+    pub disabled_at: Option<DateTime<Utc>>,
+    pub password_reset_required: bool,
+    pub totp_secret: Option<String>,
+    pub totp_enabled_at: Option<DateTime<Utc>>,
+    pub totp_last_step: Option<i64>,
+    pub totp_recovery_codes: Vec<String>,
+    pub email: Option<String>,
+    pub email_verified_at: Option<DateTime<Utc>>,
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub avatar_url: Option<String>,
+    pub locale: Option<String>,
+    pub timezone: Option<String>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+// The public user data.
+#[derive(sqlx::FromRow, Debug, Deserialize, Serialize)]
+pub struct UserData {
+    pub id: Uuid,
+    pub username: String,
+    pub email: Option<String>,
+    pub email_verified: bool,
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub avatar_url: Option<String>,
+    pub locale: Option<String>,
+    pub timezone: Option<String>,
+}
+
+// Input to the update endpoint.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UserUpdateRaw {
+    pub username: String,
+}
+
+// Input to the patch endpoint, a JSON Merge Patch (RFC 7396): Fields that are missing are left
+// as they are, `null` clears them.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct UserPatchRaw {
+    #[serde(default, deserialize_with = "patch_field")]
+    pub username: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub email: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub display_name: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub bio: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub avatar_url: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub locale: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub timezone: Option<Option<String>>,
+}
+
+// Only called for fields that are present, so `null` ends up as `Some(None)`.
+fn patch_field<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+pub struct ValidUserPatch(pub UserPatchRaw);
+
+impl ValidUserPatch {
+    pub fn parse(value: UserPatchRaw, validator: &Validator) -> Result<Self, Error> {
+        Ok(Self(validator.user_patch(value)?))
+    }
+}
+
+// Input to the password change endpoint.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PasswordChangeRaw {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+// Input to the forgot password endpoint.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PasswordForgotRaw {
+    pub username: String,
+}
+
+// Input to the password reset endpoint.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PasswordResetRaw {
+    // The token mailed by the forgot password endpoint.
+    pub token: String,
+    pub new_password: String,
+}
+
+// Returned by the login endpoint instead of `UserAuthData` if the user has 2FA enabled.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MfaPendingData {
+    pub id: Uuid,
+    // Exchanged for `UserAuthData` at the MFA login endpoint.
+    pub mfa_token: String,
+    // Seconds until the MFA token expires.
+    pub expires_in: i64,
+}
+
+// Input to the MFA login endpoint.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MfaLoginRaw {
+    pub mfa_token: String,
+    // A TOTP or recovery code.
+    pub code: String,
+}
+
+// Returned by the TOTP enroll endpoint.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TotpEnrollData {
+    // Base32 encoded secret, for manual entry.
+    pub secret: String,
+    // For authenticator apps, usually shown as QR code.
+    pub otpauth_uri: String,
+}
+
+// Input to the TOTP confirm and disable endpoints.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TotpCodeRaw {
+    pub code: String,
+}
+
+// Returned when enabling TOTP, the codes are shown only once.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RecoveryCodesData {
+    pub recovery_codes: Vec<String>,
+}
+
+// Query of the email verification endpoint, i.e. the link sent to the user.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct EmailVerifyParams {
+    pub token: String,
+}
@@ -0,0 +1,138 @@
+use std::fmt;
+use std::sync::Arc;
+
+use argonautica::config::Variant;
+use argonautica::{Hasher, Verifier};
+use async_std::task;
+use once_cell::sync::OnceCell;
+
+use crate::auth::generate_token;
+use crate::error::Error;
+use crate::settings::{self, Settings};
+
+/// Hashes and verifies passwords. All methods block for a while, so they should be called on
+/// the thread pool for blocking tasks, see the helpers below.
+pub trait PasswordHasher: fmt::Debug + Send + Sync {
+    fn hash(&self, password: &str) -> Result<String, Error>;
+
+    fn verify(&self, hash: &str, password: &str) -> Result<bool, Error>;
+
+    /// Whether the hash has been made with other parameters than the current ones, in which case
+    /// it should be replaced the next time the password is known.
+    fn needs_rehash(&self, hash: &str) -> bool;
+}
+
+/// Argon2id with the parameters from `Settings.password_hashing`.
+pub struct Argon2Hasher {
+    params: settings::PasswordHashing,
+    secret: String,
+}
+
+impl Argon2Hasher {
+    pub fn from_settings(settings: &Settings) -> Result<Self, Error> {
+        let params = &settings.password_hashing;
+
+        // Argonautica only accepts powers of two, we'd rather find out before the first signup.
+        if !params.memory_kib.is_power_of_two() || params.memory_kib < 8 * params.parallelism {
+            return Err(Error::Config(format!(
+                "Invalid argon2 memory size {} KiB, it has to be a power of two of at least 8 KiB per lane",
+                params.memory_kib
+            )));
+        }
+
+        Ok(Self {
+            params: params.clone(),
+            secret: settings.app.secret.clone(),
+        })
+    }
+}
+
+impl fmt::Debug for Argon2Hasher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Argon2Hasher")
+            .field("params", &self.params)
+            .finish()
+    }
+}
+
+impl PasswordHasher for Argon2Hasher {
+    fn hash(&self, password: &str) -> Result<String, Error> {
+        let mut hasher = Hasher::default();
+        hasher
+            .configure_variant(Variant::Argon2id)
+            .configure_memory_size(self.params.memory_kib)
+            .configure_iterations(self.params.iterations)
+            .configure_lanes(self.params.parallelism)
+            .configure_threads(self.params.parallelism)
+            .with_password(password)
+            .with_secret_key(self.secret.as_str())
+            .hash()
+            .map_err(Error::from)
+    }
+
+    fn verify(&self, hash: &str, password: &str) -> Result<bool, Error> {
+        // The parameters are part of the hash, so older hashes can still be verified.
+        let mut verifier = Verifier::default();
+        verifier
+            .with_hash(hash)
+            .with_password(password)
+            .with_secret_key(self.secret.as_str())
+            .verify()
+            .map_err(Error::from)
+    }
+
+    fn needs_rehash(&self, hash: &str) -> bool {
+        let params = &self.params;
+        let current = format!(
+            "m={},t={},p={}",
+            params.memory_kib, params.iterations, params.parallelism
+        );
+
+        // An encoded hash looks like `$argon2id$v=19$m=4096,t=3,p=1$<salt>$<hash>`.
+        let mut parts = hash.split('$').skip(1);
+        let variant = parts.next();
+        let encoded_params = parts.nth(1);
+
+        variant != Some("argon2id") || encoded_params != Some(current.as_str())
+    }
+}
+
+/// Verifies a password on the thread pool for blocking tasks, since verifying takes some time.
+pub async fn verify_password(
+    hasher: Arc<dyn PasswordHasher>,
+    hash: String,
+    password: String,
+) -> Result<bool, Error> {
+    task::spawn_blocking(move || hasher.verify(&hash, &password)).await
+}
+
+/// Hashes a password on the thread pool for blocking tasks, since hashing takes some time.
+pub async fn hash_password(
+    hasher: Arc<dyn PasswordHasher>,
+    password: String,
+) -> Result<String, Error> {
+    task::spawn_blocking(move || hasher.hash(&password)).await
+}
+
+/// A hash of a random password, verified against when a user doesn't exist, so that it takes
+/// as long to reject unknown usernames as wrong passwords.
+#[derive(Clone, Debug, Default)]
+pub struct DummyHash(Arc<OnceCell<String>>);
+
+impl DummyHash {
+    /// Computes the hash in the background, so that it is ready by the time it's needed.
+    pub fn prepare(&self, hasher: Arc<dyn PasswordHasher>) {
+        let dummy = self.clone();
+        task::spawn(async move { dummy.get(hasher).await });
+    }
+
+    pub async fn get(&self, hasher: Arc<dyn PasswordHasher>) -> Result<String, Error> {
+        let cell = self.0.clone();
+
+        task::spawn_blocking(move || {
+            cell.get_or_try_init(|| hasher.hash(&generate_token()))
+                .cloned()
+        })
+        .await
+    }
+}
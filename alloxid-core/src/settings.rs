@@ -0,0 +1,372 @@
+use config::{Config, ConfigError, File};
+use dotenv;
+use jsonwebtoken::Algorithm;
+use names::Generator;
+use rand::{thread_rng, Rng};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+use crate::error::Error;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Settings {
+    pub app: App,
+    pub auth: Auth,
+    pub database: Database,
+    pub deletion: Deletion,
+    pub email: Email,
+    pub export: Export,
+    pub grpc: Grpc,
+    pub mailer: Mailer,
+    pub oauth: OAuth,
+    pub password_hashing: PasswordHashing,
+    pub validation: Validation,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct App {
+    pub cors_url: String,
+    pub host: String,
+    pub port: usize,
+    pub secret: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Auth {
+    /// Lifetime of the JWTs handed out on login and refresh.
+    pub access_token_minutes: i64,
+    /// Lifetime of a refresh token, each rotation starts a new one.
+    pub refresh_token_days: i64,
+    /// Maximum number of sessions whose revocation status is cached.
+    pub revocation_cache_capacity: usize,
+    /// How long a cached revocation status is trusted before it is looked up again.
+    pub revocation_cache_seconds: u64,
+    /// Lifetime of the single-use tokens handed out to reset a forgotten password.
+    pub password_reset_minutes: i64,
+    /// Lifetime of the token that is exchanged for a session once the second factor is provided.
+    pub mfa_pending_minutes: i64,
+    /// Shown next to the account name in authenticator apps.
+    pub totp_issuer: String,
+    pub login_throttle: LoginThrottle,
+    /// Limits requests for password reset tokens, every request counts like a failed login.
+    pub password_reset_throttle: LoginThrottle,
+    pub session_cookie: SessionCookie,
+    /// The key all new tokens are signed with.
+    pub signing_key: JwtKey,
+    /// Additional keys which are only used to verify tokens, e.g. keys that have been rotated out.
+    #[serde(default)]
+    pub verification_keys: Vec<JwtKey>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct LoginThrottle {
+    /// Failed logins of a username before it is locked out.
+    pub username_free_attempts: u32,
+    /// Failed logins from an IP address before it is locked out, across all usernames.
+    pub ip_free_attempts: u32,
+    /// The first lockout, doubled with every further failed attempt.
+    pub backoff_seconds: u64,
+    pub max_lockout_seconds: u64,
+    /// Failures are forgotten once there hasn't been another one for this long.
+    pub forget_after_seconds: u64,
+    /// Maximum number of usernames and IP addresses whose failures are counted.
+    pub capacity: usize,
+}
+
+/// Optional cookie mode for browsers, in which the tokens are also set as HttpOnly cookies.
+#[derive(Clone, Debug, Deserialize)]
+pub struct SessionCookie {
+    pub enabled: bool,
+    /// Holds the access token, sent along with every request.
+    pub access_name: String,
+    /// Holds the refresh token, only sent to the refresh and logout endpoints.
+    pub refresh_name: String,
+    /// Readable by scripts, its value has to be sent in the CSRF header with state-changing
+    /// requests.
+    pub csrf_name: String,
+    /// Only turn this off for local development over plain HTTP.
+    pub secure: bool,
+    pub same_site: SameSite,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct JwtKey {
+    /// Written to the `kid` header of issued tokens, used to pick the key when verifying.
+    pub kid: String,
+    pub algorithm: Algorithm,
+    /// Secret for HMAC algorithms.
+    pub secret: Option<String>,
+    /// PEM files for RSA and EC algorithms, relative paths are resolved from the crate root.
+    pub private_key_path: Option<String>,
+    pub public_key_path: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Database {
+    pub host: String,
+    pub name: String,
+    password: String,
+    pub port: usize,
+    username: String,
+}
+
+/// Deleted accounts, which are kept for a while so that they can be restored.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Deletion {
+    /// How long a deleted account can be restored before it is purged.
+    pub grace_period_days: i64,
+    /// How often the background task looks for accounts to purge.
+    pub purge_interval_seconds: u64,
+}
+
+/// Email addresses of users and their verification.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Email {
+    /// The sender of all mails, e.g. "alloxid <noreply@example.com>".
+    pub from: String,
+    /// Lifetime of verification links.
+    pub verification_hours: i64,
+    /// Minimum time between two verification mails to the same user.
+    pub resend_interval_seconds: i64,
+    /// The base URL of verification and download links, defaults to the address the app listens
+    /// on.
+    pub link_base_url: Option<String>,
+}
+
+/// Exports of all data stored about a user.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Export {
+    /// Exports with more rows than this run in the background instead of being returned right
+    /// away.
+    pub inline_max_records: i64,
+    /// How long the download link of a background export stays valid.
+    pub download_hours: i64,
+    /// Background exports still pending after this long are marked as failed, e.g. because the
+    /// app was stopped while running them, so that a new one can be started.
+    pub pending_minutes: i64,
+}
+
+/// The services of `alloxid-grpc`, served by its server and called by `alloxid-http`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Grpc {
+    /// The address the server listens on and clients connect to, e.g. "[::1]:50051".
+    pub address: String,
+    /// How long clients wait for a connection before giving up.
+    pub connect_timeout_seconds: u64,
+    /// How long clients wait for the response to a call.
+    pub timeout_seconds: u64,
+    /// How often the server checks the database to report the health of the `UserService`.
+    pub health_check_seconds: u64,
+    /// How long the server waits for calls in flight to finish when shutting down.
+    pub shutdown_grace_seconds: u64,
+    /// Serve the services from within `alloxid-http`, so they see its user events.
+    pub embedded: bool,
+    /// How many user events a watcher may fall behind before it misses some.
+    pub event_capacity: usize,
+    /// Talk TLS instead of plain text, both as server and client.
+    pub tls: Option<GrpcTls>,
+}
+
+/// PEM files are resolved from the crate root if their paths are relative.
+#[derive(Clone, Debug, Deserialize)]
+pub struct GrpcTls {
+    /// The certificate chain and private key the server presents.
+    pub cert_path: String,
+    pub key_path: String,
+    /// The CA clients verify the server certificate with, defaults to the webpki roots.
+    pub ca_cert_path: Option<String>,
+    /// The name the server certificate is issued for, defaults to the host of the address.
+    pub domain_name: Option<String>,
+}
+
+/// How outgoing mail is delivered.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Mailer {
+    /// Writes mails to the log, only meant for local development.
+    Log,
+    /// Appends mails as JSON lines to a file, relative paths are resolved from the crate root.
+    File { path: String },
+    /// Sends mails through an SMTP relay, always using TLS.
+    Smtp {
+        host: String,
+        port: u16,
+        /// Connect in plain text and upgrade with STARTTLS instead of connecting via TLS.
+        #[serde(default)]
+        starttls: bool,
+        username: Option<String>,
+        /// Should be set via env var SMTP_PASSWORD.
+        password: Option<String>,
+    },
+}
+
+/// The OAuth2/OpenID Connect provider, whose tokens are signed with `Auth.signing_key`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct OAuth {
+    /// The `iss` claim of ID tokens and base URL in the discovery document, defaults to the
+    /// address the app listens on.
+    pub issuer: Option<String>,
+    /// Where users without a session are sent to log in from the authorize endpoint, with the
+    /// authorize URL to come back to in the `return_to` query parameter.
+    pub login_url: String,
+    /// Lifetime of authorization codes, which are exchanged right away by clients.
+    pub authorization_code_seconds: i64,
+    /// Lifetime of the access and ID tokens handed out to clients.
+    pub access_token_minutes: i64,
+}
+
+/// Argon2id parameters for new password hashes. Existing hashes are upgraded on login.
+#[derive(Clone, Debug, Deserialize)]
+pub struct PasswordHashing {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Validation {
+    pub username_min_length: usize,
+    pub username_max_length: usize,
+    /// Characters allowed in usernames besides letters and digits.
+    pub username_extra_chars: String,
+    /// Usernames nobody can sign up with, compared case-insensitively.
+    #[serde(default)]
+    pub reserved_usernames: Vec<String>,
+    pub password_min_length: usize,
+    pub password_max_length: usize,
+    /// File with one banned password per line, relative paths are resolved from the crate root.
+    pub password_denylist_path: Option<String>,
+    pub display_name_max_length: usize,
+    pub bio_max_length: usize,
+}
+
+impl Settings {
+    pub fn new() -> Result<Self, ConfigError> {
+        let mut config = Config::new();
+
+        let cfg_path = crate_root();
+
+        config.merge(File::from(cfg_path.join("config/prod")))?;
+
+        #[cfg(debug_assertions)]
+        config.merge(File::from(cfg_path.join("config/dev")))?;
+
+        #[cfg(test)]
+        config.merge(File::from(cfg_path.join("config/test")))?;
+
+        // Load .env file into environment, if present.
+        dotenv::dotenv().expect("Failed to load .env file");
+
+        // Set the app secret from environment. (Unfortunately `config` doesn't support
+        // setting vars into nested parts of the config, e.g. `Settings.app`.
+        let secret = std::env::var("APP_SECRET").expect("Failed to read APP_SECRET");
+        config.set("app.secret", secret)?;
+
+        let db_pw = std::env::var("DATABASE_PASSWORD").expect("Failed to read DATABASE_PASSWORD");
+        config.set("database.password", db_pw)?;
+
+        // The secret for HMAC signing keys, if it isn't set in the config files.
+        if let Ok(jwt_secret) = std::env::var("JWT_SECRET") {
+            config.set("auth.signing_key.secret", jwt_secret)?;
+        }
+
+        if let Ok(smtp_password) = std::env::var("SMTP_PASSWORD") {
+            config.set("mailer.password", smtp_password)?;
+        }
+
+        config.try_into()
+    }
+
+    pub fn new_for_test() -> Result<Self, ConfigError> {
+        let mut settings = Settings::new()?;
+
+        let mut generator = Generator::default();
+        let name = generator
+            .next()
+            .expect("Failed to generate random db name.");
+        let db_name = format!("{}-{}", settings.database.name, name);
+        settings.database.name = db_name;
+
+        let mut rng = thread_rng();
+        let port = rng.gen_range(8080..9000);
+        settings.app.port = port;
+
+        Ok(settings)
+    }
+
+    /// Checks the values whose format the config files can't express, so that they fail at
+    /// startup rather than on the first request that needs them.
+    pub fn validate(&self) -> Result<(), Error> {
+        // Otherwise, the authorize endpoint would only find out once a user without a session
+        // comes by.
+        Url::parse(&self.oauth.login_url).map_err(|err| {
+            Error::Config(format!(
+                "Invalid [oauth].login_url {:?}: {}",
+                self.oauth.login_url, err
+            ))
+        })?;
+
+        Ok(())
+    }
+}
+
+/// The config files live in `alloxid-http`. We don't know if we're being run from the workspace,
+/// its crate root or the root of another crate of the workspace.
+pub fn crate_root() -> PathBuf {
+    let cwd = std::env::current_dir().expect("Failed to read cwd");
+
+    let crate_root = Path::new("alloxid-http");
+    if cwd.ends_with(crate_root) {
+        return cwd;
+    }
+
+    match cwd.parent() {
+        Some(workspace)
+            if !cwd.join(crate_root).exists() && workspace.join(crate_root).exists() =>
+        {
+            workspace.join(crate_root)
+        }
+        _ => cwd.join(crate_root),
+    }
+}
+
+impl JwtKey {
+    /// Creates a HMAC key, mainly useful for setting up keys in tests.
+    pub fn hmac(kid: &str, algorithm: Algorithm, secret: &str) -> Self {
+        Self {
+            kid: kid.to_string(),
+            algorithm,
+            secret: Some(secret.to_string()),
+            private_key_path: None,
+            public_key_path: None,
+        }
+    }
+}
+
+impl Database {
+    pub fn conn_string(&self) -> String {
+        format!(
+            "postgres://{}:{}@{}:{}",
+            self.username, self.password, self.host, self.port
+        )
+    }
+
+    pub fn full_url(&self) -> String {
+        format!(
+            "postgres://{}:{}@{}:{}/{}",
+            self.username, self.password, self.host, self.port, self.name
+        )
+    }
+
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+}
@@ -0,0 +1,421 @@
+use std::collections::HashSet;
+
+use chrono_tz::Tz;
+use unicode_normalization::UnicodeNormalization;
+use url::Url;
+
+use crate::error::{Error, FieldError};
+use crate::model::user::UserPatchRaw;
+use crate::settings;
+
+// The longest address that fits into a SMTP path, see RFC 5321.
+const EMAIL_MAX_LENGTH: usize = 254;
+const AVATAR_URL_MAX_LENGTH: usize = 2048;
+
+/// Checks user input against the rules in `Settings.validation`, built once at startup.
+#[derive(Clone, Debug)]
+pub struct Validator {
+    rules: settings::Validation,
+    reserved_usernames: HashSet<String>,
+    password_denylist: HashSet<String>,
+}
+
+impl Validator {
+    pub fn from_settings(rules: &settings::Validation) -> Result<Self, Error> {
+        let reserved_usernames = rules
+            .reserved_usernames
+            .iter()
+            .map(|name| normalize(name).to_lowercase())
+            .collect();
+
+        let password_denylist = match &rules.password_denylist_path {
+            Some(path) => read_denylist(path)?,
+            None => HashSet::new(),
+        };
+
+        Ok(Self {
+            rules: rules.clone(),
+            reserved_usernames,
+            password_denylist,
+        })
+    }
+
+    /// Returns the NFKC normalized username, so that look-alike names are stored the same way.
+    pub fn username(&self, username: &str) -> Result<String, Error> {
+        let (username, errors) = self.check_username(username);
+
+        if errors.is_empty() {
+            Ok(username)
+        } else {
+            Err(Error::Validation(errors))
+        }
+    }
+
+    /// Normalizes the username or email address someone logs in with the way it was stored on
+    /// signup. Doesn't validate it, unknown names fail like wrong passwords.
+    pub fn login_name(&self, name: &str) -> String {
+        let name = name.trim();
+
+        if name.contains('@') {
+            name.to_string()
+        } else {
+            normalize(name)
+        }
+    }
+
+    /// Validates a new set of credentials, collecting all errors instead of stopping at the first.
+    /// Returns the normalized username and email address.
+    pub fn credentials(
+        &self,
+        username: &str,
+        password: &str,
+        email: Option<&str>,
+    ) -> Result<(String, Option<String>), Error> {
+        let (username, mut errors) = self.check_username(username);
+        errors.extend(self.check_password(&username, password));
+
+        let email = email.map(|email| {
+            let (email, email_errors) = check_email(email);
+            errors.extend(email_errors);
+            email
+        });
+
+        if errors.is_empty() {
+            Ok((username, email))
+        } else {
+            Err(Error::Validation(errors))
+        }
+    }
+
+    /// Validates the fields a patch sets, collecting all errors. Profile fields that are set to
+    /// empty strings are cleared.
+    pub fn user_patch(&self, patch: UserPatchRaw) -> Result<UserPatchRaw, Error> {
+        let rules = &self.rules;
+        let mut errors = Vec::new();
+
+        let username = match patch.username {
+            Some(Some(username)) => {
+                let (username, username_errors) = self.check_username(&username);
+                errors.extend(username_errors);
+                Some(Some(username))
+            }
+            Some(None) => {
+                errors.push(FieldError::new(
+                    "username",
+                    "required",
+                    "Username can't be removed.",
+                ));
+                None
+            }
+            None => None,
+        };
+
+        let email = patch.email.map(|email| {
+            email.map(|email| {
+                let (email, email_errors) = check_email(&email);
+                errors.extend(email_errors);
+                email
+            })
+        });
+
+        let display_name = profile_field(patch.display_name, |display_name| {
+            check_text(
+                "display_name",
+                "Display name",
+                display_name,
+                rules.display_name_max_length,
+                false,
+            )
+        });
+        errors.extend(display_name.1);
+
+        let bio = profile_field(patch.bio, |bio| {
+            check_text("bio", "Bio", bio, rules.bio_max_length, true)
+        });
+        errors.extend(bio.1);
+
+        let avatar_url = profile_field(patch.avatar_url, check_avatar_url);
+        errors.extend(avatar_url.1);
+
+        let locale = profile_field(patch.locale, check_locale);
+        errors.extend(locale.1);
+
+        let timezone = profile_field(patch.timezone, check_timezone);
+        errors.extend(timezone.1);
+
+        if !errors.is_empty() {
+            return Err(Error::Validation(errors));
+        }
+
+        Ok(UserPatchRaw {
+            username,
+            email,
+            display_name: display_name.0,
+            bio: bio.0,
+            avatar_url: avatar_url.0,
+            locale: locale.0,
+            timezone: timezone.0,
+        })
+    }
+
+    /// Validates a new password of the user with the given username.
+    pub fn password(&self, username: &str, password: &str) -> Result<(), Error> {
+        let errors = self.check_password(username, password);
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Validation(errors))
+        }
+    }
+
+    fn check_username(&self, username: &str) -> (String, Vec<FieldError>) {
+        let rules = &self.rules;
+        let username = normalize(username.trim());
+        let len = username.chars().count();
+        let mut errors = Vec::new();
+
+        if len < rules.username_min_length {
+            errors.push(FieldError::new(
+                "username",
+                "too_short",
+                format!(
+                    "Username must be at least {} characters long.",
+                    rules.username_min_length
+                ),
+            ));
+        } else if len > rules.username_max_length {
+            errors.push(FieldError::new(
+                "username",
+                "too_long",
+                format!(
+                    "Username must be at most {} characters long.",
+                    rules.username_max_length
+                ),
+            ));
+        }
+
+        let allowed = |c: char| c.is_alphanumeric() || rules.username_extra_chars.contains(c);
+        if !username.chars().all(allowed) {
+            errors.push(FieldError::new(
+                "username",
+                "invalid_characters",
+                format!(
+                    "Username may only contain letters, digits and any of \"{}\".",
+                    rules.username_extra_chars
+                ),
+            ));
+        }
+
+        if self.reserved_usernames.contains(&username.to_lowercase()) {
+            errors.push(FieldError::new(
+                "username",
+                "reserved",
+                "This username is reserved.",
+            ));
+        }
+
+        (username, errors)
+    }
+
+    fn check_password(&self, username: &str, password: &str) -> Vec<FieldError> {
+        let rules = &self.rules;
+        let len = password.chars().count();
+        let mut errors = Vec::new();
+
+        if len < rules.password_min_length {
+            errors.push(FieldError::new(
+                "password",
+                "too_short",
+                format!(
+                    "Password must be at least {} characters long.",
+                    rules.password_min_length
+                ),
+            ));
+        } else if len > rules.password_max_length {
+            errors.push(FieldError::new(
+                "password",
+                "too_long",
+                format!(
+                    "Password must be at most {} characters long.",
+                    rules.password_max_length
+                ),
+            ));
+        }
+
+        let lowercase = password.to_lowercase();
+        if self.password_denylist.contains(&lowercase) {
+            errors.push(FieldError::new(
+                "password",
+                "too_common",
+                "This password is too common.",
+            ));
+        }
+
+        if lowercase == username.to_lowercase() {
+            errors.push(FieldError::new(
+                "password",
+                "same_as_username",
+                "Password must not be the same as the username.",
+            ));
+        }
+
+        errors
+    }
+}
+
+/// Only a sanity check, whether an address really exists is found out by verifying it.
+fn check_email(email: &str) -> (String, Vec<FieldError>) {
+    let email = email.trim().to_string();
+    let mut errors = Vec::new();
+
+    let valid = match email.rsplit_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !email.chars().any(|c| c.is_whitespace() || c.is_control())
+        }
+        None => false,
+    };
+
+    if !valid {
+        errors.push(FieldError::new(
+            "email",
+            "invalid",
+            "This is not a valid email address.",
+        ));
+    } else if email.len() > EMAIL_MAX_LENGTH {
+        errors.push(FieldError::new(
+            "email",
+            "too_long",
+            format!(
+                "Email address must be at most {} characters long.",
+                EMAIL_MAX_LENGTH
+            ),
+        ));
+    }
+
+    (email, errors)
+}
+
+/// Trims a patched profile field and checks it, unless it's cleared.
+fn profile_field(
+    value: Option<Option<String>>,
+    check: impl FnOnce(&str) -> Option<FieldError>,
+) -> (Option<Option<String>>, Option<FieldError>) {
+    match value {
+        Some(Some(value)) if !value.trim().is_empty() => {
+            let value = value.trim().to_string();
+            let error = check(&value);
+            (Some(Some(value)), error)
+        }
+        Some(_) => (Some(None), None),
+        None => (None, None),
+    }
+}
+
+fn check_text(
+    field: &str,
+    name: &str,
+    value: &str,
+    max_length: usize,
+    multiline: bool,
+) -> Option<FieldError> {
+    if value.chars().count() > max_length {
+        return Some(FieldError::new(
+            field,
+            "too_long",
+            format!("{} must be at most {} characters long.", name, max_length),
+        ));
+    }
+
+    if value
+        .chars()
+        .any(|c| c.is_control() && !(multiline && c == '\n'))
+    {
+        return Some(FieldError::new(
+            field,
+            "invalid_characters",
+            format!("{} must not contain control characters.", name),
+        ));
+    }
+
+    None
+}
+
+fn check_avatar_url(url: &str) -> Option<FieldError> {
+    if url.len() > AVATAR_URL_MAX_LENGTH {
+        return Some(FieldError::new(
+            "avatar_url",
+            "too_long",
+            format!(
+                "Avatar URL must be at most {} characters long.",
+                AVATAR_URL_MAX_LENGTH
+            ),
+        ));
+    }
+
+    match Url::parse(url) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => None,
+        _ => Some(FieldError::new(
+            "avatar_url",
+            "invalid",
+            "Avatar URL must be an absolute http(s) URL.",
+        )),
+    }
+}
+
+/// A BCP 47 language tag, only checking its shape, e.g. "de" or "de-CH".
+fn check_locale(locale: &str) -> Option<FieldError> {
+    let mut subtags = locale.split('-');
+    let language = subtags.next().unwrap_or_default();
+
+    let valid = (2..=8).contains(&language.len())
+        && language.chars().all(|c| c.is_ascii_alphabetic())
+        && subtags.all(|subtag| {
+            (1..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric())
+        });
+
+    if valid {
+        None
+    } else {
+        Some(FieldError::new(
+            "locale",
+            "invalid",
+            "Locale must be a language tag like \"en\" or \"de-CH\".",
+        ))
+    }
+}
+
+fn check_timezone(timezone: &str) -> Option<FieldError> {
+    match timezone.parse::<Tz>() {
+        Ok(_) => None,
+        Err(_) => Some(FieldError::new(
+            "timezone",
+            "invalid",
+            "Timezone must be an IANA time zone name like \"Europe/Zurich\".",
+        )),
+    }
+}
+
+fn normalize(s: &str) -> String {
+    s.nfkc().collect()
+}
+
+fn read_denylist(path: &str) -> Result<HashSet<String>, Error> {
+    let contents = std::fs::read_to_string(settings::crate_root().join(path)).map_err(|err| {
+        Error::Config(format!(
+            "Failed to read password denylist {}: {}",
+            path, err
+        ))
+    })?;
+
+    Ok(contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_lowercase)
+        .collect())
+}
@@ -2,6 +2,9 @@ import { Head } from "$fresh/runtime.ts";
 import { ComponentChildren } from "preact";
 
 import LinkButton from "islands/LinkButton.tsx";
+import SignOut from "islands/SignOut.tsx";
+
+import { API_URL, CSRF_COOKIE } from "config";
 
 import { ServerState } from "routes/_middleware.ts";
 import { Link } from "components";
@@ -14,10 +17,6 @@ type Props = {
 export function Layout(props: Props) {
   const { user, error } = props.state;
 
-  const buttProps = user
-    ? { href: "/api/user/logout", text: "Sign Out" }
-    : { href: "/sign-in", text: "Sign In" };
-
   return (
     <>
       <div class="container">
@@ -50,7 +49,9 @@ export function Layout(props: Props) {
               <Link href="/sign-up">Create account</Link>
             </li>
             <li>
-              <LinkButton href={buttProps.href}>{buttProps.text}</LinkButton>
+              {user
+                ? <SignOut apiUrl={API_URL} csrfCookie={CSRF_COOKIE} />
+                : <LinkButton href="/sign-in">Sign In</LinkButton>}
             </li>
           </ul>
         </nav>
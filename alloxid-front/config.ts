@@ -10,6 +10,9 @@ if (DENO_ENV == "dev" || DENO_ENV == "development") {
 
 export const PORT = Deno.env.get("PORT") || "";
 export const API_URL = Deno.env.get("API_URL") || "";
+// Has to match `auth.session_cookie` of alloxid-http, which needs to be enabled.
+export const SESSION_COOKIE = "alloxid_session";
+export const CSRF_COOKIE = "alloxid_csrf";
 // export const REDIS_HOST = Deno.env.get("REDIS_HOST") || "";
 // export const REDIS_PORT = Deno.env.get("REDIS_PORT") || "";
 // export const REDIS_PASS = Deno.env.get("REDIS_PASS") || "";
@@ -1,10 +1,18 @@
+import { useState } from "preact/hooks";
+import { JSX } from "preact";
+
 import { Link } from "components";
 
 type Props = {
   mode: "In" | "Up";
+  apiUrl: string;
+  // Where to go once logged in, e.g. back to an OAuth authorization.
+  returnTo?: string;
 };
 
-export default function AuthForm({ mode }: Props) {
+export default function AuthForm({ mode, apiUrl, returnTo }: Props) {
+  const [error, setError] = useState<string | null>(null);
+
   const signIn = {
     title: "Sign In",
     href: "/user/login",
@@ -19,22 +27,51 @@ export default function AuthForm({ mode }: Props) {
 
   const buttProps = mode == "In" ? signIn : signUp;
   const footProps = mode == "In" ? signUp : signIn;
+  // Accounts are created by posting to /user.
+  const path = mode == "In" ? "/user/login" : "/user";
+
+  // Sent straight to the backend, so that the browser keeps its HttpOnly session cookies.
+  async function onSubmit(event: JSX.TargetedEvent<HTMLFormElement>) {
+    event.preventDefault();
+    const form = new FormData(event.currentTarget);
+
+    const res = await fetch(`${apiUrl}${path}`, {
+      method: "POST",
+      credentials: "include",
+      headers: new Headers({ "content-type": "application/json" }),
+      body: JSON.stringify({
+        username: String(form.get("username")),
+        password: String(form.get("password")),
+      }),
+    });
+
+    if (!res.ok) {
+      const { status, statusText } = res;
+      console.error("AuthForm | ERROR: ", status, statusText);
+      setError(`${status} ${statusText}`);
+      return;
+    }
+
+    const { data: { id } } = await res.json();
+    // Not a secret, the middleware only needs to know whose profile to fetch.
+    document.cookie = `user_id=${id}; path=/; SameSite=Lax; Secure`;
+    window.location.assign(returnTo ?? "/");
+  }
 
   return (
     <>
       <h2>{buttProps.title}</h2>
 
-      <form method="post">
+      <form method="post" onSubmit={onSubmit}>
         <input type="email" name="username" autofocus />
         <input type="password" name="password" />
 
-        <button
-          type="submit"
-          formAction={"/api" + buttProps.href}
-        >
+        <button type="submit">
           {buttProps.title}
         </button>
 
+        {error && <p>There was an error: {error}</p>}
+
         <p>
           {footProps.text} <Link href={footProps.href}>{footProps.title}</Link>
         </p>
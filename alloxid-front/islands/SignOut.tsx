@@ -0,0 +1,43 @@
+import { IS_BROWSER } from "$fresh/runtime.ts";
+
+import { Link } from "components";
+
+type Props = {
+  apiUrl: string;
+  csrfCookie: string;
+};
+
+function getCookie(name: string): string | undefined {
+  return document.cookie
+    .split(";")
+    .map((pair) => pair.trim().split("="))
+    .find(([key]) => key == name)?.[1];
+}
+
+export default function SignOut({ apiUrl, csrfCookie }: Props) {
+  // The refresh cookie is only sent to the backend, along with the CSRF token it asks for.
+  async function onClick(event: Event) {
+    event.preventDefault();
+
+    const res = await fetch(`${apiUrl}/user/logout`, {
+      method: "POST",
+      credentials: "include",
+      headers: new Headers({ "x-csrf-token": getCookie(csrfCookie) || "" }),
+    });
+
+    if (!res.ok) {
+      const { status, statusText } = res;
+      console.error("SignOut | ERROR: ", status, statusText);
+    }
+
+    // The backend clears its cookies, this one is ours.
+    document.cookie = "user_id=; path=/; Max-Age=0";
+    window.location.assign("/");
+  }
+
+  return (
+    <Link role="button" href="/" onClick={onClick} disabled={!IS_BROWSER}>
+      Sign Out
+    </Link>
+  );
+}
@@ -1,7 +1,7 @@
 import { MiddlewareHandlerContext } from "$fresh/server.ts";
 import { getCookies } from "std/http/cookie.ts";
 
-import { API_URL } from "config";
+import { API_URL, SESSION_COOKIE } from "config";
 
 export type User = {
   id: string;
@@ -19,7 +19,8 @@ export async function handler(
 ) {
   const url = new URL(req.url);
   const cookies = getCookies(req.headers);
-  const access_token = cookies.session_id;
+  // Set by the backend in cookie mode, the frontend shares its host.
+  const access_token = cookies[SESSION_COOKIE];
   const user_id = cookies.user_id;
   console.debug(`_middle | ${req.method} ${url}`);
 
@@ -37,7 +38,8 @@ export async function handler(
   }
 
   if (access_token && user_id) {
-    const headers = new Headers({ authorization: `Bearer ${access_token}` });
+    // The session cookie is HttpOnly, so it's only ever passed on as it is.
+    const headers = new Headers({ cookie: `${SESSION_COOKIE}=${access_token}` });
 
     try {
       const res = await fetch(`${API_URL}/user/${user_id}`, {
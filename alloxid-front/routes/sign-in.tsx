@@ -1,20 +1,30 @@
 import { Handlers, PageProps } from "$fresh/server.ts";
 
+import { API_URL } from "config";
 import { ServerState } from "routes/_middleware.ts";
 
 import { Layout } from "components";
 import AuthForm from "islands/AuthForm.tsx";
 
-export const handler: Handlers = {
-  GET(_req, ctx) {
-    return ctx.render(ctx.state);
+type Data = ServerState & { returnTo?: string };
+
+export const handler: Handlers<Data> = {
+  GET(req, ctx) {
+    // Set by the OAuth authorize endpoint, only followed back there.
+    const returnTo = new URL(req.url).searchParams.get("return_to");
+    const isAuthorize = returnTo?.startsWith(`${API_URL}/oauth/authorize?`);
+
+    return ctx.render({
+      ...(ctx.state as ServerState),
+      returnTo: isAuthorize ? returnTo! : undefined,
+    });
   },
 };
 
-export default function Page(props: PageProps<ServerState>) {
+export default function Page(props: PageProps<Data>) {
   return (
     <Layout state={props.data}>
-      <AuthForm mode="In" />
+      <AuthForm mode="In" apiUrl={API_URL} returnTo={props.data.returnTo} />
     </Layout>
   );
 }
@@ -1,5 +1,6 @@
 import { Handlers, PageProps } from "$fresh/server.ts";
 
+import { API_URL } from "config";
 import { ServerState } from "routes/_middleware.ts";
 
 import { Layout } from "components";
@@ -14,7 +15,7 @@ export const handler: Handlers = {
 export default function Page(props: PageProps<ServerState>) {
   return (
     <Layout state={props.data}>
-      <AuthForm mode="Up" />
+      <AuthForm mode="Up" apiUrl={API_URL} />
     </Layout>
   );
 }
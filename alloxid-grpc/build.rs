@@ -1,4 +1,12 @@
+use std::env;
+use std::path::PathBuf;
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    tonic_build::compile_protos("proto/hello.proto")?;
+    let out_dir = PathBuf::from(env::var("OUT_DIR")?);
+
+    // The descriptors are served by the reflection service.
+    tonic_build::configure()
+        .file_descriptor_set_path(out_dir.join("alloxid_descriptor.bin"))
+        .compile(&["proto/hello.proto", "proto/user.proto"], &["proto"])?;
     Ok(())
 }
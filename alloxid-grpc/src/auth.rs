@@ -0,0 +1,146 @@
+use std::future::Future;
+
+use sqlx::PgPool;
+use tonic::metadata::MetadataMap;
+use tonic::service::Interceptor;
+use tonic::{Request, Status};
+use tracing::error;
+use uuid::Uuid;
+
+use alloxid_core::auth::{Claims, JwtKeys, Role};
+use alloxid_core::database;
+
+use crate::error::to_status;
+
+const SCHEME_PREFIX: &str = "Bearer ";
+
+/// The user a call was made by, attached to the request extensions by the `Authenticator`.
+#[derive(Clone, Copy, Debug)]
+pub struct AuthUser {
+    pub user_id: Uuid,
+    pub role: Role,
+    // The session (refresh token family) the token belongs to.
+    pub session_id: Uuid,
+}
+
+impl AuthUser {
+    /// Only allows acting on the given user if it's the authenticated user itself or an admin.
+    pub fn ensure_self_or_admin(&self, user_id: &Uuid) -> Result<(), Status> {
+        if self.user_id == *user_id || self.role == Role::Admin {
+            return Ok(());
+        }
+
+        error!(
+            "user_id={} is not allowed to act on user_id={}",
+            self.user_id, user_id
+        );
+        Err(Status::permission_denied("Forbidden."))
+    }
+}
+
+/// Validates the bearer JWTs handed out by `alloxid-http`, sent in the `authorization` metadata,
+/// and attaches the `AuthUser` to the request extensions.
+///
+/// Calls without a token pass, so that services can mix public and protected methods. Each
+/// method states the role it requires with `require`, which also looks up the session.
+#[derive(Clone, Debug)]
+pub struct Authenticator {
+    keys: JwtKeys,
+}
+
+impl Authenticator {
+    pub fn new(keys: JwtKeys) -> Self {
+        Self { keys }
+    }
+
+    fn authenticate(&self, metadata: &MetadataMap) -> Result<Option<AuthUser>, Status> {
+        let header = match metadata.get("authorization") {
+            Some(header) => header,
+            None => return Ok(None),
+        };
+
+        let token = header
+            .to_str()
+            .ok()
+            .and_then(|header| header.strip_prefix(SCHEME_PREFIX))
+            .ok_or_else(|| {
+                error!("Malformed authorization metadata");
+                Status::unauthenticated("Malformed authorization metadata.")
+            })?;
+
+        let claims = self
+            .keys
+            .decode::<Claims>(token)
+            .map_err(|err| {
+                error!("Invalid token: {:?}", err);
+                Status::unauthenticated("Invalid token.")
+            })?
+            .claims;
+
+        if claims.mfa_pending {
+            error!("Token is still waiting for a second factor");
+            return Err(Status::unauthenticated("Invalid token."));
+        }
+
+        Ok(Some(AuthUser {
+            user_id: claims.sub.take(),
+            role: Role::from_str(&claims.role),
+            session_id: claims.jti,
+        }))
+    }
+}
+
+impl Interceptor for Authenticator {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        if let Some(user) = self.authenticate(request.metadata())? {
+            request.extensions_mut().insert(user);
+        }
+
+        Ok(request)
+    }
+}
+
+/// Rejects calls that aren't authenticated, whose session has been revoked, or whose user lacks
+/// the role. Admins may call anything users may call.
+///
+/// Like `alloxid-http`, the session is looked up on every call, so that logging out or disabling
+/// the user takes effect right away. Admin rights are checked against the current role of the
+/// user rather than the one in their token.
+// Not an `async fn`, so that the future doesn't hold on to streaming requests, which aren't `Sync`.
+pub fn require<'a, T>(
+    request: &Request<T>,
+    role: Role,
+    pool: &'a PgPool,
+) -> impl Future<Output = Result<AuthUser, Status>> + 'a {
+    let user = request.extensions().get::<AuthUser>().copied();
+
+    async move {
+        let mut user = user.ok_or_else(|| Status::unauthenticated("Missing token."))?;
+
+        let active = database::is_session_active(pool, &user.session_id, &user.user_id)
+            .await
+            .map_err(|err| to_status(err.into()))?;
+        if !active {
+            error!(
+                "Session session_id={} of user_id={} has been revoked",
+                user.session_id, user.user_id
+            );
+            return Err(Status::unauthenticated("Invalid token."));
+        }
+
+        // Admin rights are the ones that matter once they've been taken away.
+        if role == Role::Admin || user.role == Role::Admin {
+            user.role = database::get_user_role(pool, &user.user_id)
+                .await
+                .map_err(|err| to_status(err.into()))?
+                .ok_or_else(|| Status::unauthenticated("Invalid token."))?;
+        }
+
+        if role == Role::Admin && user.role != Role::Admin {
+            error!("user_id={} lacks role={}", user.user_id, role);
+            return Err(Status::permission_denied("Forbidden."));
+        }
+
+        Ok(user)
+    }
+}
@@ -0,0 +1,47 @@
+use alloxid_core::Error;
+use tonic::metadata::MetadataMap;
+use tonic::{Code, Status};
+use tracing::error;
+
+/// Maps the errors of the domain logic onto gRPC statuses, the way `ServiceError` of
+/// `alloxid-http` maps them onto HTTP statuses.
+pub fn to_status(err: Error) -> Status {
+    match err {
+        Error::NotFound => Status::not_found("Not found."),
+        Error::Unauthorized => Status::unauthenticated("Invalid credentials."),
+        Error::Forbidden(reason) => Status::permission_denied(reason),
+        Error::Validation(errors) => {
+            let message = errors
+                .iter()
+                .map(|err| format!("{}: {}", err.field, err.message))
+                .collect::<Vec<_>>()
+                .join(" ");
+            Status::invalid_argument(message)
+        }
+        Error::Conflict(err) => Status::already_exists(format!("{}: {}", err.field, err.message)),
+        Error::TooManyRequests {
+            retry_after_seconds,
+        } => {
+            let mut metadata = MetadataMap::new();
+            metadata.insert("retry-after", retry_after_seconds.into());
+            Status::with_metadata(
+                Code::ResourceExhausted,
+                "Too many requests, try again later.",
+                metadata,
+            )
+        }
+        Error::Unavailable(reason) => {
+            error!("Service unavailable: {}", reason);
+            Status::unavailable("Service temporarily unavailable.")
+        }
+        err => {
+            error!("Internal error: {:?}", err);
+            Status::internal("Internal server error.")
+        }
+    }
+}
+
+/// The status of a request message with an id that isn't a UUID.
+pub fn invalid_id() -> Status {
+    Status::invalid_argument("id: Not a valid UUID.")
+}
@@ -0,0 +1,39 @@
+use sqlx::PgPool;
+use tonic::{Request, Response, Status};
+use tracing::debug;
+
+use alloxid_core::auth::Role;
+
+use crate::auth;
+use crate::hello::greeter_server::Greeter;
+use crate::hello::{self, HelloReply, HelloRequest};
+
+#[derive(Debug)]
+pub struct MyGreeter {
+    db_pool: PgPool,
+}
+
+impl MyGreeter {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self { db_pool }
+    }
+}
+
+#[tonic::async_trait]
+impl Greeter for MyGreeter {
+    async fn say_hello(
+        &self,
+        request: Request<HelloRequest>,
+    ) -> Result<Response<HelloReply>, Status> {
+        // Only the message, the metadata carries the token.
+        debug!("Request received: {:?}", request.get_ref());
+        auth::require(&request, Role::User, &self.db_pool).await?;
+
+        let reply = hello::HelloReply {
+            // We must use .into_inner() as the fields of gRPC requests and responses are private
+            message: format!("Hello, {}!", request.into_inner().name),
+        };
+
+        Ok(Response::new(reply))
+    }
+}
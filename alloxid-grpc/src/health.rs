@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+use sqlx::PgPool;
+use tonic_health::server::HealthReporter;
+use tonic_health::ServingStatus;
+use tracing::{info, warn};
+
+use crate::hello::greeter_server::GreeterServer;
+use crate::user::user_service_server::UserServiceServer;
+use crate::{greeter::MyGreeter, users::Users};
+
+/// The services reported by the `grpc.health.v1.Health` service, "" being the server as a whole.
+pub fn service_names() -> [&'static str; 3] {
+    use tonic::transport::NamedService;
+
+    [
+        "",
+        <GreeterServer<MyGreeter> as NamedService>::NAME,
+        <UserServiceServer<Users> as NamedService>::NAME,
+    ]
+}
+
+/// Reports the `UserService` as not serving while the database can't be reached.
+pub async fn watch_database(mut reporter: HealthReporter, db_pool: PgPool, interval: Duration) {
+    let mut last_status = ServingStatus::Unknown;
+
+    loop {
+        let status = match sqlx::query("select 1").execute(&db_pool).await {
+            Ok(_) => ServingStatus::Serving,
+            Err(err) => {
+                warn!("Database health check failed: {:?}", err);
+                ServingStatus::NotServing
+            }
+        };
+
+        if status != last_status {
+            info!("UserService is now {}", status);
+            reporter
+                .set_service_status(
+                    <UserServiceServer<Users> as tonic::transport::NamedService>::NAME,
+                    status,
+                )
+                .await;
+            last_status = status;
+        }
+
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// Reports all services as not serving, so that clients stop sending new calls.
+pub async fn set_not_serving(reporter: &mut HealthReporter) {
+    for name in service_names() {
+        reporter
+            .set_service_status(name, ServingStatus::NotServing)
+            .await;
+    }
+}
+
+/// Resolves once the process is asked to stop with SIGINT or SIGTERM.
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to listen for SIGINT");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => info!("Received SIGINT"),
+        _ = terminate => info!("Received SIGTERM"),
+    }
+}
@@ -1,3 +1,110 @@
+// `tonic::Status` is large, but it's what all gRPC handlers and interceptors return.
+#![allow(clippy::result_large_err)]
+
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use sqlx::PgPool;
+use tokio::sync::oneshot;
+use tracing::{info, warn};
+
+use alloxid_core::auth::{JwtKeys, LoginThrottle};
+use alloxid_core::events::UserEvents;
+use alloxid_core::settings::Settings;
+
+pub mod auth;
+pub mod error;
+pub mod greeter;
+pub mod health;
+pub mod transport;
+pub mod users;
+
 pub mod hello {
     tonic::include_proto!("hello"); // The string specified here must match the proto package name
 }
+
+pub mod user {
+    tonic::include_proto!("user");
+}
+
+/// The descriptors of our protos, for the reflection service.
+pub const FILE_DESCRIPTOR_SET: &[u8] = tonic::include_file_descriptor_set!("alloxid_descriptor");
+
+use auth::Authenticator;
+use greeter::MyGreeter;
+use hello::greeter_server::GreeterServer;
+use user::user_service_server::UserServiceServer;
+use users::Users;
+
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Serves all services on `Settings.grpc.address` until `shutdown` resolves. From then on, the
+/// health service reports them as not serving and no new connections are accepted, while the
+/// calls in flight get `Settings.grpc.shutdown_grace_seconds` to finish.
+///
+/// The user events of `events` are streamed to watchers, along with those of our own calls. Failed
+/// logins are counted in `login_throttle`, so that sharing it with `alloxid-http` leaves attackers
+/// a single budget for both.
+pub async fn serve(
+    db_pool: PgPool,
+    settings: Settings,
+    events: UserEvents,
+    login_throttle: Arc<LoginThrottle>,
+    shutdown: impl Future<Output = ()>,
+) -> Result<(), BoxError> {
+    let addr = transport::address(&settings.grpc)?;
+    let mut server = transport::server(&settings.grpc)?;
+    let health_check_interval = Duration::from_secs(settings.grpc.health_check_seconds);
+    let grace = Duration::from_secs(settings.grpc.shutdown_grace_seconds);
+
+    let authenticator = Authenticator::new(JwtKeys::from_settings(&settings.auth)?);
+    let greeter = MyGreeter::new(db_pool.clone());
+    let users = Users::new(db_pool.clone(), settings, events, login_throttle)?;
+
+    let (mut reporter, health_service) = tonic_health::server::health_reporter();
+    reporter.set_serving::<GreeterServer<MyGreeter>>().await;
+    let watcher = tokio::spawn(health::watch_database(
+        reporter.clone(),
+        db_pool,
+        health_check_interval,
+    ));
+
+    let reflection_service = tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(FILE_DESCRIPTOR_SET)
+        .register_encoded_file_descriptor_set(
+            tonic_health::proto::GRPC_HEALTH_V1_FILE_DESCRIPTOR_SET,
+        )
+        .build()?;
+
+    let (draining_tx, draining_rx) = oneshot::channel();
+    let signal = async move {
+        shutdown.await;
+        watcher.abort();
+        health::set_not_serving(&mut reporter).await;
+        info!("Shutting down, draining calls in flight");
+        let _ = draining_tx.send(());
+    };
+
+    println!("\nGreeterServer and UserService listening on {}", addr);
+    let serving = server
+        .add_service(health_service)
+        .add_service(reflection_service)
+        .add_service(GreeterServer::with_interceptor(
+            greeter,
+            authenticator.clone(),
+        ))
+        .add_service(UserServiceServer::with_interceptor(users, authenticator))
+        .serve_with_shutdown(addr, signal);
+
+    // Streams like `Health.Watch` never end by themselves, so draining is cut off at some point.
+    tokio::select! {
+        res = serving => res?,
+        _ = async {
+            let _ = draining_rx.await;
+            tokio::time::sleep(grace).await;
+        } => warn!("Calls still in flight after {:?}, stopping anyway", grace),
+    }
+
+    Ok(())
+}
@@ -1,42 +1,26 @@
-use tonic::{transport::Server, Request, Response, Status};
+use std::sync::Arc;
 
-use hello::greeter_server::{Greeter, GreeterServer};
-use hello::{HelloReply, HelloRequest};
+use sqlx::PgPool;
 
-pub mod hello {
-    tonic::include_proto!("hello");
-}
-
-#[derive(Debug, Default)]
-pub struct MyGreeter {}
-
-#[tonic::async_trait]
-impl Greeter for MyGreeter {
-    async fn say_hello(
-        &self,
-        request: Request<HelloRequest>,
-    ) -> Result<Response<HelloReply>, Status> {
-        println!("Request received: {:?}", request);
-
-        let reply = hello::HelloReply {
-            // We must use .into_inner() as the fields of gRPC requests and responses are private
-            message: format!("Hello, {}!", request.into_inner().name).into(),
-        };
-
-        Ok(Response::new(reply))
-    }
-}
+use alloxid_core::auth::LoginThrottle;
+use alloxid_core::events::UserEvents;
+use alloxid_core::settings::Settings;
+use alloxid_grpc::{health, BoxError};
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let addr = "[::1]:50051".parse()?;
-    let greeter = MyGreeter::default();
-
-    println!("\nGreeterServer listening on {}", addr);
-    Server::builder()
-        .add_service(GreeterServer::new(greeter))
-        .serve(addr)
-        .await?;
-
-    Ok(())
+async fn main() -> Result<(), BoxError> {
+    let settings = Settings::new()?;
+    let db_pool = PgPool::connect(&settings.database.full_url()).await?;
+    // On its own, the server only sees the events and failed logins of its own calls.
+    let events = UserEvents::new(settings.grpc.event_capacity);
+    let login_throttle = Arc::new(LoginThrottle::new(settings.auth.login_throttle.clone()));
+
+    alloxid_grpc::serve(
+        db_pool,
+        settings,
+        events,
+        login_throttle,
+        health::shutdown_signal(),
+    )
+    .await
 }
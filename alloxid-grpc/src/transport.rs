@@ -0,0 +1,68 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use tonic::transport::{
+    Certificate, Channel, ClientTlsConfig, Endpoint, Identity, Server, ServerTlsConfig,
+};
+
+use alloxid_core::settings::{self, GrpcTls};
+use alloxid_core::Error;
+
+/// The address the server binds to.
+pub fn address(grpc: &settings::Grpc) -> Result<SocketAddr, Error> {
+    grpc.address
+        .parse()
+        .map_err(|err| Error::Config(format!("Invalid gRPC address {}: {}", grpc.address, err)))
+}
+
+/// The channel clients call the services through. It only connects on the first call and
+/// reconnects by itself once the connection is lost, so it's built once and cloned from there.
+pub fn channel(grpc: &settings::Grpc) -> Result<Channel, Error> {
+    let scheme = if grpc.tls.is_some() { "https" } else { "http" };
+
+    let mut endpoint = Endpoint::from_shared(format!("{}://{}", scheme, grpc.address))
+        .map_err(config_error)?
+        .connect_timeout(Duration::from_secs(grpc.connect_timeout_seconds))
+        .timeout(Duration::from_secs(grpc.timeout_seconds));
+
+    if let Some(tls) = &grpc.tls {
+        let mut tls_config = ClientTlsConfig::new();
+        if let Some(path) = &tls.ca_cert_path {
+            tls_config = tls_config.ca_certificate(Certificate::from_pem(read_pem(path)?));
+        }
+        if let Some(domain_name) = &tls.domain_name {
+            tls_config = tls_config.domain_name(domain_name);
+        }
+        endpoint = endpoint.tls_config(tls_config).map_err(config_error)?;
+    }
+
+    Ok(endpoint.connect_lazy())
+}
+
+/// The server builder, set up to serve via TLS if it's configured.
+pub fn server(grpc: &settings::Grpc) -> Result<Server, Error> {
+    let server = Server::builder();
+
+    match &grpc.tls {
+        Some(GrpcTls {
+            cert_path,
+            key_path,
+            ..
+        }) => {
+            let identity = Identity::from_pem(read_pem(cert_path)?, read_pem(key_path)?);
+            server
+                .tls_config(ServerTlsConfig::new().identity(identity))
+                .map_err(config_error)
+        }
+        None => Ok(server),
+    }
+}
+
+fn read_pem(path: &str) -> Result<Vec<u8>, Error> {
+    std::fs::read(settings::crate_root().join(path))
+        .map_err(|err| Error::Config(format!("Failed to read PEM file {}: {}", path, err)))
+}
+
+fn config_error(err: tonic::transport::Error) -> Error {
+    Error::Config(format!("Invalid gRPC configuration: {}", err))
+}
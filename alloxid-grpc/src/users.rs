@@ -0,0 +1,451 @@
+use std::pin::Pin;
+use std::sync::Arc;
+
+use sqlx::PgPool;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+use tonic::{Request, Response, Status, Streaming};
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use alloxid_core::audit;
+use alloxid_core::auth::{authenticate, session, JwtKeys, Login, LoginThrottle, Role};
+use alloxid_core::database;
+use alloxid_core::events::{UserEvent as Event, UserEventKind, UserEvents};
+use alloxid_core::model::audit::{AuditAction, AuditOrigin};
+use alloxid_core::model::user::{UserAuthData, UserCreateRaw, UserData, ValidUserData};
+use alloxid_core::password::{Argon2Hasher, DummyHash, PasswordHasher};
+use alloxid_core::settings::Settings;
+use alloxid_core::validation::Validator;
+use alloxid_core::Error;
+
+use crate::auth;
+use crate::error::{invalid_id, to_status};
+use crate::user::user_event::Kind;
+use crate::user::user_service_server::UserService;
+use crate::user::{
+    DeleteUserReply, DeleteUserRequest, GetUserRequest, ImportFailure, ImportSummary, LoginRequest,
+    UpdateUserRequest, User, UserAuth, UserCreate, UserEvent, WatchUserEventsRequest,
+};
+
+/// The `UserService`, working on the same database as `alloxid-http`.
+#[derive(Clone, Debug)]
+pub struct Users {
+    db_pool: PgPool,
+    dummy_hash: DummyHash,
+    events: UserEvents,
+    hasher: Arc<dyn PasswordHasher>,
+    keys: JwtKeys,
+    login_throttle: Arc<LoginThrottle>,
+    settings: Settings,
+    validator: Arc<Validator>,
+}
+
+impl Users {
+    /// Publishes to and streams from `events` and counts failed logins in `login_throttle`, both
+    /// of which `alloxid-http` shares when it embeds us.
+    pub fn new(
+        db_pool: PgPool,
+        settings: Settings,
+        events: UserEvents,
+        login_throttle: Arc<LoginThrottle>,
+    ) -> Result<Self, Error> {
+        let keys = JwtKeys::from_settings(&settings.auth)?;
+        let validator = Arc::new(Validator::from_settings(&settings.validation)?);
+
+        let hasher: Arc<dyn PasswordHasher> = Arc::new(Argon2Hasher::from_settings(&settings)?);
+        let dummy_hash = DummyHash::default();
+        dummy_hash.prepare(hasher.clone());
+
+        Ok(Self {
+            db_pool,
+            dummy_hash,
+            events,
+            hasher,
+            keys,
+            login_throttle,
+            settings,
+            validator,
+        })
+    }
+
+    async fn record(
+        &self,
+        origin: &AuditOrigin,
+        action: AuditAction,
+        actor_id: Option<Uuid>,
+        subject_id: Option<Uuid>,
+        details: serde_json::Value,
+    ) {
+        audit::record(
+            &self.db_pool,
+            &self.events,
+            origin,
+            action,
+            actor_id,
+            subject_id,
+            details,
+        )
+        .await
+    }
+}
+
+/// Where a call came from, recorded along with every audit event.
+fn audit_origin<T>(request: &Request<T>) -> AuditOrigin {
+    let user_agent = request
+        .metadata()
+        .get("user-agent")
+        .and_then(|value| value.to_str().ok());
+
+    AuditOrigin::new(
+        request.remote_addr().map(|addr| addr.ip()),
+        user_agent,
+        None,
+    )
+}
+
+fn non_empty(value: String) -> Option<String> {
+    Some(value).filter(|value| !value.is_empty())
+}
+
+impl From<UserAuthData> for UserAuth {
+    fn from(data: UserAuthData) -> Self {
+        Self {
+            id: data.id.to_string(),
+            token: data.token,
+            expires_in: data.expires_in,
+            refresh_token: data.refresh_token,
+        }
+    }
+}
+
+impl From<Event> for UserEvent {
+    fn from(event: Event) -> Self {
+        let kind = match event.kind {
+            UserEventKind::Created => Kind::Created,
+            UserEventKind::Updated => Kind::Updated,
+            UserEventKind::Deleted => Kind::Deleted,
+            UserEventKind::LoggedIn => Kind::LoggedIn,
+        };
+
+        Self {
+            kind: kind as i32,
+            user_id: event.user_id.to_string(),
+            actor_id: event.actor_id.map(|id| id.to_string()).unwrap_or_default(),
+            occurred_at: event.occurred_at.to_rfc3339(),
+        }
+    }
+}
+
+impl From<UserData> for User {
+    fn from(data: UserData) -> Self {
+        Self {
+            id: data.id.to_string(),
+            username: data.username,
+            email: data.email.unwrap_or_default(),
+            email_verified: data.email_verified,
+            display_name: data.display_name.unwrap_or_default(),
+            bio: data.bio.unwrap_or_default(),
+            avatar_url: data.avatar_url.unwrap_or_default(),
+            locale: data.locale.unwrap_or_default(),
+            timezone: data.timezone.unwrap_or_default(),
+        }
+    }
+}
+
+type UserEventStream = Pin<Box<dyn Stream<Item = Result<UserEvent, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl UserService for Users {
+    type WatchUserEventsStream = UserEventStream;
+
+    async fn create_user(
+        &self,
+        request: Request<UserCreate>,
+    ) -> Result<Response<UserAuth>, Status> {
+        debug!(
+            "create_user called, db_name={}",
+            self.settings.database.name
+        );
+
+        let origin = audit_origin(&request);
+        let UserCreate {
+            username,
+            password,
+            email,
+        } = request.into_inner();
+
+        let raw_user_data = UserCreateRaw {
+            username,
+            password,
+            email: non_empty(email),
+        };
+        let valid_user_data =
+            ValidUserData::parse(raw_user_data, &self.validator).map_err(to_status)?;
+
+        let user = database::insert_new_user(&self.db_pool, valid_user_data, self.hasher.clone())
+            .await
+            .map_err(to_status)?;
+
+        self.record(
+            &origin,
+            AuditAction::UserCreated,
+            Some(user.id),
+            Some(user.id),
+            serde_json::json!({ "username": user.username, "email": user.email }),
+        )
+        .await;
+
+        let data = session::start(
+            &self.db_pool,
+            &self.keys,
+            &self.settings.auth,
+            user.id,
+            Role::from_str(&user.role),
+        )
+        .await
+        .map_err(to_status)?;
+
+        info!("Successfully created user_id={}", user.id);
+        Ok(Response::new(data.into()))
+    }
+
+    async fn login(&self, request: Request<LoginRequest>) -> Result<Response<UserAuth>, Status> {
+        debug!("login called, db_name={}", self.settings.database.name);
+
+        let origin = audit_origin(&request);
+        let LoginRequest { username, password } = request.into_inner();
+        let username = self.validator.login_name(&username);
+
+        let row = authenticate(
+            &self.db_pool,
+            &self.events,
+            self.hasher.clone(),
+            &self.dummy_hash,
+            &self.login_throttle,
+            Login {
+                username: &username,
+                password,
+                deleted: false,
+                origin: &origin,
+            },
+        )
+        .await
+        .map_err(to_status)?;
+        let user_id = row.user_id;
+
+        // The second step of the login only exists in `alloxid-http` so far.
+        if row.totp_enabled_at.is_some() {
+            return Err(Status::failed_precondition(
+                "TOTP is enabled, log in at /user/login instead.",
+            ));
+        }
+
+        let data = session::start(
+            &self.db_pool,
+            &self.keys,
+            &self.settings.auth,
+            user_id,
+            Role::from_str(&row.role),
+        )
+        .await
+        .map_err(to_status)?;
+
+        self.record(
+            &origin,
+            AuditAction::Login,
+            Some(user_id),
+            Some(user_id),
+            serde_json::json!({ "mfa": false }),
+        )
+        .await;
+
+        info!("Successfully logged in user_id={}", user_id);
+        Ok(Response::new(data.into()))
+    }
+
+    async fn get_user(&self, request: Request<GetUserRequest>) -> Result<Response<User>, Status> {
+        let user_id: Uuid = request.get_ref().id.parse().map_err(|_| invalid_id())?;
+        let auth_user = auth::require(&request, Role::User, &self.db_pool).await?;
+
+        debug!(
+            "get_user called, db_name={} user_id={}",
+            self.settings.database.name, user_id,
+        );
+
+        auth_user.ensure_self_or_admin(&user_id)?;
+
+        let user = database::get_user_data(&self.db_pool, &user_id)
+            .await
+            .map_err(|err| to_status(err.into()))?
+            .ok_or_else(|| Status::not_found("Not found."))?;
+
+        Ok(Response::new(user.into()))
+    }
+
+    async fn update_user(
+        &self,
+        request: Request<UpdateUserRequest>,
+    ) -> Result<Response<User>, Status> {
+        let origin = audit_origin(&request);
+        let auth_user = auth::require(&request, Role::User, &self.db_pool).await?;
+        let UpdateUserRequest { id, username } = request.into_inner();
+        let user_id: Uuid = id.parse().map_err(|_| invalid_id())?;
+
+        debug!(
+            "update_user called, db_name={} user_id={}",
+            self.settings.database.name, user_id,
+        );
+
+        auth_user.ensure_self_or_admin(&user_id)?;
+
+        let username = self.validator.username(&username).map_err(to_status)?;
+        let updated_user = database::update_username(&self.db_pool, &user_id, &username)
+            .await
+            .map_err(to_status)?;
+
+        self.record(
+            &origin,
+            AuditAction::UsernameChanged,
+            Some(auth_user.user_id),
+            Some(user_id),
+            serde_json::json!({ "username": updated_user.username }),
+        )
+        .await;
+
+        info!("Successfully updated user_id={}", user_id);
+        Ok(Response::new(updated_user.into()))
+    }
+
+    /// Deletes the user, who can restore their account within `Settings.deletion.grace_period_days`.
+    async fn delete_user(
+        &self,
+        request: Request<DeleteUserRequest>,
+    ) -> Result<Response<DeleteUserReply>, Status> {
+        let origin = audit_origin(&request);
+        let auth_user = auth::require(&request, Role::User, &self.db_pool).await?;
+        let user_id: Uuid = request.get_ref().id.parse().map_err(|_| invalid_id())?;
+
+        debug!(
+            "delete_user called, db_name={} user_id={}",
+            self.settings.database.name, user_id,
+        );
+
+        auth_user.ensure_self_or_admin(&user_id)?;
+
+        let deleted = database::soft_delete_user(&self.db_pool, &user_id)
+            .await
+            .map_err(|err| to_status(err.into()))?;
+        if !deleted {
+            return Err(Status::not_found("Not found."));
+        }
+
+        self.record(
+            &origin,
+            AuditAction::UserDeleted,
+            Some(auth_user.user_id),
+            Some(user_id),
+            serde_json::json!({}),
+        )
+        .await;
+
+        info!("Successfully deleted user_id={}", user_id);
+        Ok(Response::new(DeleteUserReply {}))
+    }
+
+    async fn watch_user_events(
+        &self,
+        request: Request<WatchUserEventsRequest>,
+    ) -> Result<Response<Self::WatchUserEventsStream>, Status> {
+        let auth_user = auth::require(&request, Role::Admin, &self.db_pool).await?;
+        info!("user_id={} is watching user events", auth_user.user_id);
+
+        // Watchers that fall behind skip the events they missed instead of being cut off.
+        let events =
+            BroadcastStream::new(self.events.subscribe()).filter_map(|event| match event {
+                Ok(event) => Some(Ok(UserEvent::from(event))),
+                Err(BroadcastStreamRecvError::Lagged(missed)) => {
+                    warn!("Watcher fell behind, missed {} user events", missed);
+                    None
+                }
+            });
+
+        Ok(Response::new(Box::pin(events)))
+    }
+
+    /// Creates the users one by one. Invalid or conflicting users are reported in the summary,
+    /// any other error ends the import, keeping the users created so far.
+    async fn import_users(
+        &self,
+        request: Request<Streaming<UserCreate>>,
+    ) -> Result<Response<ImportSummary>, Status> {
+        let origin = audit_origin(&request);
+        let auth_user = auth::require(&request, Role::Admin, &self.db_pool).await?;
+        let mut stream = request.into_inner();
+
+        debug!(
+            "import_users called, db_name={} user_id={}",
+            self.settings.database.name, auth_user.user_id,
+        );
+
+        let mut summary = ImportSummary::default();
+        let mut index = 0;
+
+        while let Some(UserCreate {
+            username,
+            password,
+            email,
+        }) = stream.message().await?
+        {
+            let raw_user_data = UserCreateRaw {
+                username: username.clone(),
+                password,
+                email: non_empty(email),
+            };
+
+            let created = match ValidUserData::parse(raw_user_data, &self.validator) {
+                Ok(valid_user_data) => {
+                    database::insert_new_user(&self.db_pool, valid_user_data, self.hasher.clone())
+                        .await
+                }
+                Err(err) => Err(err),
+            };
+
+            match created {
+                Ok(user) => {
+                    self.record(
+                        &origin,
+                        AuditAction::UserCreated,
+                        Some(auth_user.user_id),
+                        Some(user.id),
+                        serde_json::json!({
+                            "username": user.username,
+                            "email": user.email,
+                            "import": true,
+                        }),
+                    )
+                    .await;
+                    summary.created += 1;
+                }
+                Err(err @ (Error::Validation(_) | Error::Conflict(_))) => {
+                    summary.failed += 1;
+                    summary.failures.push(ImportFailure {
+                        index,
+                        username,
+                        reason: to_status(err).message().to_string(),
+                    });
+                }
+                Err(err) => return Err(to_status(err)),
+            }
+
+            index += 1;
+        }
+
+        info!(
+            "Imported users, created={} failed={}",
+            summary.created, summary.failed
+        );
+        Ok(Response::new(summary))
+    }
+}
@@ -0,0 +1,253 @@
+use tokio::time::{timeout, Duration};
+use tonic::{Code, Request, Streaming};
+
+use alloxid_grpc::user::user_event::Kind;
+use alloxid_grpc::user::{
+    DeleteUserRequest, GetUserRequest, LoginRequest, UpdateUserRequest, UserAuth, UserEvent,
+    WatchUserEventsRequest,
+};
+
+mod helpers;
+use helpers::{authorized, spawn_test_server, user_create, TestServer, PASSWORD};
+
+async fn create_admin(server: &mut TestServer) -> UserAuth {
+    let auth = server
+        .client
+        .create_user(Request::new(user_create("ilmari")))
+        .await
+        .expect("Failed to create user")
+        .into_inner();
+    sqlx::query("update users set role = 'Admin' where id = $1::uuid")
+        .bind(&auth.id)
+        .execute(&server.test_db.pool())
+        .await
+        .expect("Failed to promote user to admin.");
+
+    // The role is part of the token, so only a new one makes the user an admin.
+    server
+        .client
+        .login(LoginRequest {
+            username: "ilmari".to_string(),
+            password: PASSWORD.to_string(),
+        })
+        .await
+        .expect("Failed to log in")
+        .into_inner()
+}
+
+async fn watch(server: &mut TestServer, token: &str) -> Streaming<UserEvent> {
+    server
+        .client
+        .watch_user_events(authorized(WatchUserEventsRequest {}, token))
+        .await
+        .expect("Failed to watch user events")
+        .into_inner()
+}
+
+async fn next_event(events: &mut Streaming<UserEvent>) -> UserEvent {
+    timeout(Duration::from_secs(5), events.message())
+        .await
+        .expect("No user event within 5s")
+        .expect("Failed to receive user event")
+        .expect("User event stream ended")
+}
+
+#[tokio::test]
+async fn watch_user_events_streams_the_lifecycle_of_users() {
+    let mut server = spawn_test_server().await;
+    println!("db_name={} port={}", server.test_db.db_name, server.port);
+
+    let admin = create_admin(&mut server).await;
+    let mut events = watch(&mut server, &admin.token).await;
+
+    server
+        .client
+        .create_user(Request::new(user_create("synul")))
+        .await
+        .expect("Failed to create user");
+    let synul = server
+        .client
+        .login(LoginRequest {
+            username: "synul".to_string(),
+            password: PASSWORD.to_string(),
+        })
+        .await
+        .expect("Failed to log in")
+        .into_inner();
+    server
+        .client
+        .update_user(authorized(
+            UpdateUserRequest {
+                id: synul.id.clone(),
+                username: "lunys".to_string(),
+            },
+            &synul.token,
+        ))
+        .await
+        .expect("Failed to update user");
+    // Deleted by the admin, so the actor differs from the user.
+    server
+        .client
+        .delete_user(authorized(
+            DeleteUserRequest {
+                id: synul.id.clone(),
+            },
+            &admin.token,
+        ))
+        .await
+        .expect("Failed to delete user");
+
+    let expected = [
+        (Kind::Created, &synul.id),
+        (Kind::LoggedIn, &synul.id),
+        (Kind::Updated, &synul.id),
+        (Kind::Deleted, &admin.id),
+    ];
+    for (kind, actor_id) in expected {
+        let event = next_event(&mut events).await;
+        assert_eq!(event.kind, kind as i32, "Unexpected event {:?}", event);
+        assert_eq!(&event.user_id, &synul.id);
+        assert_eq!(&event.actor_id, actor_id);
+        assert!(chrono::DateTime::parse_from_rfc3339(&event.occurred_at).is_ok());
+    }
+}
+
+#[tokio::test]
+async fn import_users_returns_a_summary() {
+    let mut server = spawn_test_server().await;
+    println!("db_name={} port={}", server.test_db.db_name, server.port);
+
+    let admin = create_admin(&mut server).await;
+    let mut events = watch(&mut server, &admin.token).await;
+
+    let users = vec![
+        user_create("alpha"),
+        user_create("beta"),
+        user_create("g"),
+        user_create("alpha"),
+    ];
+    let summary = server
+        .client
+        .import_users(authorized(tokio_stream::iter(users), &admin.token))
+        .await
+        .expect("Failed to import users")
+        .into_inner();
+
+    assert_eq!(summary.created, 2);
+    assert_eq!(summary.failed, 2);
+    let failures: Vec<_> = summary
+        .failures
+        .iter()
+        .map(|failure| (failure.index, failure.username.as_str()))
+        .collect();
+    assert_eq!(failures, [(2, "g"), (3, "alpha")]);
+    assert!(summary.failures[0].reason.starts_with("username:"));
+    assert!(summary.failures[1].reason.starts_with("username:"));
+
+    // The imported users can log in right away.
+    let alpha = server
+        .client
+        .login(LoginRequest {
+            username: "alpha".to_string(),
+            password: PASSWORD.to_string(),
+        })
+        .await
+        .expect("Failed to log in imported user")
+        .into_inner();
+
+    let event = next_event(&mut events).await;
+    assert_eq!(event.kind, Kind::Created as i32);
+    assert_eq!(event.user_id, alpha.id);
+    assert_eq!(event.actor_id, admin.id);
+    let event = next_event(&mut events).await;
+    assert_eq!(event.kind, Kind::Created as i32);
+    assert_eq!(event.actor_id, admin.id);
+}
+
+#[tokio::test]
+async fn only_admins_may_watch_and_import() {
+    let mut server = spawn_test_server().await;
+    println!("db_name={} port={}", server.test_db.db_name, server.port);
+
+    let synul = server
+        .client
+        .create_user(Request::new(user_create("synul")))
+        .await
+        .expect("Failed to create user")
+        .into_inner();
+
+    let status = server
+        .client
+        .watch_user_events(authorized(WatchUserEventsRequest {}, &synul.token))
+        .await
+        .expect_err("A user watched user events");
+    assert_eq!(status.code(), Code::PermissionDenied);
+
+    let status = server
+        .client
+        .watch_user_events(Request::new(WatchUserEventsRequest {}))
+        .await
+        .expect_err("Watched user events without a token");
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    let status = server
+        .client
+        .import_users(authorized(
+            tokio_stream::iter(vec![user_create("alpha")]),
+            &synul.token,
+        ))
+        .await
+        .expect_err("A user imported users");
+    assert_eq!(status.code(), Code::PermissionDenied);
+}
+
+#[tokio::test]
+async fn demoted_admins_and_revoked_sessions_lose_access() {
+    let mut server = spawn_test_server().await;
+    println!("db_name={} port={}", server.test_db.db_name, server.port);
+
+    let admin = create_admin(&mut server).await;
+    watch(&mut server, &admin.token).await;
+
+    // The token still claims the admin role, but the database is asked.
+    sqlx::query("update users set role = 'User' where id = $1::uuid")
+        .bind(&admin.id)
+        .execute(&server.test_db.pool())
+        .await
+        .expect("Failed to demote admin.");
+    let status = server
+        .client
+        .watch_user_events(authorized(WatchUserEventsRequest {}, &admin.token))
+        .await
+        .expect_err("A demoted admin watched user events");
+    assert_eq!(status.code(), Code::PermissionDenied);
+
+    server
+        .client
+        .get_user(authorized(
+            GetUserRequest {
+                id: admin.id.clone(),
+            },
+            &admin.token,
+        ))
+        .await
+        .expect("Failed to get user");
+
+    // Like logging out everywhere.
+    sqlx::query("update auth_tokens set revoked_at = now() where user_id = $1::uuid")
+        .bind(&admin.id)
+        .execute(&server.test_db.pool())
+        .await
+        .expect("Failed to revoke sessions.");
+    let status = server
+        .client
+        .get_user(authorized(
+            GetUserRequest {
+                id: admin.id.clone(),
+            },
+            &admin.token,
+        ))
+        .await
+        .expect_err("A revoked session got a user");
+    assert_eq!(status.code(), Code::Unauthenticated);
+}
@@ -0,0 +1,174 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use sqlx::{Connection, Executor, PgConnection, PgPool};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, Duration};
+use tonic::transport::Channel;
+use tonic::Request;
+
+use alloxid_core::auth::LoginThrottle;
+use alloxid_core::events::UserEvents;
+use alloxid_core::settings::Settings;
+use alloxid_grpc::user::user_service_client::UserServiceClient;
+use alloxid_grpc::user::UserCreate;
+use alloxid_grpc::BoxError;
+
+#[allow(dead_code)]
+pub const PASSWORD: &str = "correct horse battery";
+
+pub struct TestServer {
+    #[allow(dead_code)]
+    pub channel: Channel,
+    #[allow(dead_code)]
+    pub client: UserServiceClient<Channel>,
+    // Resolves once the server has shut down.
+    #[allow(dead_code)]
+    pub server: JoinHandle<Result<(), BoxError>>,
+    // Starts the graceful shutdown.
+    #[allow(dead_code)]
+    pub shutdown: Option<oneshot::Sender<()>>,
+    // We want to keep this alive until the end of the test.
+    #[allow(dead_code)]
+    pub test_db: TestDb,
+    #[allow(dead_code)]
+    pub port: usize,
+}
+
+#[derive(Debug)]
+pub struct TestDb {
+    pub db_name: String,
+    db_pool: PgPool,
+    conn_string: String,
+}
+
+impl TestDb {
+    pub async fn new(settings: &Settings) -> Self {
+        let Settings { database, .. } = settings;
+
+        let conn_string = database.conn_string();
+        let mut pg_conn = PgConnection::connect(&conn_string)
+            .await
+            .expect("Failed to connect to Postgres.");
+        pg_conn
+            .execute(&*format!(r#"CREATE DATABASE "{}";"#, database.name))
+            .await
+            .expect("Failed to create database.");
+
+        let db_pool = PgPool::connect(&database.full_url())
+            .await
+            .expect("Failed to connect to database.");
+        // The migrations are maintained along with `alloxid-http`.
+        sqlx::migrate!("../alloxid-http/migrations")
+            .run(&db_pool)
+            .await
+            .expect("Failed to migrate the database");
+
+        Self {
+            db_name: database.name(),
+            db_pool,
+            conn_string,
+        }
+    }
+
+    pub fn pool(&self) -> PgPool {
+        self.db_pool.clone()
+    }
+}
+
+impl Drop for TestDb {
+    fn drop(&mut self) {
+        async_std::task::block_on(self.db_pool.close());
+        async_std::task::block_on(drop_db(&self.conn_string, &self.db_name));
+    }
+}
+
+#[allow(dead_code)]
+pub async fn spawn_test_server() -> TestServer {
+    let settings = Settings::new_for_test().expect("Failed to load configuration.");
+
+    spawn_test_server_with_settings(settings).await
+}
+
+pub async fn spawn_test_server_with_settings(mut settings: Settings) -> TestServer {
+    let test_db = TestDb::new(&settings).await;
+
+    let port = settings.app.port;
+    let addr = SocketAddr::from(([127, 0, 0, 1], port as u16));
+    settings.grpc.address = addr.to_string();
+
+    let events = UserEvents::new(settings.grpc.event_capacity);
+    let login_throttle = Arc::new(LoginThrottle::new(settings.auth.login_throttle.clone()));
+    let (shutdown, shutdown_rx) = oneshot::channel::<()>();
+    let server = tokio::spawn(alloxid_grpc::serve(
+        test_db.pool(),
+        settings,
+        events,
+        login_throttle,
+        async {
+            let _ = shutdown_rx.await;
+        },
+    ));
+
+    sleep(Duration::from_millis(100)).await;
+
+    let channel = Channel::from_shared(format!("http://{}", addr))
+        .expect("Failed to parse address")
+        .connect()
+        .await
+        .expect("Failed to connect client");
+
+    TestServer {
+        channel: channel.clone(),
+        client: UserServiceClient::new(channel),
+        server,
+        shutdown: Some(shutdown),
+        test_db,
+        port,
+    }
+}
+
+#[allow(dead_code)]
+pub fn authorized<T>(message: T, token: &str) -> Request<T> {
+    let mut request = Request::new(message);
+    request.metadata_mut().insert(
+        "authorization",
+        format!("Bearer {}", token)
+            .parse()
+            .expect("Failed to parse authorization metadata"),
+    );
+    request
+}
+
+#[allow(dead_code)]
+pub fn user_create(username: &str) -> UserCreate {
+    UserCreate {
+        username: username.to_string(),
+        password: PASSWORD.to_string(),
+        email: String::new(),
+    }
+}
+
+async fn drop_db(conn_string: &str, db_name: &str) {
+    let mut conn = PgConnection::connect(conn_string)
+        .await
+        .expect("Failed to connect to Postgres.");
+
+    // Disconnect any existing connections to the DB
+    conn.execute(&*format!(
+        r#"
+        SELECT pg_terminate_backend(pg_stat_activity.pid)
+        FROM pg_stat_activity
+        WHERE pg_stat_activity.datname = '{}'
+        AND pid <> pg_backend_pid();
+        "#,
+        db_name
+    ))
+    .await
+    .expect("Failed to drop existing connections to database.");
+
+    conn.execute(&*format!(r#"DROP DATABASE "{}";"#, db_name))
+        .await
+        .expect("Failed to drop database.");
+}
@@ -0,0 +1,114 @@
+use prost::Message;
+use tokio::time::{sleep, timeout, Duration};
+use tonic::Code;
+use tonic_health::proto::health_check_response::ServingStatus;
+use tonic_health::proto::health_client::HealthClient;
+use tonic_health::proto::HealthCheckRequest;
+
+use alloxid_core::settings::Settings;
+
+mod helpers;
+use helpers::spawn_test_server_with_settings;
+
+async fn check(client: &mut HealthClient<tonic::transport::Channel>, service: &str) -> i32 {
+    client
+        .check(HealthCheckRequest {
+            service: service.to_string(),
+        })
+        .await
+        .expect("Failed to check health")
+        .into_inner()
+        .status
+}
+
+#[tokio::test]
+async fn health_is_reported_per_service() {
+    let mut settings = Settings::new_for_test().expect("Failed to load configuration.");
+    settings.grpc.health_check_seconds = 1;
+    let server = spawn_test_server_with_settings(settings).await;
+    println!("db_name={} port={}", server.test_db.db_name, server.port);
+
+    let mut client = HealthClient::new(server.channel.clone());
+    for service in ["", "hello.Greeter", "user.UserService"] {
+        assert_eq!(
+            check(&mut client, service).await,
+            ServingStatus::Serving as i32,
+            "{} isn't serving",
+            service
+        );
+    }
+
+    let status = client
+        .check(HealthCheckRequest {
+            service: "unknown.Service".to_string(),
+        })
+        .await
+        .expect_err("Unknown service has a status");
+    assert_eq!(status.code(), Code::NotFound);
+
+    // Without a database, only the UserService is affected.
+    server.test_db.pool().close().await;
+    let mut user_service_status = ServingStatus::Serving as i32;
+    for _ in 0..30 {
+        user_service_status = check(&mut client, "user.UserService").await;
+        if user_service_status != ServingStatus::Serving as i32 {
+            break;
+        }
+        sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(user_service_status, ServingStatus::NotServing as i32);
+    assert_eq!(
+        check(&mut client, "hello.Greeter").await,
+        ServingStatus::Serving as i32
+    );
+}
+
+#[tokio::test]
+async fn shutdown_reports_not_serving_and_drains_calls() {
+    let mut settings = Settings::new_for_test().expect("Failed to load configuration.");
+    settings.grpc.shutdown_grace_seconds = 1;
+    let mut server = spawn_test_server_with_settings(settings).await;
+    println!("db_name={} port={}", server.test_db.db_name, server.port);
+
+    // The watch stream stays open, so it's still in flight when the server shuts down.
+    let mut client = HealthClient::new(server.channel.clone());
+    let mut statuses = client
+        .watch(HealthCheckRequest {
+            service: String::new(),
+        })
+        .await
+        .expect("Failed to watch health")
+        .into_inner();
+    let first = statuses.message().await.unwrap().unwrap();
+    assert_eq!(first.status, ServingStatus::Serving as i32);
+
+    server.shutdown.take().unwrap().send(()).unwrap();
+
+    let next = statuses.message().await.unwrap().unwrap();
+    assert_eq!(next.status, ServingStatus::NotServing as i32);
+
+    timeout(Duration::from_secs(5), &mut server.server)
+        .await
+        .expect("Server didn't stop after the grace period")
+        .expect("Server task panicked")
+        .expect("Server failed");
+}
+
+#[test]
+fn reflection_describes_all_services() {
+    let descriptors = prost_types::FileDescriptorSet::decode(alloxid_grpc::FILE_DESCRIPTOR_SET)
+        .expect("Failed to decode file descriptor set");
+
+    let services: Vec<String> = descriptors
+        .file
+        .iter()
+        .flat_map(|file| {
+            file.service
+                .iter()
+                .map(move |service| format!("{}.{}", file.package(), service.name()))
+        })
+        .collect();
+
+    assert!(services.contains(&"hello.Greeter".to_string()));
+    assert!(services.contains(&"user.UserService".to_string()));
+}
@@ -0,0 +1,257 @@
+use tonic::{Code, Request};
+
+use alloxid_grpc::user::{DeleteUserRequest, GetUserRequest, LoginRequest, UpdateUserRequest};
+
+mod helpers;
+use helpers::{authorized, spawn_test_server, user_create, PASSWORD};
+
+#[tokio::test]
+async fn create_get_update_and_delete_user() {
+    let mut server = spawn_test_server().await;
+    println!("db_name={} port={}", server.test_db.db_name, server.port);
+
+    let auth = server
+        .client
+        .create_user(Request::new(user_create("synul")))
+        .await
+        .expect("Failed to create user")
+        .into_inner();
+    assert!(!auth.token.is_empty());
+    assert!(!auth.refresh_token.is_empty());
+
+    let user = server
+        .client
+        .get_user(authorized(
+            GetUserRequest {
+                id: auth.id.clone(),
+            },
+            &auth.token,
+        ))
+        .await
+        .expect("Failed to get user")
+        .into_inner();
+    assert_eq!(user.username, "synul");
+    assert_eq!(user.email, "");
+
+    let user = server
+        .client
+        .update_user(authorized(
+            UpdateUserRequest {
+                id: auth.id.clone(),
+                username: "lunys".to_string(),
+            },
+            &auth.token,
+        ))
+        .await
+        .expect("Failed to update user")
+        .into_inner();
+    assert_eq!(user.username, "lunys");
+
+    server
+        .client
+        .delete_user(authorized(
+            DeleteUserRequest {
+                id: auth.id.clone(),
+            },
+            &auth.token,
+        ))
+        .await
+        .expect("Failed to delete user");
+
+    let status = server
+        .client
+        .get_user(authorized(GetUserRequest { id: auth.id }, &auth.token))
+        .await
+        .expect_err("Deleted user was found");
+    // Deleting the user ended its sessions.
+    assert_eq!(status.code(), Code::Unauthenticated);
+}
+
+#[tokio::test]
+async fn login_returns_tokens_for_valid_credentials() {
+    let mut server = spawn_test_server().await;
+    println!("db_name={} port={}", server.test_db.db_name, server.port);
+
+    let created = server
+        .client
+        .create_user(user_create("synul"))
+        .await
+        .expect("Failed to create user")
+        .into_inner();
+
+    let auth = server
+        .client
+        .login(LoginRequest {
+            username: "synul".to_string(),
+            password: PASSWORD.to_string(),
+        })
+        .await
+        .expect("Failed to log in")
+        .into_inner();
+    assert_eq!(auth.id, created.id);
+    assert!(auth.expires_in > 0);
+
+    let status = server
+        .client
+        .login(LoginRequest {
+            username: "synul".to_string(),
+            password: "wrong password".to_string(),
+        })
+        .await
+        .expect_err("Logged in with a wrong password");
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    // The login is recorded in the same audit log as the logins at alloxid-http.
+    let actions: Vec<String> = sqlx::query_scalar(
+        "select action from audit_events where subject_id = $1::uuid order by created_at",
+    )
+    .bind(&created.id)
+    .fetch_all(&server.test_db.pool())
+    .await
+    .expect("Failed to query audit events.");
+    assert_eq!(
+        actions,
+        vec!["user.created", "user.login", "user.login_failed"]
+    );
+}
+
+#[tokio::test]
+async fn domain_errors_are_mapped_to_status_codes() {
+    let mut server = spawn_test_server().await;
+    println!("db_name={} port={}", server.test_db.db_name, server.port);
+
+    let auth = server
+        .client
+        .create_user(user_create("synul"))
+        .await
+        .expect("Failed to create user")
+        .into_inner();
+
+    let status = server
+        .client
+        .create_user(user_create("synul"))
+        .await
+        .expect_err("Created the same user twice");
+    assert_eq!(status.code(), Code::AlreadyExists);
+
+    let status = server
+        .client
+        .create_user(user_create("a"))
+        .await
+        .expect_err("Created a user with an invalid username");
+    assert_eq!(status.code(), Code::InvalidArgument);
+
+    let status = server
+        .client
+        .get_user(authorized(
+            GetUserRequest {
+                id: "not-a-uuid".to_string(),
+            },
+            &auth.token,
+        ))
+        .await
+        .expect_err("Got a user with an invalid id");
+    assert_eq!(status.code(), Code::InvalidArgument);
+
+    // Admins may act on any user, so that's the only way to ask for one that doesn't exist.
+    sqlx::query("update users set role = 'Admin' where id = $1::uuid")
+        .bind(&auth.id)
+        .execute(&server.test_db.pool())
+        .await
+        .expect("Failed to promote user to admin.");
+    let admin = server
+        .client
+        .login(LoginRequest {
+            username: "synul".to_string(),
+            password: PASSWORD.to_string(),
+        })
+        .await
+        .expect("Failed to log in")
+        .into_inner();
+
+    let status = server
+        .client
+        .delete_user(authorized(
+            DeleteUserRequest {
+                id: uuid::Uuid::new_v4().to_string(),
+            },
+            &admin.token,
+        ))
+        .await
+        .expect_err("Deleted a user that doesn't exist");
+    assert_eq!(status.code(), Code::NotFound);
+}
+
+#[tokio::test]
+async fn calls_are_authenticated_by_token() {
+    let mut server = spawn_test_server().await;
+    println!("db_name={} port={}", server.test_db.db_name, server.port);
+
+    let synul = server
+        .client
+        .create_user(user_create("synul"))
+        .await
+        .expect("Failed to create user")
+        .into_inner();
+    let lunys = server
+        .client
+        .create_user(user_create("lunys"))
+        .await
+        .expect("Failed to create user")
+        .into_inner();
+
+    let status = server
+        .client
+        .get_user(GetUserRequest {
+            id: synul.id.clone(),
+        })
+        .await
+        .expect_err("Got a user without a token");
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    let status = server
+        .client
+        .get_user(authorized(
+            GetUserRequest {
+                id: synul.id.clone(),
+            },
+            "not-a-jwt",
+        ))
+        .await
+        .expect_err("Got a user with an invalid token");
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    // Even public methods reject invalid tokens.
+    let status = server
+        .client
+        .login(authorized(
+            LoginRequest {
+                username: "synul".to_string(),
+                password: PASSWORD.to_string(),
+            },
+            "not-a-jwt",
+        ))
+        .await
+        .expect_err("Logged in with an invalid token");
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    let status = server
+        .client
+        .delete_user(authorized(
+            DeleteUserRequest {
+                id: synul.id.clone(),
+            },
+            &lunys.token,
+        ))
+        .await
+        .expect_err("Deleted another user");
+    assert_eq!(status.code(), Code::PermissionDenied);
+
+    let user = server
+        .client
+        .get_user(authorized(GetUserRequest { id: synul.id }, &synul.token))
+        .await
+        .expect("Failed to get user")
+        .into_inner();
+    assert_eq!(user.username, "synul");
+}
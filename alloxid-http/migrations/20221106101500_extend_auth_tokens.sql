@@ -0,0 +1,13 @@
+-- Rows so far held copies of the issued JWTs, they are replaced by hashed refresh tokens.
+DELETE FROM auth_tokens;
+
+ALTER TABLE auth_tokens RENAME COLUMN token TO token_hash;
+
+ALTER TABLE auth_tokens
+    ADD COLUMN family_id UUID NOT NULL,
+    ADD COLUMN created_at TIMESTAMP WITH time zone NOT NULL,
+    ADD COLUMN expires_at TIMESTAMP WITH time zone NOT NULL,
+    ADD COLUMN revoked_at TIMESTAMP WITH time zone;
+
+CREATE UNIQUE INDEX auth_tokens_token_hash_idx ON auth_tokens (token_hash);
+CREATE INDEX auth_tokens_family_id_idx ON auth_tokens (family_id);
@@ -0,0 +1,4 @@
+ALTER TABLE users
+    ADD COLUMN role VARCHAR NOT NULL DEFAULT 'User',
+    ADD COLUMN disabled_at TIMESTAMP WITH time zone,
+    ADD COLUMN password_reset_required BOOLEAN NOT NULL DEFAULT false;
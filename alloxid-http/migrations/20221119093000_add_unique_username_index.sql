@@ -0,0 +1,16 @@
+-- Usernames that only differ in case used to be accepted. The oldest account keeps its name, the
+-- others get the start of their id appended, which they can change afterwards.
+UPDATE users
+SET username = username || '-' || substr(id::text, 1, 8), updated_at = now()
+WHERE id IN (
+    SELECT id FROM (
+        SELECT id, row_number() OVER (
+            PARTITION BY lower(username) ORDER BY created_at, id
+        ) AS position
+        FROM users
+    ) AS ranked
+    WHERE position > 1
+);
+
+-- Usernames are unique regardless of case, so that login can't match more than one row.
+CREATE UNIQUE INDEX users_username_lower_idx ON users (lower(username));
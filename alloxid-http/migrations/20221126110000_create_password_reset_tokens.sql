@@ -0,0 +1,11 @@
+CREATE TABLE password_reset_tokens (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id),
+    token_hash VARCHAR NOT NULL,
+    created_at TIMESTAMP WITH time zone NOT NULL,
+    expires_at TIMESTAMP WITH time zone NOT NULL,
+    used_at TIMESTAMP WITH time zone
+);
+
+CREATE UNIQUE INDEX password_reset_tokens_token_hash_idx ON password_reset_tokens (token_hash);
+CREATE INDEX password_reset_tokens_user_id_idx ON password_reset_tokens (user_id);
@@ -0,0 +1,9 @@
+ALTER TABLE users
+    -- Base32 encoded TOTP secret, set on enrollment.
+    ADD COLUMN totp_secret VARCHAR,
+    -- Set once the enrollment has been confirmed with a first code.
+    ADD COLUMN totp_enabled_at TIMESTAMP WITH time zone,
+    -- The last accepted time step, so that codes can't be replayed.
+    ADD COLUMN totp_last_step BIGINT,
+    -- Hashes of the unused recovery codes.
+    ADD COLUMN totp_recovery_codes VARCHAR[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,16 @@
+CREATE TABLE api_keys (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id),
+    name VARCHAR NOT NULL,
+    -- The first characters of the key, so that users can tell their keys apart.
+    prefix VARCHAR NOT NULL,
+    key_hash VARCHAR NOT NULL,
+    scopes VARCHAR[] NOT NULL,
+    created_at TIMESTAMP WITH time zone NOT NULL,
+    expires_at TIMESTAMP WITH time zone,
+    last_used_at TIMESTAMP WITH time zone,
+    revoked_at TIMESTAMP WITH time zone
+);
+
+CREATE UNIQUE INDEX api_keys_key_hash_idx ON api_keys (key_hash);
+CREATE INDEX api_keys_user_id_idx ON api_keys (user_id);
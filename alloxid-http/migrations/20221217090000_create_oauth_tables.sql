@@ -0,0 +1,24 @@
+CREATE TABLE oauth_clients (
+    id VARCHAR PRIMARY KEY,
+    name VARCHAR NOT NULL,
+    -- Public clients, like single page apps, have no secret and rely on PKCE alone.
+    secret_hash VARCHAR,
+    redirect_uris VARCHAR[] NOT NULL,
+    created_at TIMESTAMP WITH time zone NOT NULL
+);
+
+CREATE TABLE oauth_authorization_codes (
+    id UUID PRIMARY KEY,
+    code_hash VARCHAR NOT NULL,
+    client_id VARCHAR NOT NULL REFERENCES oauth_clients(id),
+    user_id UUID NOT NULL REFERENCES users(id),
+    redirect_uri VARCHAR NOT NULL,
+    scope VARCHAR NOT NULL,
+    nonce VARCHAR,
+    code_challenge VARCHAR NOT NULL,
+    created_at TIMESTAMP WITH time zone NOT NULL,
+    expires_at TIMESTAMP WITH time zone NOT NULL,
+    used_at TIMESTAMP WITH time zone
+);
+
+CREATE UNIQUE INDEX oauth_authorization_codes_code_hash_idx ON oauth_authorization_codes (code_hash);
@@ -0,0 +1,17 @@
+ALTER TABLE users ADD COLUMN email VARCHAR(254);
+ALTER TABLE users ADD COLUMN email_verified_at TIMESTAMP WITH time zone;
+
+-- Like usernames, addresses are unique regardless of case, so that login can't match more than one row.
+CREATE UNIQUE INDEX users_email_lower_idx ON users (lower(email));
+
+-- Verification links carry a signed token, this table makes them single-use.
+CREATE TABLE email_verifications (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id),
+    email VARCHAR(254) NOT NULL,
+    created_at TIMESTAMP WITH time zone NOT NULL,
+    expires_at TIMESTAMP WITH time zone NOT NULL,
+    used_at TIMESTAMP WITH time zone
+);
+
+CREATE INDEX email_verifications_user_id_idx ON email_verifications (user_id);
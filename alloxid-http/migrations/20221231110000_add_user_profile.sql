@@ -0,0 +1,7 @@
+ALTER TABLE users ADD COLUMN display_name VARCHAR;
+ALTER TABLE users ADD COLUMN bio VARCHAR;
+ALTER TABLE users ADD COLUMN avatar_url VARCHAR;
+-- A BCP 47 language tag, e.g. "de-CH".
+ALTER TABLE users ADD COLUMN locale VARCHAR;
+-- An IANA time zone name, e.g. "Europe/Zurich".
+ALTER TABLE users ADD COLUMN timezone VARCHAR;
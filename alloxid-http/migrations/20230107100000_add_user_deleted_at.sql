@@ -0,0 +1,3 @@
+-- Deleted users are kept for a grace period in which they can restore their account.
+ALTER TABLE users ADD COLUMN deleted_at TIMESTAMP WITH time zone;
+CREATE INDEX users_deleted_at_idx ON users (deleted_at) WHERE deleted_at IS NOT NULL;
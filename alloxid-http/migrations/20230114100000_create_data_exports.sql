@@ -0,0 +1,13 @@
+CREATE TABLE data_exports (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id),
+    -- One of "pending", "ready" or "failed".
+    status VARCHAR NOT NULL,
+    data JSONB,
+    created_at TIMESTAMP WITH time zone NOT NULL,
+    completed_at TIMESTAMP WITH time zone,
+    -- Expired exports are removed together with their data.
+    expires_at TIMESTAMP WITH time zone NOT NULL
+);
+
+CREATE INDEX data_exports_user_id_idx ON data_exports (user_id);
@@ -0,0 +1,19 @@
+-- Security-relevant events. Users aren't referenced by foreign keys, as events outlive accounts.
+CREATE TABLE audit_events (
+    id UUID PRIMARY KEY,
+    -- Who did it, missing for anonymous requests like failed logins of unknown users.
+    actor_id UUID,
+    -- The user it has been done to.
+    subject_id UUID,
+    action VARCHAR NOT NULL,
+    ip VARCHAR,
+    user_agent VARCHAR,
+    request_id UUID,
+    details JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP WITH time zone NOT NULL
+);
+
+CREATE INDEX audit_events_created_at_idx ON audit_events (created_at, id);
+CREATE INDEX audit_events_actor_id_idx ON audit_events (actor_id, created_at);
+CREATE INDEX audit_events_subject_id_idx ON audit_events (subject_id, created_at);
+CREATE INDEX audit_events_action_idx ON audit_events (action, created_at);
@@ -0,0 +1,6 @@
+-- The tokens of the second login step that have been exchanged for a session, so that they can't
+-- be replayed. Rows are purged once the tokens have expired anyway.
+CREATE TABLE used_mfa_tokens (
+    jti UUID PRIMARY KEY,
+    expires_at TIMESTAMP WITH time zone NOT NULL
+);
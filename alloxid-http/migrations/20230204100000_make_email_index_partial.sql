@@ -0,0 +1,4 @@
+-- Only verified addresses are claimed, so that nobody can hold on to somebody else's address by
+-- signing up with it, and signing up doesn't tell whether an address is registered.
+DROP INDEX users_email_lower_idx;
+CREATE UNIQUE INDEX users_email_lower_idx ON users (lower(email)) WHERE email_verified_at IS NOT NULL;
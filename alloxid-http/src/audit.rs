@@ -0,0 +1,94 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+
+use axum::extract::{ConnectInfo, FromRequest, RequestParts};
+use chrono::prelude::*;
+use http::header::USER_AGENT;
+use uuid::Uuid;
+
+use alloxid_core::audit;
+
+use crate::error::ServiceError;
+use crate::model::audit::{AuditAction, AuditOrigin};
+use crate::request_id::RequestId;
+use crate::State;
+
+/// Where a request came from, recorded along with every audit event.
+#[derive(Debug, Default)]
+pub(crate) struct AuditContext(pub AuditOrigin);
+
+#[async_trait::async_trait]
+impl<B> FromRequest<B> for AuditContext
+where
+    B: Send,
+{
+    type Rejection = Infallible;
+
+    async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        let ip = req
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        let user_agent = req
+            .headers()
+            .get(USER_AGENT)
+            .and_then(|header| header.to_str().ok());
+        let request_id = req.extensions().get::<RequestId>().map(|id| id.0);
+
+        Ok(Self(AuditOrigin::new(ip, user_agent, request_id)))
+    }
+}
+
+impl AuditContext {
+    /// Records the event with `alloxid_core::audit::record`, see there.
+    pub(crate) async fn record(
+        &self,
+        state: &State,
+        action: AuditAction,
+        actor_id: Option<Uuid>,
+        subject_id: Option<Uuid>,
+        details: serde_json::Value,
+    ) {
+        audit::record(
+            &state.db_pool,
+            &state.events,
+            &self.0,
+            action,
+            actor_id,
+            subject_id,
+            details,
+        )
+        .await
+    }
+
+    /// Records something the user did to their own account.
+    pub(crate) async fn record_own(
+        &self,
+        state: &State,
+        action: AuditAction,
+        user_id: Uuid,
+        details: serde_json::Value,
+    ) {
+        self.record(state, action, Some(user_id), Some(user_id), details)
+            .await
+    }
+}
+
+/// Encodes the position after the given event, the events are ordered by time and id.
+pub(crate) fn encode_cursor(created_at: DateTime<Utc>, id: &Uuid) -> String {
+    let cursor = format!("{}_{}", created_at.timestamp_nanos(), id);
+    base64::encode_config(cursor, base64::URL_SAFE_NO_PAD)
+}
+
+pub(crate) fn decode_cursor(cursor: &str) -> Result<(DateTime<Utc>, Uuid), ServiceError> {
+    let invalid = || ServiceError::BadRequest("Invalid cursor.".to_string());
+
+    let cursor = base64::decode_config(cursor, base64::URL_SAFE_NO_PAD).map_err(|_| invalid())?;
+    let cursor = String::from_utf8(cursor).map_err(|_| invalid())?;
+    let (nanos, id) = cursor.split_once('_').ok_or_else(invalid)?;
+
+    let nanos = nanos.parse::<i64>().map_err(|_| invalid())?;
+    let id = id.parse::<Uuid>().map_err(|_| invalid())?;
+
+    Ok((Utc.timestamp_nanos(nanos), id))
+}
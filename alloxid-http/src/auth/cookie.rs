@@ -0,0 +1,164 @@
+use axum::body::Body;
+use hmac::{Hmac, Mac};
+use http::header::{COOKIE, SET_COOKIE};
+use http::{HeaderMap, HeaderValue, Response};
+use sha2::Sha256;
+use uuid::Uuid;
+
+use super::Claims;
+use crate::error::ServiceError;
+use crate::model::user::UserAuthData;
+use crate::settings::{SameSite, Settings};
+use crate::State;
+
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+// The refresh cookie is only needed by the refresh and logout endpoints.
+const REFRESH_COOKIE_PATH: &str = "/user";
+
+/// Reads a cookie from the `Cookie` headers of a request.
+pub(crate) fn get_cookie<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|header| header.to_str().ok())
+        .flat_map(|header| header.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+/// The CSRF token of a session. It's derived from the session id, so it doesn't have to be
+/// stored and can't be set by anybody who doesn't know the app secret.
+pub(crate) fn csrf_token(secret: &str, session_id: &Uuid) -> String {
+    let mac = csrf_mac(secret, session_id).finalize().into_bytes();
+    base64::encode_config(mac, base64::URL_SAFE_NO_PAD)
+}
+
+pub(crate) fn verify_csrf_token(secret: &str, session_id: &Uuid, token: &str) -> bool {
+    match base64::decode_config(token, base64::URL_SAFE_NO_PAD) {
+        Ok(mac) => csrf_mac(secret, session_id).verify_slice(&mac).is_ok(),
+        Err(_) => false,
+    }
+}
+
+fn csrf_mac(secret: &str, session_id: &Uuid) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any size");
+    mac.update(b"csrf:");
+    mac.update(session_id.as_bytes());
+    mac
+}
+
+/// Sets the tokens as cookies, if the cookie mode is enabled.
+pub(crate) fn set_session_cookies(
+    res: &mut Response<Body>,
+    state: &State,
+    data: &UserAuthData,
+) -> Result<(), ServiceError> {
+    let settings = &state.settings;
+    let names = &settings.auth.session_cookie;
+    if !names.enabled {
+        return Ok(());
+    }
+
+    let session_id = state.keys.decode::<Claims>(&data.token)?.claims.jti;
+    let refresh_max_age = settings.auth.refresh_token_days * 24 * 60 * 60;
+
+    let cookies = [
+        cookie(
+            settings,
+            &names.access_name,
+            &data.token,
+            "/",
+            data.expires_in,
+            true,
+        ),
+        cookie(
+            settings,
+            &names.refresh_name,
+            &data.refresh_token,
+            REFRESH_COOKIE_PATH,
+            refresh_max_age,
+            true,
+        ),
+        // Scripts read this one to send it back in the CSRF header.
+        cookie(
+            settings,
+            &names.csrf_name,
+            &csrf_token(&settings.app.secret, &session_id),
+            "/",
+            refresh_max_age,
+            false,
+        ),
+    ];
+
+    for cookie in cookies {
+        res.headers_mut().append(SET_COOKIE, cookie?);
+    }
+
+    Ok(())
+}
+
+/// Expires all session cookies, if the cookie mode is enabled.
+pub(crate) fn clear_session_cookies(
+    res: &mut Response<Body>,
+    settings: &Settings,
+) -> Result<(), ServiceError> {
+    let names = &settings.auth.session_cookie;
+    if !names.enabled {
+        return Ok(());
+    }
+
+    let cookies = [
+        cookie(settings, &names.access_name, "", "/", 0, true),
+        cookie(
+            settings,
+            &names.refresh_name,
+            "",
+            REFRESH_COOKIE_PATH,
+            0,
+            true,
+        ),
+        cookie(settings, &names.csrf_name, "", "/", 0, false),
+    ];
+
+    for cookie in cookies {
+        res.headers_mut().append(SET_COOKIE, cookie?);
+    }
+
+    Ok(())
+}
+
+fn cookie(
+    settings: &Settings,
+    name: &str,
+    value: &str,
+    path: &str,
+    max_age: i64,
+    http_only: bool,
+) -> Result<HeaderValue, ServiceError> {
+    let options = &settings.auth.session_cookie;
+
+    let mut cookie = format!(
+        "{}={}; Path={}; Max-Age={}; SameSite={}",
+        name,
+        value,
+        path,
+        max_age,
+        match options.same_site {
+            SameSite::Strict => "Strict",
+            SameSite::Lax => "Lax",
+            SameSite::None => "None",
+        }
+    );
+    if http_only {
+        cookie.push_str("; HttpOnly");
+    }
+    if options.secure {
+        cookie.push_str("; Secure");
+    }
+
+    HeaderValue::from_str(&cookie)
+        .map_err(|err| ServiceError::Internal(format!("Invalid cookie {}: {}", name, err)))
+}
@@ -1,24 +1,46 @@
 // Adapted from:
 // https://github.com/launchbadge/realworld-axum-sqlx/blob/main/src/http/extractor.rs
-use axum::extract::{FromRequest, RequestParts};
+use std::sync::Arc;
+
+use axum::extract::{Extension, FromRequest, RequestParts};
 use http::header::AUTHORIZATION;
-use http::HeaderValue;
-use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use http::{HeaderMap, HeaderValue, Method};
 use tracing::instrument;
 use tracing::{debug, error};
+use uuid::Uuid;
 
 use super::ServiceError;
 use super::UserId;
-use super::{Claims, Role, SCHEME_PREFIX, SECRET};
+use super::{cookie, Claims, JwtKeys, Role, Scope, API_KEY_HEADER, CSRF_HEADER, SCHEME_PREFIX};
+use crate::database::{authenticate_api_key, is_session_active};
+use crate::State;
 
+/// A user authenticated either by a JWT or an API key.
 #[derive(Debug)]
 pub struct AuthUser {
     pub user_id: UserId,
+    pub role: Role,
+    pub credential: Credential,
 }
 
+#[derive(Debug)]
+pub enum Credential {
+    // The session (refresh token family) the token belongs to.
+    Session(Uuid),
+    // An API key, which is limited to its scopes.
+    ApiKey { id: Uuid, scopes: Vec<Scope> },
+}
+
+/// An authenticated user with the `Admin` role.
+#[derive(Debug)]
+pub struct AdminUser(pub AuthUser);
+
 impl AuthUser {
-    #[instrument]
-    pub fn from_auth_header(auth_header: &HeaderValue) -> Result<Self, ServiceError> {
+    #[instrument(skip(keys))]
+    pub fn from_auth_header(
+        auth_header: &HeaderValue,
+        keys: &JwtKeys,
+    ) -> Result<Self, ServiceError> {
         debug!("Called with auth_header: {:?}", &auth_header);
 
         let auth_header = auth_header.to_str().map_err(|_| {
@@ -33,29 +55,180 @@ impl AuthUser {
             return Err(ServiceError::Unauthorized);
         }
 
-        let token = auth_header.trim_start_matches(SCHEME_PREFIX);
+        Self::from_token(auth_header.trim_start_matches(SCHEME_PREFIX), keys)
+    }
 
-        let decoded = decode::<Claims>(
-            token,
-            &DecodingKey::from_secret(SECRET),
-            &Validation::new(Algorithm::HS512),
-        )
-        .map_err(|err| {
-            error!("Failed to decode token: {:?}", err);
-            ServiceError::TokenExtractionError
-        })?;
+    /// Authenticates by the session cookie. As browsers attach it to any request, including
+    /// forged cross-site ones, state-changing requests also have to echo the CSRF token.
+    #[instrument(skip(headers, state))]
+    pub fn from_session_cookie(
+        method: &Method,
+        headers: &HeaderMap,
+        state: &State,
+    ) -> Result<Self, ServiceError> {
+        let settings = &state.settings;
+        let token = cookie::get_cookie(headers, &settings.auth.session_cookie.access_name)
+            .filter(|token| !token.is_empty())
+            .ok_or(ServiceError::Unauthorized)?;
 
-        // TODO: Move this to a new struct `AdminUser`.
-        let role = Role::User;
-        if role == Role::Admin && Role::from_str(&decoded.claims.role) != Role::Admin {
-            error!("Role permissions not sufficient");
-            return Err(ServiceError::TokenPermissionError);
+        let user = Self::from_token(token, &state.keys)?;
+
+        if !matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS) {
+            let session_id = user.ensure_session()?;
+            let valid = headers
+                .get(CSRF_HEADER)
+                .and_then(|header| header.to_str().ok())
+                .map(|csrf| cookie::verify_csrf_token(&settings.app.secret, &session_id, csrf))
+                .unwrap_or(false);
+
+            if !valid {
+                error!("Missing or invalid CSRF token for {} request", method);
+                return Err(ServiceError::Forbidden);
+            }
+        }
+
+        Ok(user)
+    }
+
+    fn from_token(token: &str, keys: &JwtKeys) -> Result<Self, ServiceError> {
+        let decoded = keys.decode::<Claims>(token)?;
+
+        if decoded.claims.mfa_pending {
+            error!("Token is still waiting for a second factor");
+            return Err(ServiceError::Unauthorized);
         }
 
         Ok(Self {
             user_id: decoded.claims.sub,
+            role: Role::from_str(&decoded.claims.role),
+            credential: Credential::Session(decoded.claims.jti),
         })
     }
+
+    #[instrument(skip(key, state))]
+    pub async fn from_api_key(key: &HeaderValue, state: &State) -> Result<Self, ServiceError> {
+        let key = key.to_str().map_err(|_| {
+            error!("API key header is not UTF-8");
+            ServiceError::Unauthorized
+        })?;
+
+        let owner = authenticate_api_key(&state.db_pool, key)
+            .await?
+            .ok_or_else(|| {
+                error!("API key is unknown, revoked or expired");
+                ServiceError::Unauthorized
+            })?;
+
+        debug!("Authenticated api_key_id={}", owner.id);
+
+        Ok(Self {
+            user_id: UserId::new(owner.user_id),
+            role: Role::from_str(&owner.role),
+            credential: Credential::ApiKey {
+                id: owner.id,
+                scopes: owner
+                    .scopes
+                    .iter()
+                    .filter_map(|scope| Scope::from_str(scope))
+                    .collect(),
+            },
+        })
+    }
+
+    /// API keys of admins only act as admin if they have the `admin` scope.
+    pub fn is_admin(&self) -> bool {
+        self.role == Role::Admin && self.has_scope(Scope::Admin)
+    }
+
+    fn has_scope(&self, scope: Scope) -> bool {
+        match &self.credential {
+            Credential::Session(_) => true,
+            Credential::ApiKey { scopes, .. } => scopes.contains(&scope),
+        }
+    }
+
+    /// Only allows API keys with the given scope, sessions are always allowed.
+    pub fn ensure_scope(&self, scope: Scope) -> Result<(), ServiceError> {
+        if self.has_scope(scope) {
+            return Ok(());
+        }
+
+        error!(
+            "API key of user_id={} lacks scope={}",
+            self.user_id.take(),
+            scope
+        );
+        Err(ServiceError::TokenPermissionError)
+    }
+
+    /// Rejects API keys, which mustn't manage the credentials of their user. Returns the id of
+    /// the session otherwise.
+    pub fn ensure_session(&self) -> Result<Uuid, ServiceError> {
+        match self.credential {
+            Credential::Session(session_id) => Ok(session_id),
+            Credential::ApiKey { id, .. } => {
+                error!("api_key_id={} can't be used to manage credentials", id);
+                Err(ServiceError::Forbidden)
+            }
+        }
+    }
+
+    /// Only allows acting on the given user if it's the authenticated user itself or an admin.
+    pub fn ensure_self_or_admin(&self, user_id: &Uuid) -> Result<(), ServiceError> {
+        if self.user_id.take() == *user_id || self.is_admin() {
+            return Ok(());
+        }
+
+        error!(
+            "user_id={} is not allowed to act on user_id={}",
+            self.user_id.take(),
+            user_id
+        );
+        Err(ServiceError::Forbidden)
+    }
+
+    /// Only allows acting on the given user if it's the authenticated user itself.
+    pub fn ensure_self(&self, user_id: &Uuid) -> Result<(), ServiceError> {
+        if self.user_id.take() == *user_id {
+            return Ok(());
+        }
+
+        error!(
+            "user_id={} is not allowed to act on user_id={}",
+            self.user_id.take(),
+            user_id
+        );
+        Err(ServiceError::Forbidden)
+    }
+
+    /// Makes sure the token's session hasn't been revoked and its user still exists.
+    async fn check_revocation(self, state: &State) -> Result<Self, ServiceError> {
+        let user_id = self.user_id.take();
+        let session_id = match self.credential {
+            Credential::Session(session_id) => session_id,
+            // API keys are checked on every request anyway.
+            Credential::ApiKey { .. } => return Ok(self),
+        };
+
+        let active = match state.revocations.get(&session_id) {
+            Some(active) => active,
+            None => {
+                let active = is_session_active(&state.db_pool, &session_id, &user_id).await?;
+                state.revocations.insert(session_id, user_id, active);
+                active
+            }
+        };
+
+        if !active {
+            error!(
+                "Session session_id={} of user_id={} has been revoked",
+                session_id, user_id
+            );
+            return Err(ServiceError::Unauthorized);
+        }
+
+        Ok(self)
+    }
 }
 
 #[async_trait::async_trait]
@@ -66,12 +239,54 @@ where
     type Rejection = ServiceError;
 
     async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
-        let auth_header = req
-            .headers()
-            //.ok_or(ServiceError::Unauthorized)?
-            .get(AUTHORIZATION)
-            .ok_or(ServiceError::Unauthorized)?;
+        let Extension(state) = Extension::<Arc<State>>::from_request(req)
+            .await
+            .map_err(|err| ServiceError::Internal(err.to_string()))?;
+
+        if let Some(key) = req.headers().get(API_KEY_HEADER) {
+            return Self::from_api_key(key, &state).await;
+        }
+
+        let user = match req.headers().get(AUTHORIZATION) {
+            Some(auth_header) => Self::from_auth_header(auth_header, &state.keys)?,
+            None if state.settings.auth.session_cookie.enabled => {
+                Self::from_session_cookie(req.method(), req.headers(), &state)?
+            }
+            None => return Err(ServiceError::Unauthorized),
+        };
+
+        user.check_revocation(&state).await
+    }
+}
+
+/// The access token a session was authenticated with, from the `Authorization` header or the
+/// session cookie, e.g. to pass it on to `alloxid-grpc`.
+pub fn access_token<'a>(headers: &'a HeaderMap, state: &State) -> Option<&'a str> {
+    match headers.get(AUTHORIZATION) {
+        Some(auth_header) => auth_header.to_str().ok()?.strip_prefix(SCHEME_PREFIX),
+        None if state.settings.auth.session_cookie.enabled => {
+            cookie::get_cookie(headers, &state.settings.auth.session_cookie.access_name)
+        }
+        None => None,
+    }
+}
+
+#[async_trait::async_trait]
+impl<B> FromRequest<B> for AdminUser
+where
+    B: Send,
+{
+    type Rejection = ServiceError;
+
+    async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        let user = AuthUser::from_request(req).await?;
+
+        // Also rejects API keys of admins without the `admin` scope.
+        if !user.is_admin() {
+            error!("Role permissions not sufficient");
+            return Err(ServiceError::TokenPermissionError);
+        }
 
-        Self::from_auth_header(auth_header)
+        Ok(Self(user))
     }
 }
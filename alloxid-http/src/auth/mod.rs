@@ -1,78 +1,131 @@
 use chrono::prelude::*;
-use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
 use serde::{Deserialize, Serialize};
-use std::fmt;
 use uuid::Uuid;
 
+pub(crate) mod cookie;
 pub(crate) mod extractor;
+pub(crate) mod revocation;
+pub use alloxid_core::auth::{
+    authenticate, ensure_allowed, hash_token, session, Claims, JwtKeys, Login, LoginThrottle, Role,
+    Scope, UserId,
+};
+pub(crate) use cookie::{clear_session_cookies, set_session_cookies, CSRF_HEADER};
 pub(crate) use extractor::*;
+pub use revocation::RevocationCache;
 
 use crate::error::ServiceError;
 
 pub const SCHEME_PREFIX: &str = "Bearer ";
-pub const SECRET: &[u8] = b"totally secret";
+pub const API_KEY_HEADER: &str = "x-api-key";
 
-#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
-pub struct UserId(Uuid);
+/// Creates a token that can only be exchanged for a real session at the MFA login endpoint.
+pub fn create_mfa_pending(
+    keys: &JwtKeys,
+    user_id: UserId,
+    ttl: chrono::Duration,
+) -> Result<String, ServiceError> {
+    let exp = Utc::now()
+        .checked_add_signed(ttl)
+        .expect("Failed to create valid timestamp")
+        .timestamp();
 
-impl UserId {
-    pub fn new(uuid: Uuid) -> Self {
-        Self(uuid)
-    }
+    let claims = Claims {
+        sub: user_id,
+        role: String::new(),
+        exp: exp as usize,
+        jti: Uuid::new_v4(),
+        mfa_pending: true,
+    };
 
-    pub fn take(self) -> Uuid {
-        self.0
+    Ok(keys.encode(&claims)?)
+}
+
+/// Returns the claims of a token from `create_mfa_pending`, whose `jti` makes it single-use.
+pub fn decode_mfa_pending(keys: &JwtKeys, token: &str) -> Result<Claims, ServiceError> {
+    let claims = keys.decode::<Claims>(token)?.claims;
+
+    if !claims.mfa_pending {
+        return Err(ServiceError::Unauthorized);
     }
+
+    Ok(claims)
 }
 
 #[derive(Debug, Deserialize, Serialize)]
-struct Claims {
-    // Subject of the token
+struct EmailVerificationClaims {
     sub: UserId,
-    // Custom data point.
-    role: String,
-    // Expiration date.
+    // The address the link has been sent to, which may have changed since.
+    email: String,
     exp: usize,
+    // The id of the row making the link single-use.
+    jti: Uuid,
 }
 
-#[derive(Clone, PartialEq)]
-pub enum Role {
-    Admin,
-    User,
-}
+/// Creates the token of an email verification link.
+pub fn create_email_verification(
+    keys: &JwtKeys,
+    user_id: UserId,
+    email: &str,
+    verification_id: Uuid,
+    expires_at: DateTime<Utc>,
+) -> Result<String, ServiceError> {
+    let claims = EmailVerificationClaims {
+        sub: user_id,
+        email: email.to_string(),
+        exp: expires_at.timestamp() as usize,
+        jti: verification_id,
+    };
 
-impl Role {
-    pub fn from_str(role: &str) -> Self {
-        match role {
-            "Admin" => Self::Admin,
-            _ => Self::User,
-        }
-    }
+    Ok(keys.encode(&claims)?)
 }
 
-impl fmt::Display for Role {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Self::Admin => write!(f, "Admin"),
-            Self::User => write!(f, "User"),
-        }
-    }
+/// Returns the user, email address and verification id of a token from
+/// `create_email_verification`.
+pub fn decode_email_verification(
+    keys: &JwtKeys,
+    token: &str,
+) -> Result<(UserId, String, Uuid), ServiceError> {
+    let claims = keys
+        .decode::<EmailVerificationClaims>(token)
+        .map_err(|_| ServiceError::Unauthorized)?
+        .claims;
+
+    Ok((claims.sub, claims.email, claims.jti))
 }
 
-pub fn create(user_id: UserId, role: Role) -> Result<String, ServiceError> {
-    let exp = Utc::now()
-        .checked_add_signed(chrono::Duration::days(7))
-        .expect("Failed to create valid timestamp")
-        .timestamp();
+#[derive(Debug, Deserialize, Serialize)]
+struct DataExportClaims {
+    sub: UserId,
+    exp: usize,
+    // The export that can be downloaded with the token.
+    export_id: Uuid,
+}
 
-    let claims = Claims {
+/// Creates the token of the download link of a data export.
+pub fn create_data_export_download(
+    keys: &JwtKeys,
+    user_id: UserId,
+    export_id: Uuid,
+    expires_at: DateTime<Utc>,
+) -> Result<String, ServiceError> {
+    let claims = DataExportClaims {
         sub: user_id,
-        role: role.to_string(),
-        exp: exp as usize,
+        exp: expires_at.timestamp() as usize,
+        export_id,
     };
 
-    let header = Header::new(Algorithm::HS512);
+    Ok(keys.encode(&claims)?)
+}
+
+/// Returns the user and export id of a token from `create_data_export_download`.
+pub fn decode_data_export_download(
+    keys: &JwtKeys,
+    token: &str,
+) -> Result<(UserId, Uuid), ServiceError> {
+    let claims = keys
+        .decode::<DataExportClaims>(token)
+        .map_err(|_| ServiceError::Unauthorized)?
+        .claims;
 
-    encode(&header, &claims, &EncodingKey::from_secret(SECRET))
-        .map_err(|_| ServiceError::TokenCreationError)
+    Ok((claims.sub, claims.export_id))
 }
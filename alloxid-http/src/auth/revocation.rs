@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use uuid::Uuid;
+
+/// Bounded cache of session lookups, so that not every authenticated request hits the database.
+///
+/// Revocations made by this instance are applied to the cache right away, revocations made by
+/// other instances are picked up once the cached entry expires.
+#[derive(Debug)]
+pub struct RevocationCache {
+    capacity: usize,
+    ttl: Duration,
+    entries: Mutex<HashMap<Uuid, Entry>>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    user_id: Uuid,
+    active: bool,
+    inserted_at: Instant,
+}
+
+impl RevocationCache {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            capacity,
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns whether the session is active, or `None` if it has to be looked up.
+    pub fn get(&self, session_id: &Uuid) -> Option<bool> {
+        let entries = self.entries.lock().expect("Revocation cache poisoned");
+
+        entries
+            .get(session_id)
+            .filter(|entry| entry.inserted_at.elapsed() < self.ttl)
+            .map(|entry| entry.active)
+    }
+
+    pub fn insert(&self, session_id: Uuid, user_id: Uuid, active: bool) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut entries = self.entries.lock().expect("Revocation cache poisoned");
+
+        if entries.len() >= self.capacity && !entries.contains_key(&session_id) {
+            let ttl = self.ttl;
+            entries.retain(|_, entry| entry.inserted_at.elapsed() < ttl);
+        }
+
+        if entries.len() >= self.capacity && !entries.contains_key(&session_id) {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.inserted_at)
+                .map(|(id, _)| *id);
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+
+        entries.insert(
+            session_id,
+            Entry {
+                user_id,
+                active,
+                inserted_at: Instant::now(),
+            },
+        );
+    }
+
+    /// Marks a single session as revoked.
+    pub fn revoke(&self, session_id: Uuid, user_id: Uuid) {
+        self.insert(session_id, user_id, false);
+    }
+
+    /// Marks all cached sessions of a user as revoked.
+    pub fn revoke_user(&self, user_id: Uuid) {
+        let mut entries = self.entries.lock().expect("Revocation cache poisoned");
+
+        entries
+            .values_mut()
+            .filter(|entry| entry.user_id == user_id)
+            .for_each(|entry| entry.active = false);
+    }
+
+    /// Marks all cached sessions of a user but the given one as revoked.
+    pub fn revoke_user_except(&self, user_id: Uuid, session_id: Uuid) {
+        let mut entries = self.entries.lock().expect("Revocation cache poisoned");
+
+        entries
+            .iter_mut()
+            .filter(|(id, entry)| entry.user_id == user_id && **id != session_id)
+            .for_each(|(_, entry)| entry.active = false);
+    }
+}
@@ -0,0 +1,57 @@
+use axum::body::Body;
+use axum_macros::debug_handler;
+use http::Response;
+use tracing::{debug, debug_span, info, Instrument};
+
+use crate::audit::{decode_cursor, encode_cursor};
+use crate::auth::AdminUser;
+use crate::database::list_audit_events;
+use crate::error::ServiceError;
+use crate::extract::Query;
+use crate::model::audit::{AuditPage, AuditQuery};
+use crate::{JsonBody, StateExtension};
+
+/// Lists audit events newest first, a page at a time.
+#[debug_handler]
+pub(crate) async fn list_audit(
+    state: StateExtension,
+    AdminUser(admin): AdminUser,
+    Query(query): Query<AuditQuery>,
+) -> Result<Response<Body>, ServiceError> {
+    let settings = &state.settings;
+
+    debug!(
+        "admin list_audit called, port={} db_name={} admin_id={:?}",
+        settings.app.port, settings.database.name, admin.user_id,
+    );
+
+    let after = query.cursor.as_deref().map(decode_cursor).transpose()?;
+    let limit = query.limit();
+
+    // One more than asked for, to know whether there is another page.
+    let mut items = list_audit_events(
+        &state.db_pool,
+        query.user_id,
+        query.action,
+        query.from,
+        query.to,
+        after,
+        limit + 1,
+    )
+    .instrument(debug_span!("query_span"))
+    .await?;
+
+    let next_cursor = if items.len() as i64 > limit {
+        items.truncate(limit as usize);
+        items
+            .last()
+            .map(|event| encode_cursor(event.created_at, &event.id))
+    } else {
+        None
+    };
+
+    let json = serde_json::to_vec(&JsonBody::new(AuditPage { items, next_cursor }))?;
+
+    info!("Successfully listed audit events");
+    Ok(Response::new(Body::from(json)))
+}
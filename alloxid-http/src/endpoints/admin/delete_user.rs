@@ -0,0 +1,46 @@
+use axum::response::IntoResponse;
+use axum_macros::debug_handler;
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use crate::audit::AuditContext;
+use crate::auth::AdminUser;
+use crate::database::delete_user as delete_user_entry;
+use crate::error::ServiceError;
+use crate::extract::Path;
+use crate::model::audit::AuditAction;
+use crate::StateExtension;
+
+#[debug_handler]
+pub(crate) async fn delete_user(
+    state: StateExtension,
+    audit_ctx: AuditContext,
+    AdminUser(admin): AdminUser,
+    Path(user_id): Path<Uuid>,
+) -> Result<impl IntoResponse, ServiceError> {
+    let settings = &state.settings;
+
+    debug!(
+        "admin delete_user called, port={} db_name={} admin_id={:?} user_id={}",
+        settings.app.port, settings.database.name, admin.user_id, user_id,
+    );
+
+    // Unlike users themselves, admins delete accounts right away, including soft deleted ones.
+    if !delete_user_entry(&state.db_pool, &user_id).await? {
+        return Err(ServiceError::NotFound);
+    }
+    state.revocations.revoke_user(user_id);
+
+    audit_ctx
+        .record(
+            &state,
+            AuditAction::UserPurged,
+            Some(admin.user_id.take()),
+            Some(user_id),
+            serde_json::json!({}),
+        )
+        .await;
+
+    info!("Successfully deleted user_id={}", user_id);
+    Ok(())
+}
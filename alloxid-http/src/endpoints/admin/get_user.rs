@@ -0,0 +1,35 @@
+use axum::body::Body;
+use axum_macros::debug_handler;
+use http::Response;
+use tracing::{debug, debug_span, info, Instrument};
+use uuid::Uuid;
+
+use crate::auth::AdminUser;
+use crate::database::get_admin_user_data;
+use crate::error::ServiceError;
+use crate::extract::Path;
+use crate::{JsonBody, StateExtension};
+
+#[debug_handler]
+pub(crate) async fn get_user(
+    state: StateExtension,
+    AdminUser(admin): AdminUser,
+    Path(user_id): Path<Uuid>,
+) -> Result<Response<Body>, ServiceError> {
+    let settings = &state.settings;
+
+    debug!(
+        "admin get_user called, port={} db_name={} admin_id={:?} user_id={}",
+        settings.app.port, settings.database.name, admin.user_id, user_id,
+    );
+
+    let user = get_admin_user_data(&state.db_pool, &user_id)
+        .instrument(debug_span!("query_span"))
+        .await?
+        .ok_or(ServiceError::NotFound)?;
+
+    let json = serde_json::to_vec(&JsonBody::new(user))?;
+
+    info!("Successfully got user_id={}", user_id);
+    Ok(Response::new(Body::from(json)))
+}
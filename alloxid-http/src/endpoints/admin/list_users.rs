@@ -0,0 +1,43 @@
+use axum::body::Body;
+use axum_macros::debug_handler;
+use http::Response;
+use tracing::{debug, debug_span, info, Instrument};
+
+use crate::auth::AdminUser;
+use crate::database::list_users as query_users;
+use crate::error::ServiceError;
+use crate::extract::Query;
+use crate::model::admin::{Page, Pagination};
+use crate::{JsonBody, StateExtension};
+
+#[debug_handler]
+pub(crate) async fn list_users(
+    state: StateExtension,
+    AdminUser(admin): AdminUser,
+    Query(pagination): Query<Pagination>,
+) -> Result<Response<Body>, ServiceError> {
+    let settings = &state.settings;
+
+    debug!(
+        "admin list_users called, port={} db_name={} admin_id={:?}",
+        settings.app.port, settings.database.name, admin.user_id,
+    );
+
+    let offset = pagination
+        .offset()
+        .ok_or_else(|| ServiceError::BadRequest("Page is out of range.".to_string()))?;
+    let (items, total) = query_users(&state.db_pool, pagination.per_page(), offset)
+        .instrument(debug_span!("query_span"))
+        .await?;
+
+    let page = Page {
+        items,
+        page: pagination.page(),
+        per_page: pagination.per_page(),
+        total,
+    };
+    let json = serde_json::to_vec(&JsonBody::new(page))?;
+
+    info!("Successfully listed users");
+    Ok(Response::new(Body::from(json)))
+}
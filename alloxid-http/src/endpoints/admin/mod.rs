@@ -0,0 +1,17 @@
+pub(crate) mod audit;
+pub(crate) mod delete_user;
+pub(crate) mod get_user;
+pub(crate) mod list_users;
+pub(crate) mod oauth_clients;
+pub(crate) mod reset_password;
+pub(crate) mod set_role;
+pub(crate) mod set_status;
+
+pub(crate) use audit::*;
+pub(crate) use delete_user::*;
+pub(crate) use get_user::*;
+pub(crate) use list_users::*;
+pub(crate) use oauth_clients::*;
+pub(crate) use reset_password::*;
+pub(crate) use set_role::*;
+pub(crate) use set_status::*;
@@ -0,0 +1,148 @@
+use axum::body::Body;
+use axum::response::IntoResponse;
+use axum_macros::debug_handler;
+use http::{Response, StatusCode};
+use tracing::{debug, error, info};
+use url::Url;
+
+use crate::audit::AuditContext;
+use crate::auth::AdminUser;
+use crate::database::{
+    delete_oauth_client as delete_oauth_client_entry, insert_oauth_client,
+    list_oauth_clients as query_oauth_clients,
+};
+use crate::error::{FieldError, ServiceError};
+use crate::extract::{Json, Path};
+use crate::model::audit::AuditAction;
+use crate::model::oauth::{OAuthClientCreateRaw, OAuthClientCreatedData, OAuthClientData};
+use crate::{JsonBody, StateExtension};
+
+/// Registers an OAuth client. The secret of confidential clients is only part of this response.
+#[debug_handler]
+pub(crate) async fn create_oauth_client(
+    state: StateExtension,
+    audit_ctx: AuditContext,
+    AdminUser(admin): AdminUser,
+    Json(raw): Json<OAuthClientCreateRaw>,
+) -> Result<Response<Body>, ServiceError> {
+    let settings = &state.settings;
+
+    debug!(
+        "admin create_oauth_client called, port={} db_name={} admin_id={:?}",
+        settings.app.port, settings.database.name, admin.user_id,
+    );
+
+    let name = raw.name.trim();
+    let mut errors = Vec::new();
+
+    if name.is_empty() {
+        errors.push(FieldError::new("name", "empty", "Name is required."));
+    }
+    if raw.redirect_uris.is_empty() {
+        errors.push(FieldError::new(
+            "redirect_uris",
+            "empty",
+            "At least one redirect URI is required.",
+        ));
+    }
+    // RFC 6749, section 3.1.2: absolute URIs without a fragment.
+    for uri in &raw.redirect_uris {
+        let valid = match Url::parse(uri) {
+            Ok(url) => matches!(url.scheme(), "http" | "https") && url.fragment().is_none(),
+            Err(_) => false,
+        };
+        if !valid {
+            errors.push(FieldError::new(
+                "redirect_uris",
+                "invalid",
+                format!("{} is not an absolute http(s) URI without fragment.", uri),
+            ));
+        }
+    }
+
+    if !errors.is_empty() {
+        error!("Invalid oauth client: {:?}", errors);
+        return Err(ServiceError::Validation(errors));
+    }
+
+    let (client_secret, entry) =
+        insert_oauth_client(&state.db_pool, name, &raw.redirect_uris, raw.confidential).await?;
+    info!("Successfully registered oauth client_id={}", entry.id);
+
+    audit_ctx
+        .record(
+            &state,
+            AuditAction::OAuthClientCreated,
+            Some(admin.user_id.take()),
+            None,
+            serde_json::json!({ "client_id": entry.id, "name": name }),
+        )
+        .await;
+
+    let data = OAuthClientCreatedData {
+        client_secret,
+        client: entry.into(),
+    };
+    let json = serde_json::to_vec(&JsonBody::new(data))?;
+
+    let res = Response::builder()
+        .status(StatusCode::CREATED)
+        .body(Body::from(json))
+        .expect("Failed to create response.");
+
+    Ok(res)
+}
+
+#[debug_handler]
+pub(crate) async fn list_oauth_clients(
+    state: StateExtension,
+    AdminUser(admin): AdminUser,
+) -> Result<Response<Body>, ServiceError> {
+    let settings = &state.settings;
+
+    debug!(
+        "admin list_oauth_clients called, port={} db_name={} admin_id={:?}",
+        settings.app.port, settings.database.name, admin.user_id,
+    );
+
+    let clients: Vec<OAuthClientData> = query_oauth_clients(&state.db_pool)
+        .await?
+        .into_iter()
+        .map(OAuthClientData::from)
+        .collect();
+    let json = serde_json::to_vec(&JsonBody::new(clients))?;
+
+    Ok(Response::new(Body::from(json)))
+}
+
+#[debug_handler]
+pub(crate) async fn delete_oauth_client(
+    state: StateExtension,
+    audit_ctx: AuditContext,
+    AdminUser(admin): AdminUser,
+    Path(client_id): Path<String>,
+) -> Result<impl IntoResponse, ServiceError> {
+    let settings = &state.settings;
+
+    debug!(
+        "admin delete_oauth_client called, port={} db_name={} admin_id={:?} client_id={}",
+        settings.app.port, settings.database.name, admin.user_id, client_id,
+    );
+
+    if !delete_oauth_client_entry(&state.db_pool, &client_id).await? {
+        return Err(ServiceError::NotFound);
+    }
+
+    audit_ctx
+        .record(
+            &state,
+            AuditAction::OAuthClientDeleted,
+            Some(admin.user_id.take()),
+            None,
+            serde_json::json!({ "client_id": client_id }),
+        )
+        .await;
+
+    info!("Successfully deleted oauth client_id={}", client_id);
+    Ok(())
+}
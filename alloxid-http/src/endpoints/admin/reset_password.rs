@@ -0,0 +1,54 @@
+use axum::body::Body;
+use axum_macros::debug_handler;
+use http::Response;
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use crate::audit::AuditContext;
+use crate::auth::AdminUser;
+use crate::database::{revoke_user_auth_tokens, set_password_reset_required};
+use crate::error::ServiceError;
+use crate::extract::Path;
+use crate::model::audit::AuditAction;
+use crate::{JsonBody, StateExtension};
+
+/// Forces the user to reset their password, logging them out everywhere.
+#[debug_handler]
+pub(crate) async fn reset_password(
+    state: StateExtension,
+    audit_ctx: AuditContext,
+    AdminUser(admin): AdminUser,
+    Path(user_id): Path<Uuid>,
+) -> Result<Response<Body>, ServiceError> {
+    let settings = &state.settings;
+
+    debug!(
+        "admin reset_password called, port={} db_name={} admin_id={:?} user_id={}",
+        settings.app.port, settings.database.name, admin.user_id, user_id,
+    );
+
+    let user = set_password_reset_required(&state.db_pool, &user_id, true)
+        .await?
+        .ok_or(ServiceError::NotFound)?;
+
+    revoke_user_auth_tokens(&state.db_pool, &user_id).await?;
+    state.revocations.revoke_user(user_id);
+
+    audit_ctx
+        .record(
+            &state,
+            AuditAction::PasswordResetRequired,
+            Some(admin.user_id.take()),
+            Some(user_id),
+            serde_json::json!({}),
+        )
+        .await;
+
+    let json = serde_json::to_vec(&JsonBody::new(user))?;
+
+    info!(
+        "Successfully required password reset for user_id={}",
+        user_id
+    );
+    Ok(Response::new(Body::from(json)))
+}
@@ -0,0 +1,53 @@
+use axum::body::Body;
+use axum_macros::debug_handler;
+use http::Response;
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use crate::audit::AuditContext;
+use crate::auth::AdminUser;
+use crate::database::{revoke_user_auth_tokens, set_user_role};
+use crate::error::ServiceError;
+use crate::extract::{Json, Path};
+use crate::model::admin::RoleUpdateRaw;
+use crate::model::audit::AuditAction;
+use crate::{JsonBody, StateExtension};
+
+/// Promotes or demotes a user. The role is baked into issued tokens, so the user is logged out.
+#[debug_handler]
+pub(crate) async fn set_role(
+    state: StateExtension,
+    audit_ctx: AuditContext,
+    AdminUser(admin): AdminUser,
+    Path(user_id): Path<Uuid>,
+    Json(RoleUpdateRaw { role }): Json<RoleUpdateRaw>,
+) -> Result<Response<Body>, ServiceError> {
+    let settings = &state.settings;
+
+    debug!(
+        "admin set_role called, port={} db_name={} admin_id={:?} user_id={} role={}",
+        settings.app.port, settings.database.name, admin.user_id, user_id, role,
+    );
+
+    let user = set_user_role(&state.db_pool, &user_id, role)
+        .await?
+        .ok_or(ServiceError::NotFound)?;
+
+    revoke_user_auth_tokens(&state.db_pool, &user_id).await?;
+    state.revocations.revoke_user(user_id);
+
+    audit_ctx
+        .record(
+            &state,
+            AuditAction::RoleChanged,
+            Some(admin.user_id.take()),
+            Some(user_id),
+            serde_json::json!({ "role": role.to_string() }),
+        )
+        .await;
+
+    let json = serde_json::to_vec(&JsonBody::new(user))?;
+
+    info!("Successfully set role={} for user_id={}", role, user_id);
+    Ok(Response::new(Body::from(json)))
+}
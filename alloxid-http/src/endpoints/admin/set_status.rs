@@ -0,0 +1,85 @@
+use axum::body::Body;
+use axum_macros::debug_handler;
+use http::Response;
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use crate::audit::AuditContext;
+use crate::auth::AdminUser;
+use crate::database::{revoke_user_auth_tokens, set_user_disabled};
+use crate::error::ServiceError;
+use crate::extract::Path;
+use crate::model::audit::AuditAction;
+use crate::{JsonBody, State, StateExtension};
+
+#[debug_handler]
+pub(crate) async fn disable_user(
+    state: StateExtension,
+    audit_ctx: AuditContext,
+    AdminUser(admin): AdminUser,
+    Path(user_id): Path<Uuid>,
+) -> Result<Response<Body>, ServiceError> {
+    debug!(
+        "admin disable_user called, port={} db_name={} admin_id={:?} user_id={}",
+        state.settings.app.port, state.settings.database.name, admin.user_id, user_id,
+    );
+
+    let res = set_status(&state, user_id, true).await?;
+
+    revoke_user_auth_tokens(&state.db_pool, &user_id).await?;
+    state.revocations.revoke_user(user_id);
+
+    audit_ctx
+        .record(
+            &state,
+            AuditAction::UserDisabled,
+            Some(admin.user_id.take()),
+            Some(user_id),
+            serde_json::json!({}),
+        )
+        .await;
+
+    info!("Successfully disabled user_id={}", user_id);
+    Ok(res)
+}
+
+#[debug_handler]
+pub(crate) async fn enable_user(
+    state: StateExtension,
+    audit_ctx: AuditContext,
+    AdminUser(admin): AdminUser,
+    Path(user_id): Path<Uuid>,
+) -> Result<Response<Body>, ServiceError> {
+    debug!(
+        "admin enable_user called, port={} db_name={} admin_id={:?} user_id={}",
+        state.settings.app.port, state.settings.database.name, admin.user_id, user_id,
+    );
+
+    let res = set_status(&state, user_id, false).await?;
+
+    audit_ctx
+        .record(
+            &state,
+            AuditAction::UserEnabled,
+            Some(admin.user_id.take()),
+            Some(user_id),
+            serde_json::json!({}),
+        )
+        .await;
+
+    info!("Successfully enabled user_id={}", user_id);
+    Ok(res)
+}
+
+async fn set_status(
+    state: &State,
+    user_id: Uuid,
+    disabled: bool,
+) -> Result<Response<Body>, ServiceError> {
+    let user = set_user_disabled(&state.db_pool, &user_id, disabled)
+        .await?
+        .ok_or(ServiceError::NotFound)?;
+
+    let json = serde_json::to_vec(&JsonBody::new(user))?;
+    Ok(Response::new(Body::from(json)))
+}
@@ -1,17 +1,24 @@
 use axum::body::Body;
 use axum::response::{IntoResponse, Response};
 use axum_macros::debug_handler;
+use http::HeaderMap;
 use tracing::debug;
 
+use crate::auth::{self, AuthUser, SCHEME_PREFIX};
 use crate::error::ServiceError;
 use crate::StateExtension;
 
 use alloxid_grpc::hello::greeter_client::GreeterClient;
 use alloxid_grpc::hello::HelloRequest;
 
+/// Calls `alloxid-grpc` on behalf of the user, who has to be logged in with a session. API keys
+/// aren't JWTs and can't be passed on.
 #[debug_handler]
-pub(crate) async fn hello(state: StateExtension) -> Result<impl IntoResponse, ServiceError> {
-    let _pool = state.db_pool.clone();
+pub(crate) async fn hello(
+    state: StateExtension,
+    auth_user: AuthUser,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ServiceError> {
     let settings = state.settings.clone();
 
     debug!(
@@ -19,16 +26,22 @@ pub(crate) async fn hello(state: StateExtension) -> Result<impl IntoResponse, Se
         settings.app.port, settings.database.name,
     );
 
-    let mut client = GreeterClient::connect("http://[::1]:50051").await?;
+    auth_user.ensure_session()?;
+    let token = auth::access_token(&headers, &state).ok_or(ServiceError::Unauthorized)?;
 
-    let request = tonic::Request::new(HelloRequest {
+    let mut client = GreeterClient::new(state.grpc.clone());
+
+    let mut request = tonic::Request::new(HelloRequest {
         name: "Tonic".to_string(),
     });
-
-    let response = client
-        .say_hello(request)
-        .await
-        .expect("Failed to parse gRPC response");
+    let authorization = format!("{}{}", SCHEME_PREFIX, token)
+        .parse()
+        .map_err(|_| ServiceError::Unauthorized)?;
+    request
+        .metadata_mut()
+        .insert("authorization", authorization);
+
+    let response = client.say_hello(request).await?;
 
     Ok(Response::new(Body::from(format!(
         "Message from the grpc server: {:?}",
@@ -1,2 +1,4 @@
+pub(crate) mod admin;
 pub(crate) mod grpc;
+pub(crate) mod oauth;
 pub(crate) mod user;
@@ -0,0 +1,164 @@
+use axum::extract::RawQuery;
+use axum::response::Redirect;
+use axum_macros::debug_handler;
+use chrono::Duration;
+use tracing::{debug, error, info};
+use url::Url;
+
+use super::{issuer, SUPPORTED_SCOPES};
+use crate::audit::AuditContext;
+use crate::auth::AuthUser;
+use crate::database::{get_oauth_client, insert_authorization_code, AuthorizationCode};
+use crate::error::ServiceError;
+use crate::extract::Query;
+use crate::model::audit::AuditAction;
+use crate::model::oauth::AuthorizeParams;
+use crate::StateExtension;
+
+/// The authorization endpoint of the authorization code flow with PKCE, see RFC 6749, section
+/// 4.1 and RFC 7636.
+///
+/// Clients are registered by admins, so they are trusted and there is no consent screen. Users
+/// without a session are sent to the login page first, which sends them back here afterwards.
+#[debug_handler]
+pub(crate) async fn authorize(
+    state: StateExtension,
+    audit_ctx: AuditContext,
+    auth_user: Option<AuthUser>,
+    Query(params): Query<AuthorizeParams>,
+    RawQuery(raw_query): RawQuery,
+) -> Result<Redirect, ServiceError> {
+    let settings = &state.settings;
+
+    debug!(
+        "oauth authorize called, port={} db_name={} client_id={}",
+        settings.app.port, settings.database.name, params.client_id,
+    );
+
+    // Until the redirect URI is known to belong to the client, errors can't be sent there.
+    let client = get_oauth_client(&state.db_pool, &params.client_id)
+        .await?
+        .ok_or_else(|| {
+            error!("Unknown oauth client_id={}", params.client_id);
+            ServiceError::BadRequest("Unknown client_id".to_string())
+        })?;
+
+    if !client.redirect_uris.contains(&params.redirect_uri) {
+        error!(
+            "redirect_uri={} isn't registered for client_id={}",
+            params.redirect_uri, client.id
+        );
+        return Err(ServiceError::BadRequest(
+            "redirect_uri isn't registered for the client".to_string(),
+        ));
+    }
+
+    let redirect_error = |error: &str, description: &str| {
+        info!(
+            "Rejecting authorization request of client_id={} with {}",
+            client.id, error
+        );
+        redirect(
+            &params.redirect_uri,
+            &[("error", error), ("error_description", description)],
+            params.state.as_deref(),
+        )
+    };
+
+    if params.response_type.as_deref() != Some("code") {
+        return redirect_error(
+            "unsupported_response_type",
+            "Only the code response type is supported.",
+        );
+    }
+
+    let scope = params.scope.as_deref().unwrap_or_default();
+    if !super::has_scope(scope, "openid")
+        || !scope
+            .split_whitespace()
+            .all(|scope| SUPPORTED_SCOPES.contains(&scope))
+    {
+        return redirect_error(
+            "invalid_scope",
+            "The scope has to include openid and may include profile.",
+        );
+    }
+
+    let code_challenge = match (
+        &params.code_challenge,
+        params.code_challenge_method.as_deref(),
+    ) {
+        (Some(challenge), Some("S256")) if !challenge.is_empty() => challenge.clone(),
+        _ => return redirect_error("invalid_request", "PKCE with the S256 method is required."),
+    };
+
+    let auth_user = match auth_user {
+        Some(auth_user) => auth_user,
+        None => {
+            debug!("No session, redirecting to the login page");
+            let return_to = format!(
+                "{}/oauth/authorize?{}",
+                issuer(settings),
+                raw_query.unwrap_or_default()
+            );
+            return redirect(
+                &settings.oauth.login_url,
+                &[("return_to", &return_to)],
+                None,
+            );
+        }
+    };
+    auth_user.ensure_session()?;
+
+    let user_id = auth_user.user_id.take();
+    let grant = AuthorizationCode {
+        client_id: client.id.clone(),
+        user_id,
+        redirect_uri: params.redirect_uri.clone(),
+        scope: scope.split_whitespace().collect::<Vec<_>>().join(" "),
+        nonce: params.nonce.clone(),
+        code_challenge,
+    };
+    let ttl = Duration::seconds(settings.oauth.authorization_code_seconds);
+    let code = insert_authorization_code(&state.db_pool, &grant, ttl).await?;
+
+    audit_ctx
+        .record_own(
+            &state,
+            AuditAction::OAuthAuthorized,
+            user_id,
+            serde_json::json!({ "client_id": client.id, "scope": grant.scope }),
+        )
+        .await;
+
+    info!(
+        "Issued authorization code for user_id={} to client_id={}",
+        user_id, client.id
+    );
+    redirect(
+        &params.redirect_uri,
+        &[("code", &code)],
+        params.state.as_deref(),
+    )
+}
+
+/// Redirects to the URI with the parameters added to its query.
+fn redirect(
+    uri: &str,
+    params: &[(&str, &str)],
+    state: Option<&str>,
+) -> Result<Redirect, ServiceError> {
+    // Registered redirect URIs and the login URL are validated before they get here.
+    let mut url = Url::parse(uri)
+        .map_err(|err| ServiceError::Config(format!("Invalid redirect URI {}: {}", uri, err)))?;
+
+    {
+        let mut query = url.query_pairs_mut();
+        query.extend_pairs(params);
+        if let Some(state) = state {
+            query.append_pair("state", state);
+        }
+    }
+
+    Ok(Redirect::to(url.as_str()))
+}
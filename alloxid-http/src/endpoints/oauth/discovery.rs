@@ -0,0 +1,56 @@
+use axum::body::Body;
+use axum_macros::debug_handler;
+use http::Response;
+use tracing::debug;
+
+use super::{issuer, SUPPORTED_SCOPES};
+use crate::error::ServiceError;
+use crate::StateExtension;
+
+/// The OpenID Connect discovery document, see OpenID Connect Discovery 1.0, section 3.
+#[debug_handler]
+pub(crate) async fn openid_configuration(
+    state: StateExtension,
+) -> Result<Response<Body>, ServiceError> {
+    let settings = &state.settings;
+
+    debug!(
+        "openid_configuration called, port={} db_name={}",
+        settings.app.port, settings.database.name,
+    );
+
+    let issuer = issuer(settings);
+    let json = serde_json::to_vec(&serde_json::json!({
+        "issuer": issuer,
+        "authorization_endpoint": format!("{}/oauth/authorize", issuer),
+        "token_endpoint": format!("{}/oauth/token", issuer),
+        "userinfo_endpoint": format!("{}/oauth/userinfo", issuer),
+        "jwks_uri": format!("{}/oauth/jwks", issuer),
+        "response_types_supported": ["code"],
+        "grant_types_supported": ["authorization_code"],
+        "subject_types_supported": ["public"],
+        "id_token_signing_alg_values_supported": [format!("{:?}", state.keys.algorithm())],
+        "scopes_supported": SUPPORTED_SCOPES,
+        "claims_supported": ["iss", "sub", "aud", "exp", "iat", "nonce", "preferred_username"],
+        "token_endpoint_auth_methods_supported": ["client_secret_basic", "client_secret_post", "none"],
+        "code_challenge_methods_supported": ["S256"],
+    }))?;
+
+    Ok(Response::new(Body::from(json)))
+}
+
+/// The public keys tokens are signed with, see RFC 7517, section 5. Only asymmetric keys are
+/// published, so clients can only verify ID tokens if the signing key is one.
+#[debug_handler]
+pub(crate) async fn jwks(state: StateExtension) -> Result<Response<Body>, ServiceError> {
+    let settings = &state.settings;
+
+    debug!(
+        "jwks called, port={} db_name={}",
+        settings.app.port, settings.database.name,
+    );
+
+    let json = serde_json::to_vec(&serde_json::json!({ "keys": state.keys.jwks() }))?;
+
+    Ok(Response::new(Body::from(json)))
+}
@@ -0,0 +1,26 @@
+pub(crate) mod authorize;
+pub(crate) mod discovery;
+pub(crate) mod token;
+pub(crate) mod userinfo;
+
+pub(crate) use authorize::*;
+pub(crate) use discovery::*;
+pub(crate) use token::*;
+pub(crate) use userinfo::*;
+
+use crate::settings::Settings;
+
+// `openid` is required, `profile` adds the username to ID tokens and userinfo.
+const SUPPORTED_SCOPES: &[&str] = &["openid", "profile"];
+
+/// The base URL of the provider, used as `iss` of all tokens handed out to clients.
+pub(crate) fn issuer(settings: &Settings) -> String {
+    match &settings.oauth.issuer {
+        Some(issuer) => issuer.trim_end_matches('/').to_string(),
+        None => format!("http://{}:{}", settings.app.host, settings.app.port),
+    }
+}
+
+fn has_scope(scope: &str, wanted: &str) -> bool {
+    scope.split_whitespace().any(|scope| scope == wanted)
+}
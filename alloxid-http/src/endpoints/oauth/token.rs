@@ -0,0 +1,195 @@
+use axum::body::Body;
+use axum_macros::debug_handler;
+use chrono::{Duration, Utc};
+use http::header::{AUTHORIZATION, CACHE_CONTROL, CONTENT_TYPE};
+use http::{HeaderMap, Response};
+use sha2::{Digest, Sha256};
+use tracing::{debug, error, info};
+
+use super::{has_scope, issuer};
+use crate::auth::hash_token;
+use crate::database::{get_oauth_client, use_authorization_code};
+use crate::error::{OAuthError, ServiceError};
+use crate::extract::Form;
+use crate::model::oauth::{
+    AccessTokenClaims, IdTokenClaims, OAuthClientEntry, TokenRequest, TokenResponse,
+};
+use crate::StateExtension;
+
+/// The token endpoint, exchanging an authorization code for an access and an ID token, see
+/// RFC 6749, section 4.1.3.
+#[debug_handler]
+pub(crate) async fn token(
+    state: StateExtension,
+    headers: HeaderMap,
+    Form(req): Form<TokenRequest>,
+) -> Result<Response<Body>, OAuthError> {
+    let settings = &state.settings;
+
+    debug!(
+        "oauth token called, port={} db_name={}",
+        settings.app.port, settings.database.name,
+    );
+
+    let client = authenticate_client(&state, &headers, &req).await?;
+
+    if req.grant_type.as_deref() != Some("authorization_code") {
+        return Err(OAuthError::new(
+            "unsupported_grant_type",
+            "Only the authorization_code grant type is supported.",
+        ));
+    }
+
+    let (code, redirect_uri, code_verifier) =
+        match (&req.code, &req.redirect_uri, &req.code_verifier) {
+            (Some(code), Some(redirect_uri), Some(code_verifier)) => {
+                (code, redirect_uri, code_verifier)
+            }
+            _ => {
+                return Err(OAuthError::new(
+                    "invalid_request",
+                    "code, redirect_uri and code_verifier are required.",
+                ))
+            }
+        };
+
+    // The code is used up even if the rest of the request turns out to be invalid.
+    let grant = use_authorization_code(&state.db_pool, code)
+        .await?
+        .ok_or_else(|| invalid_grant("The code is invalid, expired or has been used."))?;
+
+    if grant.client_id != client.id || grant.redirect_uri != *redirect_uri {
+        error!(
+            "Authorization code of client_id={} was used by client_id={}",
+            grant.client_id, client.id
+        );
+        return Err(invalid_grant(
+            "The code was issued to another client or redirect_uri.",
+        ));
+    }
+
+    // RFC 7636, section 4.6.
+    let challenge = base64::encode_config(
+        Sha256::digest(code_verifier.as_bytes()),
+        base64::URL_SAFE_NO_PAD,
+    );
+    if !(43..=128).contains(&code_verifier.len()) || challenge != grant.code_challenge {
+        error!("PKCE verification failed for client_id={}", client.id);
+        return Err(invalid_grant("The code_verifier doesn't match."));
+    }
+
+    let user = sqlx::query!(
+        r#" select username, disabled_at from users where id = $1 and deleted_at is null; "#,
+        grant.user_id,
+    )
+    .fetch_optional(&state.db_pool)
+    .await?
+    .filter(|user| user.disabled_at.is_none())
+    .ok_or_else(|| invalid_grant("The user doesn't exist or has been disabled."))?;
+
+    let now = Utc::now();
+    let ttl = Duration::minutes(settings.oauth.access_token_minutes);
+    let exp = (now + ttl).timestamp() as usize;
+    let iat = now.timestamp() as usize;
+    let iss = issuer(settings);
+
+    let access_token = state.keys.encode(&AccessTokenClaims {
+        iss: iss.clone(),
+        sub: grant.user_id,
+        client_id: client.id.clone(),
+        scope: grant.scope.clone(),
+        exp,
+        iat,
+    })?;
+
+    let id_token = state.keys.encode(&IdTokenClaims {
+        iss,
+        sub: grant.user_id,
+        aud: client.id.clone(),
+        exp,
+        iat,
+        nonce: grant.nonce,
+        preferred_username: has_scope(&grant.scope, "profile").then_some(user.username),
+    })?;
+
+    let json = serde_json::to_vec(&TokenResponse {
+        access_token,
+        token_type: "Bearer".to_string(),
+        expires_in: ttl.num_seconds(),
+        id_token,
+        scope: grant.scope,
+    })
+    .map_err(ServiceError::from)?;
+
+    info!(
+        "Issued tokens for user_id={} to client_id={}",
+        grant.user_id, client.id
+    );
+
+    let res = Response::builder()
+        .header(CONTENT_TYPE, "application/json")
+        .header(CACHE_CONTROL, "no-store")
+        .body(Body::from(json))
+        .expect("Failed to create response.");
+
+    Ok(res)
+}
+
+/// Authenticates the client with HTTP Basic auth or the client credentials in the body. Public
+/// clients only send their id.
+async fn authenticate_client(
+    state: &StateExtension,
+    headers: &HeaderMap,
+    req: &TokenRequest,
+) -> Result<OAuthClientEntry, OAuthError> {
+    let (client_id, client_secret) = match headers.get(AUTHORIZATION) {
+        Some(header) => basic_credentials(header.to_str().unwrap_or_default())
+            .ok_or_else(|| invalid_client("Malformed Authorization header."))?,
+        None => (
+            req.client_id.clone().unwrap_or_default(),
+            req.client_secret.clone(),
+        ),
+    };
+
+    let client_secret = client_secret.filter(|secret| !secret.is_empty());
+
+    let client = get_oauth_client(&state.db_pool, &client_id)
+        .await?
+        .ok_or_else(|| invalid_client("Unknown client."))?;
+
+    let authenticated = match (&client.secret_hash, client_secret) {
+        (Some(secret_hash), Some(secret)) => *secret_hash == hash_token(&secret),
+        (None, None) => true,
+        _ => false,
+    };
+
+    if !authenticated {
+        error!("Failed to authenticate client_id={}", client.id);
+        return Err(invalid_client("Client authentication failed."));
+    }
+
+    Ok(client)
+}
+
+/// Parses `Basic base64(client_id:client_secret)`, see RFC 6749, section 2.3.1.
+fn basic_credentials(header: &str) -> Option<(String, Option<String>)> {
+    let encoded = header.strip_prefix("Basic ")?;
+    let decoded = String::from_utf8(base64::decode(encoded).ok()?).ok()?;
+    let (client_id, client_secret) = decoded.split_once(':')?;
+
+    let decode = |value: &str| {
+        url::form_urlencoded::parse(format!("v={}", value).as_bytes())
+            .next()
+            .map(|(_, value)| value.into_owned())
+    };
+
+    Some((decode(client_id)?, decode(client_secret)))
+}
+
+fn invalid_client(description: &str) -> OAuthError {
+    OAuthError::new("invalid_client", description)
+}
+
+fn invalid_grant(description: &str) -> OAuthError {
+    OAuthError::new("invalid_grant", description)
+}
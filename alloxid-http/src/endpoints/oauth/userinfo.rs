@@ -0,0 +1,57 @@
+use axum::body::Body;
+use axum_macros::debug_handler;
+use http::header::AUTHORIZATION;
+use http::{HeaderMap, Response};
+use tracing::{debug, error};
+
+use super::{has_scope, issuer};
+use crate::auth::SCHEME_PREFIX;
+use crate::error::ServiceError;
+use crate::model::oauth::{AccessTokenClaims, UserInfoData};
+use crate::StateExtension;
+
+/// Returns the claims about the user an OAuth access token has been issued for, see OpenID
+/// Connect Core 1.0, section 5.3.
+#[debug_handler]
+pub(crate) async fn userinfo(
+    state: StateExtension,
+    headers: HeaderMap,
+) -> Result<Response<Body>, ServiceError> {
+    let settings = &state.settings;
+
+    debug!(
+        "oauth userinfo called, port={} db_name={}",
+        settings.app.port, settings.database.name,
+    );
+
+    let token = headers
+        .get(AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix(SCHEME_PREFIX))
+        .ok_or(ServiceError::Unauthorized)?;
+
+    let claims = state.keys.decode::<AccessTokenClaims>(token)?.claims;
+    if claims.iss != issuer(settings) {
+        error!("Access token has been issued by {}", claims.iss);
+        return Err(ServiceError::TokenExtractionError);
+    }
+
+    let user = sqlx::query!(
+        r#" select username, disabled_at from users where id = $1 and deleted_at is null; "#,
+        claims.sub,
+    )
+    .fetch_optional(&state.db_pool)
+    .await?
+    .filter(|user| user.disabled_at.is_none())
+    .ok_or(ServiceError::Unauthorized)?;
+
+    let data = UserInfoData {
+        sub: claims.sub,
+        preferred_username: has_scope(&claims.scope, "profile").then_some(user.username),
+    };
+
+    // Unlike our other endpoints, the claims aren't wrapped in `JsonBody`, as clients expect them
+    // at the top level.
+    let json = serde_json::to_vec(&data)?;
+    Ok(Response::new(Body::from(json)))
+}
@@ -0,0 +1,173 @@
+use axum::body::Body;
+use axum::response::IntoResponse;
+use axum_macros::debug_handler;
+use chrono::{Duration, Utc};
+use http::{Response, StatusCode};
+use tracing::{debug, error, info};
+use uuid::Uuid;
+
+use crate::audit::AuditContext;
+use crate::auth::{AuthUser, Role, Scope};
+use crate::database::{
+    insert_api_key, list_api_keys as list_api_key_entries, revoke_api_key as revoke_api_key_entry,
+};
+use crate::error::{FieldError, ServiceError};
+use crate::extract::{Json, Path};
+use crate::model::api_key::{ApiKeyCreateRaw, ApiKeyCreatedData, ApiKeyData};
+use crate::model::audit::AuditAction;
+use crate::{JsonBody, StateExtension};
+
+const MAX_NAME_LENGTH: usize = 100;
+const MAX_EXPIRES_IN_DAYS: i64 = 365;
+
+/// Creates an API key for the authenticated user. The key is only part of this response.
+#[debug_handler]
+pub(crate) async fn create_api_key(
+    state: StateExtension,
+    audit_ctx: AuditContext,
+    auth_user: AuthUser,
+    Path(user_id): Path<Uuid>,
+    Json(raw): Json<ApiKeyCreateRaw>,
+) -> Result<Response<Body>, ServiceError> {
+    let settings = &state.settings;
+
+    debug!(
+        "create_api_key called, port={} db_name={} user_id={}",
+        settings.app.port, settings.database.name, user_id,
+    );
+
+    auth_user.ensure_self(&user_id)?;
+    auth_user.ensure_session()?;
+
+    let name = raw.name.trim();
+    let mut errors = Vec::new();
+
+    if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+        errors.push(FieldError::new(
+            "name",
+            "invalid_length",
+            format!("Name must be 1 to {} characters long.", MAX_NAME_LENGTH),
+        ));
+    }
+    if raw.scopes.is_empty() {
+        errors.push(FieldError::new(
+            "scopes",
+            "empty",
+            "At least one scope is required.",
+        ));
+    } else if raw.scopes.contains(&Scope::Admin) && auth_user.role != Role::Admin {
+        errors.push(FieldError::new(
+            "scopes",
+            "not_allowed",
+            "Only admins can create keys with the admin scope.",
+        ));
+    }
+    let expires_at = match raw.expires_in_days {
+        Some(days) if !(1..=MAX_EXPIRES_IN_DAYS).contains(&days) => {
+            errors.push(FieldError::new(
+                "expires_in_days",
+                "out_of_range",
+                format!("Keys expire after 1 to {} days.", MAX_EXPIRES_IN_DAYS),
+            ));
+            None
+        }
+        Some(days) => Some(Utc::now() + Duration::days(days)),
+        None => None,
+    };
+
+    if !errors.is_empty() {
+        error!("Invalid API key of user_id={}: {:?}", user_id, errors);
+        return Err(ServiceError::Validation(errors));
+    }
+
+    let mut scopes: Vec<String> = raw.scopes.iter().map(Scope::to_string).collect();
+    scopes.sort();
+    scopes.dedup();
+
+    let (key, entry) = insert_api_key(&state.db_pool, &user_id, name, &scopes, expires_at).await?;
+    info!("Created api_key_id={} of user_id={}", entry.id, user_id);
+
+    audit_ctx
+        .record_own(
+            &state,
+            AuditAction::ApiKeyCreated,
+            user_id,
+            serde_json::json!({ "key_id": entry.id, "name": name, "scopes": scopes }),
+        )
+        .await;
+
+    let data = ApiKeyCreatedData {
+        key,
+        api_key: entry.into(),
+    };
+    let json = serde_json::to_vec(&JsonBody::new(data))?;
+
+    let res = Response::builder()
+        .status(StatusCode::CREATED)
+        .body(Body::from(json))
+        .expect("Failed to create response.");
+
+    Ok(res)
+}
+
+/// Lists the active API keys of the authenticated user.
+#[debug_handler]
+pub(crate) async fn list_api_keys(
+    state: StateExtension,
+    auth_user: AuthUser,
+    Path(user_id): Path<Uuid>,
+) -> Result<Response<Body>, ServiceError> {
+    let settings = &state.settings;
+
+    debug!(
+        "list_api_keys called, port={} db_name={} user_id={}",
+        settings.app.port, settings.database.name, user_id,
+    );
+
+    auth_user.ensure_self(&user_id)?;
+    auth_user.ensure_session()?;
+
+    let keys: Vec<ApiKeyData> = list_api_key_entries(&state.db_pool, &user_id)
+        .await?
+        .into_iter()
+        .map(ApiKeyData::from)
+        .collect();
+    let json = serde_json::to_vec(&JsonBody::new(keys))?;
+
+    Ok(Response::new(Body::from(json)))
+}
+
+/// Revokes an API key, which takes effect immediately.
+#[debug_handler]
+pub(crate) async fn revoke_api_key(
+    state: StateExtension,
+    audit_ctx: AuditContext,
+    auth_user: AuthUser,
+    Path((user_id, key_id)): Path<(Uuid, Uuid)>,
+) -> Result<impl IntoResponse, ServiceError> {
+    let settings = &state.settings;
+
+    debug!(
+        "revoke_api_key called, port={} db_name={} user_id={} key_id={}",
+        settings.app.port, settings.database.name, user_id, key_id,
+    );
+
+    auth_user.ensure_self(&user_id)?;
+    auth_user.ensure_session()?;
+
+    if !revoke_api_key_entry(&state.db_pool, &user_id, &key_id).await? {
+        return Err(ServiceError::NotFound);
+    }
+
+    audit_ctx
+        .record_own(
+            &state,
+            AuditAction::ApiKeyRevoked,
+            user_id,
+            serde_json::json!({ "key_id": key_id }),
+        )
+        .await;
+
+    info!("Revoked api_key_id={} of user_id={}", key_id, user_id);
+    Ok(())
+}
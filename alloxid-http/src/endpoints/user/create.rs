@@ -1,36 +1,41 @@
 use axum::body::Body;
-use axum::extract::Json;
 use axum::response::IntoResponse;
 use axum_macros::debug_handler;
 use http::{Response, StatusCode};
 use tracing::{debug, debug_span, error, Instrument};
 
-use crate::database::{insert_auth_token, insert_new_user};
+use crate::audit::AuditContext;
+use crate::auth::{set_session_cookies, Role};
+use crate::database::insert_new_user;
 use crate::error::ServiceError;
-use crate::model::user::{UserAuthData, UserCreateRaw, ValidUserData};
+use crate::extract::Json;
+use crate::model::audit::AuditAction;
+use crate::model::user::{UserCreateRaw, ValidUserData};
 use crate::JsonBody;
 use crate::StateExtension;
 
+use super::{issue_tokens, send_verification_email};
+
 #[debug_handler]
 pub(crate) async fn create(
     state: StateExtension,
+    audit_ctx: AuditContext,
     Json(raw_user_data): Json<UserCreateRaw>,
 ) -> Result<impl IntoResponse, ServiceError> {
     let pool = state.db_pool.clone();
     let settings = state.settings.clone();
-    let secret = settings.app.secret.as_ref();
 
     debug!(
         "create called, port={} db_name={}",
         settings.app.port, settings.database.name,
     );
 
-    let valid_user_data: ValidUserData = raw_user_data.try_into().map_err(|err| {
+    let valid_user_data = ValidUserData::parse(raw_user_data, &state.validator).map_err(|err| {
         error!("Err: {:?}", err);
         err
     })?;
 
-    let user = insert_new_user(&pool, valid_user_data, secret)
+    let user = insert_new_user(&pool, valid_user_data, state.hasher.clone())
         .instrument(debug_span!("insert_new_user"))
         .await
         .map_err(|err| {
@@ -38,27 +43,39 @@ pub(crate) async fn create(
             err
         })?;
 
-    let token = insert_auth_token(&pool, &user.id)
-        .instrument(debug_span!("insert_auth_token"))
-        .await
-        .map_err(|err| {
-            error!("Err: {:?}", err);
-            err
-        })?;
+    audit_ctx
+        .record_own(
+            &state,
+            AuditAction::UserCreated,
+            user.id,
+            serde_json::json!({ "username": user.username, "email": user.email }),
+        )
+        .await;
+
+    // The user can ask for another mail, so this doesn't fail the signup.
+    if let Some(email) = &user.email {
+        if let Err(err) = send_verification_email(&state, user.id, email).await {
+            error!(
+                "Failed to send verification mail to user_id={}: {:?}",
+                user.id, err
+            );
+        }
+    }
 
-    let data = UserAuthData { token, id: user.id };
-    let json = serde_json::to_vec(&JsonBody::new(data))?;
+    let data = issue_tokens(&state, user.id, Role::from_str(&user.role)).await?;
+    let json = serde_json::to_vec(&JsonBody::new(&data))?;
 
     let location = format!(
         "{}:{}/user/{}",
         settings.app.host, settings.app.port, user.id
     );
 
-    let res = Response::builder()
+    let mut res = Response::builder()
         .header("Location", location)
         .status(StatusCode::CREATED)
         .body(Body::from(json))
         .expect("Failed to create response.");
+    set_session_cookies(&mut res, &state, &data)?;
 
     // Ok((StatusCode::CREATED, headers, Json(json)))
     Ok(res)
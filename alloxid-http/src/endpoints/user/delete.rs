@@ -1,32 +1,52 @@
 use axum::response::IntoResponse;
 use axum_macros::debug_handler;
 use tracing::debug;
+use uuid::Uuid;
 
-use crate::{auth::AuthUser, error::ServiceError, StateExtension};
+use crate::audit::AuditContext;
+use crate::database::soft_delete_user;
+use crate::extract::Path;
+use crate::model::audit::AuditAction;
+use crate::{
+    auth::{AuthUser, Scope},
+    error::ServiceError,
+    StateExtension,
+};
 
+/// Deletes the user, who can restore their account within `Settings.deletion.grace_period_days`.
 #[debug_handler]
 pub(crate) async fn delete(
     state: StateExtension,
-    AuthUser { user_id }: AuthUser,
-    // Json(raw_user_data): Json<UserCreateRaw>,
+    audit_ctx: AuditContext,
+    auth_user: AuthUser,
+    Path(user_id): Path<Uuid>,
 ) -> Result<impl IntoResponse, ServiceError> {
     let pool = state.db_pool.clone();
     let settings = state.settings.clone();
 
-    let user_id = user_id.take();
-
     debug!(
-        "delete called, port={} db_name={}",
-        settings.app.port, settings.database.name,
+        "delete called, port={} db_name={} user_id={}",
+        settings.app.port, settings.database.name, user_id,
     );
 
-    sqlx::query!(r#" delete from auth_tokens where user_id = $1; "#, &user_id)
-        .execute(&pool)
-        .await?;
+    auth_user.ensure_scope(Scope::UserWrite)?;
+    auth_user.ensure_self_or_admin(&user_id)?;
+
+    if !soft_delete_user(&pool, &user_id).await? {
+        return Err(ServiceError::NotFound);
+    }
+
+    state.revocations.revoke_user(user_id);
 
-    sqlx::query!(r#" delete from users where id = $1; "#, &user_id)
-        .execute(&pool)
-        .await?;
+    audit_ctx
+        .record(
+            &state,
+            AuditAction::UserDeleted,
+            Some(auth_user.user_id.take()),
+            Some(user_id),
+            serde_json::json!({}),
+        )
+        .await;
 
     debug!("Successfully deleted user_id={:?}", user_id);
     Ok(())
@@ -0,0 +1,168 @@
+use axum::body::Body;
+use axum::response::IntoResponse;
+use axum_macros::debug_handler;
+use chrono::{Duration, Utc};
+use http::Response;
+use tracing::{debug, error, info};
+use uuid::Uuid;
+
+use crate::audit::AuditContext;
+use crate::auth::{self, AuthUser, Scope, UserId};
+use crate::database::{
+    get_user_data, insert_email_verification, last_email_verification_at,
+    verify_email as use_email_verification,
+};
+use crate::error::{FieldError, ServiceError};
+use crate::extract::{Path, Query};
+use crate::helpers::link_base_url;
+use crate::mailer::Email;
+use crate::model::audit::AuditAction;
+use crate::model::user::EmailVerifyParams;
+use crate::{JsonBody, State, StateExtension};
+
+/// Sends a single-use link to verify the given address of the user.
+pub(crate) async fn send_verification_email(
+    state: &State,
+    user_id: Uuid,
+    email: &str,
+) -> Result<(), ServiceError> {
+    let settings = &state.settings;
+
+    let ttl = Duration::hours(settings.email.verification_hours);
+    let (verification_id, expires_at) =
+        insert_email_verification(&state.db_pool, &user_id, email, ttl).await?;
+    let token = auth::create_email_verification(
+        &state.keys,
+        UserId::new(user_id),
+        email,
+        verification_id,
+        expires_at,
+    )?;
+
+    // JWTs only consist of URL-safe characters.
+    let link = format!("{}/user/verify?token={}", link_base_url(settings), token);
+
+    let email = Email {
+        to: email.to_string(),
+        subject: "Verify your email address".to_string(),
+        body: format!(
+            "Open the following link to verify your email address:\n\n{}\n\nThe link expires at {}.\n",
+            link,
+            expires_at.to_rfc2822(),
+        ),
+    };
+    state.mailer.send(&email).await?;
+
+    info!("Sent verification mail to user_id={}", user_id);
+    Ok(())
+}
+
+/// The target of the verification link.
+#[debug_handler]
+pub(crate) async fn verify_email(
+    state: StateExtension,
+    audit_ctx: AuditContext,
+    Query(EmailVerifyParams { token }): Query<EmailVerifyParams>,
+) -> Result<Response<Body>, ServiceError> {
+    let settings = &state.settings;
+
+    debug!(
+        "verify_email called, port={} db_name={}",
+        settings.app.port, settings.database.name,
+    );
+
+    let (user_id, email, verification_id) = auth::decode_email_verification(&state.keys, &token)?;
+    let user_id = user_id.take();
+
+    if !use_email_verification(&state.db_pool, &verification_id, &user_id, &email).await? {
+        error!(
+            "Unknown, used or expired email verification for user_id={}",
+            user_id
+        );
+        return Err(ServiceError::Unauthorized);
+    }
+
+    audit_ctx
+        .record_own(
+            &state,
+            AuditAction::EmailVerified,
+            user_id,
+            serde_json::json!({ "email": email }),
+        )
+        .await;
+
+    let data = get_user_data(&state.db_pool, &user_id)
+        .await?
+        .ok_or(ServiceError::NotFound)?;
+    let json = serde_json::to_vec(&JsonBody::new(data))?;
+
+    info!("Successfully verified email of user_id={}", user_id);
+    Ok(Response::new(Body::from(json)))
+}
+
+/// Sends a new verification link, at most once per `Settings.email.resend_interval_seconds`.
+#[debug_handler]
+pub(crate) async fn resend_verification_email(
+    state: StateExtension,
+    audit_ctx: AuditContext,
+    auth_user: AuthUser,
+    Path(user_id): Path<Uuid>,
+) -> Result<impl IntoResponse, ServiceError> {
+    let settings = &state.settings;
+
+    debug!(
+        "resend_verification_email called, port={} db_name={} user_id={}",
+        settings.app.port, settings.database.name, user_id,
+    );
+
+    auth_user.ensure_scope(Scope::UserWrite)?;
+    auth_user.ensure_self(&user_id)?;
+
+    let user = sqlx::query!(
+        r#" select email, email_verified_at from users where id = $1 and deleted_at is null; "#,
+        user_id,
+    )
+    .fetch_optional(&state.db_pool)
+    .await?
+    .ok_or(ServiceError::NotFound)?;
+
+    let email = match (user.email, user.email_verified_at) {
+        (Some(email), None) => email,
+        (Some(_), Some(_)) => {
+            return Err(ServiceError::Conflict(FieldError::new(
+                "email",
+                "already_verified",
+                "This email address has already been verified.",
+            )))
+        }
+        (None, _) => {
+            return Err(ServiceError::BadRequest(
+                "There is no email address to verify.".to_string(),
+            ))
+        }
+    };
+
+    if let Some(last_sent_at) = last_email_verification_at(&state.db_pool, &user_id).await? {
+        let next_at = last_sent_at + Duration::seconds(settings.email.resend_interval_seconds);
+        let wait = (next_at - Utc::now()).num_seconds();
+        if wait > 0 {
+            error!("Verification mail to user_id={} resent too early", user_id);
+            return Err(ServiceError::TooManyRequests {
+                retry_after_seconds: wait as u64,
+            });
+        }
+    }
+
+    send_verification_email(&state, user_id, &email).await?;
+
+    audit_ctx
+        .record_own(
+            &state,
+            AuditAction::EmailVerificationSent,
+            user_id,
+            serde_json::json!({ "email": email }),
+        )
+        .await;
+
+    Ok(())
+}
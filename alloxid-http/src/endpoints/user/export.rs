@@ -0,0 +1,215 @@
+use axum::body::Body;
+use axum_macros::debug_handler;
+use chrono::Duration;
+use http::header::{CONTENT_DISPOSITION, CONTENT_TYPE, LOCATION};
+use http::{Response, StatusCode};
+use tracing::{debug, error, info};
+use uuid::Uuid;
+
+use crate::audit::AuditContext;
+use crate::auth::{self, AuthUser, Scope, UserId};
+use crate::database::{
+    count_user_export_records, get_data_export, get_data_export_data, get_user_export,
+    insert_data_export,
+};
+use crate::error::ServiceError;
+use crate::extract::{Path, Query};
+use crate::helpers::link_base_url;
+use crate::model::audit::AuditAction;
+use crate::model::export::{
+    DataExportData, DataExportDownloadParams, DataExportEntry, DataExportStatus,
+};
+use crate::{export, JsonBody, State, StateExtension};
+
+/// Exports everything stored about the user as a JSON file. Large exports are started as a
+/// background job instead, answered with `202` and the export's status, which links to the file
+/// once it's ready.
+#[debug_handler]
+pub(crate) async fn export(
+    state: StateExtension,
+    audit_ctx: AuditContext,
+    auth_user: AuthUser,
+    Path(user_id): Path<Uuid>,
+) -> Result<Response<Body>, ServiceError> {
+    let settings = &state.settings;
+
+    debug!(
+        "export called, port={} db_name={} user_id={}",
+        settings.app.port, settings.database.name, user_id,
+    );
+
+    auth_user.ensure_scope(Scope::UserRead)?;
+    auth_user.ensure_self_or_admin(&user_id)?;
+    let actor_id = auth_user.user_id.take();
+
+    let records = count_user_export_records(&state.db_pool, &user_id).await?;
+    if records <= settings.export.inline_max_records {
+        let data = get_user_export(&state.db_pool, &user_id)
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+
+        audit_ctx
+            .record(
+                &state,
+                AuditAction::DataExported,
+                Some(actor_id),
+                Some(user_id),
+                serde_json::json!({ "inline": true }),
+            )
+            .await;
+
+        info!("Exported user_id={}", user_id);
+        return download_response(&user_id, serde_json::to_vec(&data)?);
+    }
+
+    let ttl = Duration::hours(settings.export.download_hours);
+    let stale_before = export::stale_before(&settings.export);
+    let (entry, created) = insert_data_export(&state.db_pool, &user_id, ttl, stale_before).await?;
+    if created {
+        export::spawn(state.db_pool.clone(), entry.id, user_id, ttl);
+        info!(
+            "Started data_export_id={} of user_id={} with {} records",
+            entry.id, user_id, records
+        );
+
+        audit_ctx
+            .record(
+                &state,
+                AuditAction::DataExported,
+                Some(actor_id),
+                Some(user_id),
+                serde_json::json!({ "inline": false, "export_id": entry.id }),
+            )
+            .await;
+    }
+
+    let location = format!(
+        "{}/user/{}/export/{}",
+        link_base_url(settings),
+        user_id,
+        entry.id
+    );
+    let json = serde_json::to_vec(&JsonBody::new(export_data(&state, entry)?))?;
+
+    let res = Response::builder()
+        .header(LOCATION, location)
+        .status(StatusCode::ACCEPTED)
+        .body(Body::from(json))
+        .expect("Failed to create response.");
+
+    Ok(res)
+}
+
+/// The status of a background export, with the download link once it's ready.
+#[debug_handler]
+pub(crate) async fn get_export(
+    state: StateExtension,
+    auth_user: AuthUser,
+    Path((user_id, export_id)): Path<(Uuid, Uuid)>,
+) -> Result<Response<Body>, ServiceError> {
+    let settings = &state.settings;
+
+    debug!(
+        "get_export called, port={} db_name={} user_id={} export_id={}",
+        settings.app.port, settings.database.name, user_id, export_id,
+    );
+
+    auth_user.ensure_scope(Scope::UserRead)?;
+    auth_user.ensure_self_or_admin(&user_id)?;
+
+    let entry = get_data_export(&state.db_pool, &user_id, &export_id)
+        .await?
+        .ok_or(ServiceError::NotFound)?;
+    let json = serde_json::to_vec(&JsonBody::new(export_data(&state, entry)?))?;
+
+    Ok(Response::new(Body::from(json)))
+}
+
+/// The target of the download link of a background export, which works without a session until
+/// the export expires.
+#[debug_handler]
+pub(crate) async fn download_export(
+    state: StateExtension,
+    audit_ctx: AuditContext,
+    Query(DataExportDownloadParams { token }): Query<DataExportDownloadParams>,
+) -> Result<Response<Body>, ServiceError> {
+    let settings = &state.settings;
+
+    debug!(
+        "download_export called, port={} db_name={}",
+        settings.app.port, settings.database.name,
+    );
+
+    let (user_id, export_id) = auth::decode_data_export_download(&state.keys, &token)?;
+    let user_id = user_id.take();
+
+    let data = match get_data_export_data(&state.db_pool, &user_id, &export_id).await? {
+        Some(data) => data,
+        None => {
+            error!(
+                "Unknown or expired data_export_id={} of user_id={}",
+                export_id, user_id
+            );
+            return Err(ServiceError::NotFound);
+        }
+    };
+
+    audit_ctx
+        .record(
+            &state,
+            AuditAction::DataExportDownloaded,
+            None,
+            Some(user_id),
+            serde_json::json!({ "export_id": export_id }),
+        )
+        .await;
+
+    info!(
+        "Downloaded data_export_id={} of user_id={}",
+        export_id, user_id
+    );
+    download_response(&user_id, serde_json::to_vec(&data)?)
+}
+
+fn export_data(state: &State, entry: DataExportEntry) -> Result<DataExportData, ServiceError> {
+    let status = DataExportStatus::parse(&entry.status);
+
+    let download_url = match status {
+        DataExportStatus::Ready => {
+            let token = auth::create_data_export_download(
+                &state.keys,
+                UserId::new(entry.user_id),
+                entry.id,
+                entry.expires_at,
+            )?;
+            Some(format!(
+                "{}/user/export/download?token={}",
+                link_base_url(&state.settings),
+                token
+            ))
+        }
+        _ => None,
+    };
+
+    Ok(DataExportData {
+        id: entry.id,
+        status,
+        created_at: entry.created_at,
+        completed_at: entry.completed_at,
+        expires_at: entry.expires_at,
+        download_url,
+    })
+}
+
+fn download_response(user_id: &Uuid, json: Vec<u8>) -> Result<Response<Body>, ServiceError> {
+    let res = Response::builder()
+        .header(CONTENT_TYPE, "application/json")
+        .header(
+            CONTENT_DISPOSITION,
+            format!("attachment; filename=\"alloxid-export-{}.json\"", user_id),
+        )
+        .body(Body::from(json))
+        .expect("Failed to create response.");
+
+    Ok(res)
+}
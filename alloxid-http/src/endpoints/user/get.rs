@@ -2,9 +2,11 @@ use axum::body::Body;
 use axum_macros::debug_handler;
 use http::Response;
 use tracing::{debug, debug_span, error, info, Instrument};
+use uuid::Uuid;
 
-use crate::auth::AuthUser;
+use crate::auth::{AuthUser, Scope};
 use crate::error::ServiceError;
+use crate::extract::Path;
 use crate::model::user::{UserData, UserEntry};
 use crate::JsonBody;
 use crate::StateExtension;
@@ -12,25 +14,27 @@ use crate::StateExtension;
 #[debug_handler]
 pub(crate) async fn get(
     state: StateExtension,
-    AuthUser { user_id }: AuthUser,
+    auth_user: AuthUser,
+    Path(user_id): Path<Uuid>,
 ) -> Result<Response<Body>, ServiceError> {
     let pool = state.db_pool.clone();
     let settings = state.settings.clone();
 
     debug!(
-        "get_user called, port={} db_name={} user_id={:?}",
+        "get_user called, port={} db_name={} user_id={}",
         settings.app.port, settings.database.name, user_id,
     );
 
-    let user_id = user_id;
+    auth_user.ensure_scope(Scope::UserRead)?;
+    auth_user.ensure_self_or_admin(&user_id)?;
 
     let query_span = debug_span!("query_span");
     let user = sqlx::query_as!(
         UserEntry,
         r#"
-            select * from users where id = $1;
+            select * from users where id = $1 and deleted_at is null;
         "#,
-        user_id.take(),
+        user_id,
     )
     .fetch_one(&pool)
     .instrument(query_span)
@@ -42,13 +46,20 @@ pub(crate) async fn get(
             UserData {
                 id: user.id,
                 username: user.username,
+                email: user.email,
+                email_verified: user.email_verified_at.is_some(),
+                display_name: user.display_name,
+                bio: user.bio,
+                avatar_url: user.avatar_url,
+                locale: user.locale,
+                timezone: user.timezone,
             }
         }
         Err(err) => match err {
-            // Requested user doesn't exist, e.g. token must be illegal.
+            // The user has been deleted after the token has been checked.
             sqlx::Error::RowNotFound => {
                 error!("Err: {:?}", err);
-                return Err(ServiceError::Forbidden);
+                return Err(ServiceError::NotFound);
             }
             _ => {
                 error!("Err: {:?}", err);
@@ -59,6 +70,6 @@ pub(crate) async fn get(
 
     let json = serde_json::to_vec(&JsonBody::new(user_data))?;
 
-    info!("Successfully got user_id={:?}", user_id);
+    info!("Successfully got user_id={}", user_id);
     Ok(Response::new(Body::from(json)))
 }
@@ -1,81 +1,161 @@
+use std::net::SocketAddr;
+
 use axum::body::Body;
-use axum::extract::Json;
+use axum::extract::ConnectInfo;
+use chrono::{Duration, TimeZone, Utc};
 use http::Response;
 use tracing::{debug, debug_span, error, info, Instrument};
 
+use crate::audit::AuditContext;
+use crate::auth::{self, set_session_cookies, Login, Role, UserId};
 use crate::error::ServiceError;
-use crate::model::user::{UserAuthData, UserCreateRaw};
+use crate::extract::Json;
+use crate::model::audit::AuditAction;
+use crate::model::user::{LoginRaw, MfaLoginRaw, MfaPendingData};
 use crate::JsonBody;
-use crate::{helpers, StateExtension};
+use crate::{database, StateExtension};
+
+use super::{issue_tokens, verify_second_factor};
 
 pub async fn login(
     state: StateExtension,
-    Json(UserCreateRaw { username, password }): Json<UserCreateRaw>,
+    audit_ctx: AuditContext,
+    Json(LoginRaw { username, password }): Json<LoginRaw>,
 ) -> Result<Response<Body>, ServiceError> {
-    let pool = state.db_pool.clone();
     let settings = state.settings.clone();
-    let secret = settings.app.secret.as_ref();
 
     debug!(
         "login called, port={} db_name={}",
         settings.app.port, settings.database.name,
     );
 
-    let query_user_span = debug_span!("query_user_span");
-    let row = sqlx::query!(
-        r#"
-            select id as user_id, hashed_password from users
-            where username = $1
-        "#,
-        username,
-    )
-    .fetch_one(&pool)
-    .instrument(query_user_span)
-    .await;
-
-    debug!("User row found: {:?}", &row);
-
-    let (user_id, hashed_password) = match row {
-        Ok(row) => {
-            debug!("Found matching user_id {}", &row.user_id);
-            (row.user_id, row.hashed_password)
-        }
-        Err(err) => match err {
-            sqlx::Error::RowNotFound => {
-                error!("Err: {:?}", err);
-                return Err(ServiceError::Unauthorized);
-            }
-            _ => {
-                error!("Err: {:?}", err);
-                return Err(err.into());
-            }
+    let username = state.validator.login_name(&username);
+    let row = auth::authenticate(
+        &state.db_pool,
+        &state.events,
+        state.hasher.clone(),
+        &state.dummy_hash,
+        &state.login_throttle,
+        Login {
+            username: &username,
+            password,
+            deleted: false,
+            origin: &audit_ctx.0,
         },
-    };
+    )
+    .instrument(debug_span!("authenticate_span"))
+    .await?;
+    let user_id = row.user_id;
 
-    let is_valid = helpers::verify_password(&hashed_password, &password, secret)?;
+    if row.totp_enabled_at.is_some() {
+        let ttl = Duration::minutes(settings.auth.mfa_pending_minutes);
+        let data = MfaPendingData {
+            id: user_id,
+            mfa_token: auth::create_mfa_pending(&state.keys, UserId::new(user_id), ttl)?,
+            expires_in: ttl.num_seconds(),
+        };
+        let json = serde_json::to_vec(&JsonBody::new(data))?;
 
-    if !is_valid {
-        return Err(ServiceError::Unauthorized);
+        info!("Password of user_id={} verified, waiting for TOTP", user_id);
+        return Ok(Response::new(Body::from(json)));
     }
 
-    let query_token_span = debug_span!("query_token_span");
-    let row = sqlx::query!(
-        r#"
-            select token from auth_tokens
-            where user_id = $1
-        "#,
-        user_id,
-    )
-    .fetch_one(&pool)
-    .instrument(query_token_span)
-    .await?;
+    let data = issue_tokens(&state, user_id, Role::from_str(&row.role)).await?;
+    let json = serde_json::to_vec(&JsonBody::new(&data))?;
+
+    let mut res = Response::new(Body::from(json));
+    set_session_cookies(&mut res, &state, &data)?;
 
-    let data = UserAuthData {
-        token: row.token,
-        id: user_id,
-    };
-    let json = serde_json::to_vec(&JsonBody::new(data))?;
+    audit_ctx
+        .record_own(
+            &state,
+            AuditAction::Login,
+            user_id,
+            serde_json::json!({ "mfa": false }),
+        )
+        .await;
 
     info!("Successfully logged in user_id={}", user_id);
-    Ok(Response::new(Body::from(json)))
+    Ok(res)
+}
+
+/// Second step of the login of users with TOTP enabled, exchanging the token from `login` and a
+/// TOTP or recovery code for a session.
+pub async fn login_mfa(
+    state: StateExtension,
+    audit_ctx: AuditContext,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    Json(MfaLoginRaw { mfa_token, code }): Json<MfaLoginRaw>,
+) -> Result<Response<Body>, ServiceError> {
+    let settings = &state.settings;
+    let ip = connect_info.map(|ConnectInfo(addr)| addr.ip());
+
+    debug!(
+        "login_mfa called, port={} db_name={}",
+        settings.app.port, settings.database.name,
+    );
+
+    let claims = auth::decode_mfa_pending(&state.keys, &mfa_token)?;
+    let user_id = claims.sub.take();
+
+    // Codes are short, so guessing them is throttled just like guessing passwords.
+    let throttle_key = user_id.to_string();
+    state.login_throttle.check(&throttle_key, ip)?;
+
+    // Checked before the code, so that replaying the token doesn't use up recovery codes.
+    if database::is_mfa_token_used(&state.db_pool, &claims.jti).await? {
+        error!("Replayed MFA token of user_id={}", user_id);
+        return Err(ServiceError::Unauthorized);
+    }
+
+    if !verify_second_factor(&state, &user_id, &code).await? {
+        error!("Failed TOTP attempt for user_id={} ip={:?}", user_id, ip);
+        state.login_throttle.record_failure(&throttle_key, ip);
+        audit_ctx
+            .record(
+                &state,
+                AuditAction::MfaFailed,
+                None,
+                Some(user_id),
+                serde_json::json!({}),
+            )
+            .await;
+        return Err(ServiceError::Unauthorized);
+    }
+
+    state.login_throttle.record_success(&throttle_key);
+
+    let expires_at = Utc
+        .timestamp_opt(claims.exp as i64, 0)
+        .single()
+        .ok_or(ServiceError::Unauthorized)?;
+    if !database::use_mfa_token(&state.db_pool, &claims.jti, expires_at).await? {
+        error!("Replayed MFA token of user_id={}", user_id);
+        return Err(ServiceError::Unauthorized);
+    }
+
+    // The account might have been disabled since the first step.
+    let row = database::get_login_user(&state.db_pool, &user_id)
+        .await?
+        .ok_or(ServiceError::Unauthorized)?;
+
+    auth::ensure_allowed(&state.db_pool, &state.events, &audit_ctx.0, &row).await?;
+
+    let data = issue_tokens(&state, user_id, Role::from_str(&row.role)).await?;
+    let json = serde_json::to_vec(&JsonBody::new(&data))?;
+
+    let mut res = Response::new(Body::from(json));
+    set_session_cookies(&mut res, &state, &data)?;
+
+    audit_ctx
+        .record_own(
+            &state,
+            AuditAction::Login,
+            user_id,
+            serde_json::json!({ "mfa": true }),
+        )
+        .await;
+
+    info!("Successfully logged in user_id={} with TOTP", user_id);
+    Ok(res)
 }
@@ -0,0 +1,53 @@
+use axum::body::Body;
+use axum_macros::debug_handler;
+use http::{HeaderMap, Response};
+use tracing::{debug, info};
+
+use crate::audit::AuditContext;
+use crate::auth::clear_session_cookies;
+use crate::database::revoke_auth_token;
+use crate::error::ServiceError;
+use crate::extract::Json;
+use crate::model::audit::AuditAction;
+use crate::model::user::RefreshTokenRaw;
+use crate::StateExtension;
+
+use super::refresh_token_from;
+
+#[debug_handler]
+pub(crate) async fn logout(
+    state: StateExtension,
+    audit_ctx: AuditContext,
+    headers: HeaderMap,
+    raw: Option<Json<RefreshTokenRaw>>,
+) -> Result<Response<Body>, ServiceError> {
+    let settings = &state.settings;
+
+    debug!(
+        "logout called, port={} db_name={}",
+        settings.app.port, settings.database.name,
+    );
+
+    let refresh_token = refresh_token_from(&state, &headers, raw).await?;
+
+    let (user_id, family_id) = revoke_auth_token(&state.db_pool, &refresh_token)
+        .await?
+        .ok_or(ServiceError::Unauthorized)?;
+
+    state.revocations.revoke(family_id, user_id);
+
+    audit_ctx
+        .record_own(
+            &state,
+            AuditAction::Logout,
+            user_id,
+            serde_json::json!({ "session_id": family_id }),
+        )
+        .await;
+
+    let mut res = Response::new(Body::empty());
+    clear_session_cookies(&mut res, settings)?;
+
+    info!("Successfully logged out user_id={}", user_id);
+    Ok(res)
+}
@@ -1,11 +1,31 @@
+pub(crate) mod api_keys;
 pub(crate) mod create;
 pub(crate) mod delete;
+pub(crate) mod email;
+pub(crate) mod export;
 pub(crate) mod get;
 pub(crate) mod login;
+pub(crate) mod logout;
+pub(crate) mod password;
+pub(crate) mod password_reset;
+pub(crate) mod patch;
+pub(crate) mod restore;
+pub(crate) mod token;
+pub(crate) mod totp;
 pub(crate) mod update;
 
+pub(crate) use api_keys::*;
 pub(crate) use create::*;
 pub(crate) use delete::*;
+pub(crate) use email::*;
+pub(crate) use export::*;
 pub(crate) use get::*;
 pub(crate) use login::*;
+pub(crate) use logout::*;
+pub(crate) use password::*;
+pub(crate) use password_reset::*;
+pub(crate) use patch::*;
+pub(crate) use restore::*;
+pub(crate) use token::*;
+pub(crate) use totp::*;
 pub(crate) use update::*;
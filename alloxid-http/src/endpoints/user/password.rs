@@ -0,0 +1,90 @@
+use std::net::SocketAddr;
+
+use axum::extract::ConnectInfo;
+use axum::response::IntoResponse;
+use axum_macros::debug_handler;
+use tracing::{debug, debug_span, error, info, Instrument};
+use uuid::Uuid;
+
+use crate::audit::AuditContext;
+use crate::auth::AuthUser;
+use crate::database::{revoke_other_auth_tokens, set_password};
+use crate::error::{FieldError, ServiceError};
+use crate::extract::{Json, Path};
+use crate::model::audit::AuditAction;
+use crate::model::user::PasswordChangeRaw;
+use crate::{password, StateExtension};
+
+/// Changes the password of the authenticated user, logging out all of their other sessions.
+#[debug_handler]
+pub(crate) async fn change_password(
+    state: StateExtension,
+    audit_ctx: AuditContext,
+    auth_user: AuthUser,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    Path(user_id): Path<Uuid>,
+    Json(PasswordChangeRaw {
+        current_password,
+        new_password,
+    }): Json<PasswordChangeRaw>,
+) -> Result<impl IntoResponse, ServiceError> {
+    let pool = state.db_pool.clone();
+    let settings = state.settings.clone();
+    let ip = connect_info.map(|ConnectInfo(addr)| addr.ip());
+
+    debug!(
+        "change_password called, port={} db_name={} user_id={}",
+        settings.app.port, settings.database.name, user_id,
+    );
+
+    // Not even admins know the current password, they can require a reset instead.
+    auth_user.ensure_self(&user_id)?;
+    let session_id = auth_user.ensure_session()?;
+
+    // Otherwise a stolen session could guess the password, which is all it needs to take over.
+    let throttle_key = user_id.to_string();
+    state.login_throttle.check(&throttle_key, ip)?;
+
+    let row = sqlx::query!(
+        r#" select username, hashed_password from users where id = $1 and deleted_at is null; "#,
+        user_id,
+    )
+    .fetch_one(&pool)
+    .instrument(debug_span!("query_user_span"))
+    .await?;
+
+    let is_valid =
+        password::verify_password(state.hasher.clone(), row.hashed_password, current_password)
+            .await?;
+    if !is_valid {
+        error!("Wrong current password for user_id={} ip={:?}", user_id, ip);
+        state.login_throttle.record_failure(&throttle_key, ip);
+        return Err(ServiceError::Validation(vec![FieldError::new(
+            "current_password",
+            "incorrect",
+            "The current password is incorrect.",
+        )]));
+    }
+
+    state.login_throttle.record_success(&throttle_key);
+
+    state.validator.password(&row.username, &new_password)?;
+
+    let hash = password::hash_password(state.hasher.clone(), new_password).await?;
+    set_password(&pool, &user_id, &hash).await?;
+
+    revoke_other_auth_tokens(&pool, &user_id, &session_id).await?;
+    state.revocations.revoke_user_except(user_id, session_id);
+
+    audit_ctx
+        .record_own(
+            &state,
+            AuditAction::PasswordChanged,
+            user_id,
+            serde_json::json!({}),
+        )
+        .await;
+
+    info!("Successfully changed password of user_id={}", user_id);
+    Ok(())
+}
@@ -0,0 +1,150 @@
+use std::net::SocketAddr;
+
+use async_std::task;
+use axum::extract::ConnectInfo;
+use axum::response::IntoResponse;
+use axum_macros::debug_handler;
+use chrono::Duration;
+use tracing::{debug, error, info};
+
+use crate::audit::AuditContext;
+use crate::database::{
+    find_password_reset_token, find_password_reset_user, insert_password_reset_token,
+    reset_password,
+};
+use crate::error::ServiceError;
+use crate::extract::Json;
+use crate::mailer::Email;
+use crate::model::audit::AuditAction;
+use crate::model::user::{PasswordForgotRaw, PasswordResetRaw};
+use crate::{password, State, StateExtension};
+
+/// Mails a password reset token to the verified address of the user, who can be given by
+/// username or address like at login.
+///
+/// Always succeeds, so that it can't be used to find out which usernames exist. The user is only
+/// looked up after responding, so neither the timing nor a failing mailer give them away.
+#[debug_handler]
+pub(crate) async fn forgot_password(
+    state: StateExtension,
+    audit_ctx: AuditContext,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    Json(PasswordForgotRaw { username }): Json<PasswordForgotRaw>,
+) -> Result<impl IntoResponse, ServiceError> {
+    let settings = &state.settings;
+    let ip = connect_info.map(|ConnectInfo(addr)| addr.ip());
+
+    debug!(
+        "forgot_password called, port={} db_name={}",
+        settings.app.port, settings.database.name,
+    );
+
+    let username = state.validator.login_name(&username);
+
+    // Every request counts, known username or not, so that the limit doesn't tell them apart.
+    state.password_reset_throttle.check(&username, ip)?;
+    state.password_reset_throttle.record_failure(&username, ip);
+
+    let state = state.0.clone();
+    task::spawn(async move {
+        if let Err(err) = send_password_reset(&state, &audit_ctx, &username).await {
+            error!("Failed to send password reset: {:?}", err);
+        }
+    });
+
+    Ok(())
+}
+
+async fn send_password_reset(
+    state: &State,
+    audit_ctx: &AuditContext,
+    username: &str,
+) -> Result<(), ServiceError> {
+    let user = match find_password_reset_user(&state.db_pool, username).await? {
+        Some(user) => user,
+        None => {
+            debug!("Password reset requested for unknown, disabled or unverified user");
+            return Ok(());
+        }
+    };
+
+    let ttl = Duration::minutes(state.settings.auth.password_reset_minutes);
+    let (token, expires_at) = insert_password_reset_token(&state.db_pool, &user.id, ttl).await?;
+
+    let email = Email {
+        to: user.email,
+        subject: "Reset your password".to_string(),
+        body: format!(
+            "Somebody asked to reset the password of your account {}. If it was you, set a new password with the following token:\n\n{}\n\nThe token expires at {}. Otherwise, you can ignore this mail.\n",
+            user.username,
+            token,
+            expires_at.to_rfc2822(),
+        ),
+    };
+    state.mailer.send(&email).await?;
+
+    audit_ctx
+        .record(
+            state,
+            AuditAction::PasswordResetRequested,
+            None,
+            Some(user.id),
+            serde_json::json!({}),
+        )
+        .await;
+
+    info!("Sent password reset token to user_id={}", user.id);
+    Ok(())
+}
+
+/// Sets a new password with a token from `forgot_password`, logging the user out everywhere.
+#[debug_handler]
+pub(crate) async fn reset_forgotten_password(
+    state: StateExtension,
+    audit_ctx: AuditContext,
+    Json(PasswordResetRaw {
+        token,
+        new_password,
+    }): Json<PasswordResetRaw>,
+) -> Result<impl IntoResponse, ServiceError> {
+    let settings = &state.settings;
+
+    debug!(
+        "reset_forgotten_password called, port={} db_name={}",
+        settings.app.port, settings.database.name,
+    );
+
+    let reset_token = find_password_reset_token(&state.db_pool, &token)
+        .await?
+        .ok_or_else(|| {
+            error!("Unknown, used or expired password reset token");
+            ServiceError::Unauthorized
+        })?;
+
+    state
+        .validator
+        .password(&reset_token.username, &new_password)?;
+
+    let hash = password::hash_password(state.hasher.clone(), new_password).await?;
+    if !reset_password(&state.db_pool, &reset_token, &hash).await? {
+        error!("Password reset token has been used concurrently");
+        return Err(ServiceError::Unauthorized);
+    }
+
+    state.revocations.revoke_user(reset_token.user_id);
+
+    audit_ctx
+        .record_own(
+            &state,
+            AuditAction::PasswordReset,
+            reset_token.user_id,
+            serde_json::json!({}),
+        )
+        .await;
+
+    info!(
+        "Successfully reset password of user_id={}",
+        reset_token.user_id
+    );
+    Ok(())
+}
@@ -0,0 +1,101 @@
+use axum::body::Body;
+use axum_macros::debug_handler;
+use http::Response;
+use tracing::{debug, debug_span, error, info, Instrument};
+use uuid::Uuid;
+
+use crate::audit::AuditContext;
+use crate::auth::{AuthUser, Scope};
+use crate::database::patch_user;
+use crate::error::ServiceError;
+use crate::extract::{Json, Path};
+use crate::model::audit::AuditAction;
+use crate::model::user::{UserPatchRaw, ValidUserPatch};
+use crate::{JsonBody, StateExtension};
+
+use super::send_verification_email;
+
+/// Updates only the fields that are part of the body, see `UserPatchRaw`.
+#[debug_handler]
+pub(crate) async fn patch(
+    state: StateExtension,
+    audit_ctx: AuditContext,
+    auth_user: AuthUser,
+    Path(user_id): Path<Uuid>,
+    Json(raw): Json<UserPatchRaw>,
+) -> Result<Response<Body>, ServiceError> {
+    let settings = &state.settings;
+
+    debug!(
+        "patch called, port={} db_name={} user_id={}",
+        settings.app.port, settings.database.name, user_id,
+    );
+
+    auth_user.ensure_scope(Scope::UserWrite)?;
+    auth_user.ensure_self_or_admin(&user_id)?;
+    let patch = ValidUserPatch::parse(raw, &state.validator)?;
+    let fields = patched_fields(&patch.0);
+    // The username and email address are credentials as well, see `change_password`.
+    if fields.contains(&"username") || fields.contains(&"email") {
+        auth_user.ensure_session()?;
+    }
+
+    let (user, email_changed) = patch_user(&state.db_pool, &user_id, patch)
+        .instrument(debug_span!("patch_user"))
+        .await?
+        .ok_or(ServiceError::NotFound)?;
+
+    // Like on signup, the user can ask for another mail if this fails.
+    if let (true, Some(email)) = (email_changed, &user.email) {
+        if let Err(err) = send_verification_email(&state, user_id, email).await {
+            error!(
+                "Failed to send verification mail to user_id={}: {:?}",
+                user_id, err
+            );
+        }
+    }
+
+    let actor_id = Some(auth_user.user_id.take());
+    if fields.contains(&"username") {
+        audit_ctx
+            .record(
+                &state,
+                AuditAction::UsernameChanged,
+                actor_id,
+                Some(user_id),
+                serde_json::json!({ "username": user.username }),
+            )
+            .await;
+    }
+    audit_ctx
+        .record(
+            &state,
+            AuditAction::ProfileUpdated,
+            actor_id,
+            Some(user_id),
+            serde_json::json!({ "fields": fields, "email_changed": email_changed }),
+        )
+        .await;
+
+    let json = serde_json::to_vec(&JsonBody::new(user))?;
+
+    info!("Successfully patched user_id={}", user_id);
+    Ok(Response::new(Body::from(json)))
+}
+
+// The names of the fields that are part of the patch, for the audit log.
+fn patched_fields(patch: &UserPatchRaw) -> Vec<&'static str> {
+    [
+        ("username", patch.username.is_some()),
+        ("email", patch.email.is_some()),
+        ("display_name", patch.display_name.is_some()),
+        ("bio", patch.bio.is_some()),
+        ("avatar_url", patch.avatar_url.is_some()),
+        ("locale", patch.locale.is_some()),
+        ("timezone", patch.timezone.is_some()),
+    ]
+    .into_iter()
+    .filter(|(_, patched)| *patched)
+    .map(|(field, _)| field)
+    .collect()
+}
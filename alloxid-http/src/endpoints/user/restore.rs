@@ -0,0 +1,104 @@
+use std::net::SocketAddr;
+
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use chrono::{Duration, Utc};
+use http::Response;
+use tracing::{debug, error, info};
+
+use crate::audit::AuditContext;
+use crate::auth::{authenticate, set_session_cookies, Login, Role};
+use crate::database::restore_user;
+use crate::error::ServiceError;
+use crate::extract::Json;
+use crate::model::audit::AuditAction;
+use crate::model::user::RestoreRaw;
+use crate::{JsonBody, StateExtension};
+
+use super::{issue_tokens, verify_second_factor};
+
+/// Undoes the deletion of an account within the grace period and logs the user in. Takes the
+/// same credentials as the login, including the second factor if TOTP is enabled.
+pub async fn restore(
+    state: StateExtension,
+    audit_ctx: AuditContext,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    Json(RestoreRaw {
+        username,
+        password,
+        code,
+    }): Json<RestoreRaw>,
+) -> Result<Response<Body>, ServiceError> {
+    let settings = &state.settings;
+    let ip = connect_info.map(|ConnectInfo(addr)| addr.ip());
+
+    debug!(
+        "restore called, port={} db_name={}",
+        settings.app.port, settings.database.name,
+    );
+
+    // Like at login, otherwise deleting and restoring the account would get around a reset
+    // required by an admin.
+    let username = state.validator.login_name(&username);
+    let row = authenticate(
+        &state.db_pool,
+        &state.events,
+        state.hasher.clone(),
+        &state.dummy_hash,
+        &state.login_throttle,
+        Login {
+            username: &username,
+            password,
+            deleted: true,
+            origin: &audit_ctx.0,
+        },
+    )
+    .await?;
+    let user_id = row.user_id;
+
+    if row.totp_enabled_at.is_some() {
+        // Codes are throttled per user like in `login_mfa`, the password already counted.
+        let throttle_key = user_id.to_string();
+        state.login_throttle.check(&throttle_key, ip)?;
+
+        let verified = match &code {
+            Some(code) => verify_second_factor(&state, &user_id, code).await?,
+            None => false,
+        };
+        if !verified {
+            error!("Failed TOTP attempt to restore user_id={}", user_id);
+            state.login_throttle.record_failure(&throttle_key, ip);
+            return Err(ServiceError::Unauthorized);
+        }
+
+        state.login_throttle.record_success(&throttle_key);
+    }
+
+    // Accounts past the grace period are about to be purged.
+    let deleted_after = Utc::now() - Duration::days(settings.deletion.grace_period_days);
+    if !restore_user(&state.db_pool, &user_id, deleted_after).await? {
+        error!(
+            "Restore attempt of user_id={} after the grace period",
+            user_id
+        );
+        return Err(ServiceError::NotFound);
+    }
+
+    let data = issue_tokens(&state, user_id, Role::from_str(&row.role)).await?;
+    let json = serde_json::to_vec(&JsonBody::new(&data))?;
+
+    let mut res = Response::new(Body::from(json));
+    set_session_cookies(&mut res, &state, &data)?;
+
+    audit_ctx
+        .record_own(
+            &state,
+            AuditAction::UserRestored,
+            user_id,
+            serde_json::json!({}),
+        )
+        .await;
+
+    info!("Successfully restored user_id={}", user_id);
+    Ok(res)
+}
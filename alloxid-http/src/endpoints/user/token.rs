@@ -0,0 +1,158 @@
+use axum::body::Body;
+use axum_macros::debug_handler;
+use http::{HeaderMap, Response};
+use tracing::{debug, debug_span, error, info, Instrument};
+use uuid::Uuid;
+
+use crate::audit::AuditContext;
+use crate::auth::{cookie, session, set_session_cookies, Role, CSRF_HEADER};
+use crate::database::{find_auth_token_family, rotate_auth_token, Rotation};
+use crate::error::ServiceError;
+use crate::extract::Json;
+use crate::model::audit::AuditAction;
+use crate::model::user::{RefreshTokenRaw, UserAuthData};
+use crate::{JsonBody, State, StateExtension};
+
+/// Starts a new session for the user, i.e. a new refresh token family.
+pub(crate) async fn issue_tokens(
+    state: &State,
+    user_id: Uuid,
+    role: Role,
+) -> Result<UserAuthData, ServiceError> {
+    let data = session::start(
+        &state.db_pool,
+        &state.keys,
+        &state.settings.auth,
+        user_id,
+        role,
+    )
+    .instrument(debug_span!("start_session"))
+    .await
+    .map_err(|err| {
+        error!("Err: {:?}", err);
+        err
+    })?;
+
+    Ok(data)
+}
+
+#[debug_handler]
+pub(crate) async fn refresh(
+    state: StateExtension,
+    audit_ctx: AuditContext,
+    headers: HeaderMap,
+    raw: Option<Json<RefreshTokenRaw>>,
+) -> Result<Response<Body>, ServiceError> {
+    let settings = &state.settings;
+
+    debug!(
+        "refresh called, port={} db_name={}",
+        settings.app.port, settings.database.name,
+    );
+
+    let refresh_token = refresh_token_from(&state, &headers, raw).await?;
+
+    let rotation = rotate_auth_token(&state.db_pool, &refresh_token, refresh_token_ttl(&state))
+        .instrument(debug_span!("rotate_auth_token"))
+        .await?;
+
+    let rotated = match rotation {
+        Rotation::Rotated(rotated) => rotated,
+        Rotation::Reused { user_id, family_id } => {
+            state.revocations.revoke(family_id, user_id);
+            audit_ctx
+                .record(
+                    &state,
+                    AuditAction::RefreshTokenReused,
+                    None,
+                    Some(user_id),
+                    serde_json::json!({ "session_id": family_id }),
+                )
+                .await;
+            return Err(ServiceError::Unauthorized);
+        }
+        Rotation::Invalid => return Err(ServiceError::Unauthorized),
+    };
+
+    let data = access_token(
+        &state,
+        rotated.user_id,
+        rotated.role,
+        rotated.family_id,
+        rotated.token,
+    )?;
+    let json = serde_json::to_vec(&JsonBody::new(&data))?;
+
+    let mut res = Response::new(Body::from(json));
+    set_session_cookies(&mut res, &state, &data)?;
+
+    info!(
+        "Successfully refreshed token for user_id={}",
+        rotated.user_id
+    );
+    Ok(res)
+}
+
+/// Takes the refresh token from the body or, in cookie mode, from the refresh cookie.
+///
+/// Browsers attach the cookie to forged cross-site requests as well, so those also have to echo
+/// the CSRF token of the session.
+pub(crate) async fn refresh_token_from(
+    state: &State,
+    headers: &HeaderMap,
+    raw: Option<Json<RefreshTokenRaw>>,
+) -> Result<String, ServiceError> {
+    if let Some(Json(RefreshTokenRaw { refresh_token })) = raw {
+        return Ok(refresh_token);
+    }
+
+    let session_cookie = &state.settings.auth.session_cookie;
+    if !session_cookie.enabled {
+        error!("Missing refresh token");
+        return Err(ServiceError::Unauthorized);
+    }
+
+    let refresh_token = cookie::get_cookie(headers, &session_cookie.refresh_name)
+        .filter(|token| !token.is_empty())
+        .map(String::from)
+        .ok_or_else(|| {
+            error!("Missing refresh token and refresh cookie");
+            ServiceError::Unauthorized
+        })?;
+
+    let family_id = find_auth_token_family(&state.db_pool, &refresh_token)
+        .await?
+        .ok_or(ServiceError::Unauthorized)?;
+    let valid = headers
+        .get(CSRF_HEADER)
+        .and_then(|header| header.to_str().ok())
+        .map(|csrf| cookie::verify_csrf_token(&state.settings.app.secret, &family_id, csrf))
+        .unwrap_or(false);
+    if !valid {
+        error!("Missing or invalid CSRF token for the refresh cookie");
+        return Err(ServiceError::Forbidden);
+    }
+
+    Ok(refresh_token)
+}
+
+fn access_token(
+    state: &State,
+    user_id: Uuid,
+    role: Role,
+    family_id: Uuid,
+    refresh_token: String,
+) -> Result<UserAuthData, ServiceError> {
+    Ok(session::tokens(
+        &state.keys,
+        &state.settings.auth,
+        user_id,
+        role,
+        family_id,
+        refresh_token,
+    )?)
+}
+
+fn refresh_token_ttl(state: &State) -> chrono::Duration {
+    session::refresh_token_ttl(&state.settings.auth)
+}
@@ -0,0 +1,268 @@
+use std::net::SocketAddr;
+
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use axum::response::IntoResponse;
+use axum_macros::debug_handler;
+use chrono::Utc;
+use http::Response;
+use rand::{thread_rng, RngCore};
+use tracing::{debug, error, info};
+use uuid::Uuid;
+
+use crate::audit::AuditContext;
+use crate::auth::{hash_token, AuthUser};
+use crate::database::{
+    disable_totp as clear_totp, enable_totp, get_totp_state, set_totp_secret, use_recovery_code,
+    use_totp_step,
+};
+use crate::error::{FieldError, ServiceError};
+use crate::extract::{Json, Path};
+use crate::model::audit::AuditAction;
+use crate::model::user::{RecoveryCodesData, TotpCodeRaw, TotpEnrollData};
+use crate::{totp, JsonBody, State, StateExtension};
+
+const RECOVERY_CODE_COUNT: usize = 10;
+
+/// Starts the TOTP enrollment, it has to be confirmed with a first code to take effect.
+#[debug_handler]
+pub(crate) async fn enroll_totp(
+    state: StateExtension,
+    audit_ctx: AuditContext,
+    auth_user: AuthUser,
+    Path(user_id): Path<Uuid>,
+) -> Result<Response<Body>, ServiceError> {
+    let settings = &state.settings;
+
+    debug!(
+        "enroll_totp called, port={} db_name={} user_id={}",
+        settings.app.port, settings.database.name, user_id,
+    );
+
+    auth_user.ensure_self(&user_id)?;
+    auth_user.ensure_session()?;
+
+    let user = get_totp_state(&state.db_pool, &user_id)
+        .await?
+        .ok_or(ServiceError::NotFound)?;
+
+    let secret = totp::generate_secret();
+    if !set_totp_secret(&state.db_pool, &user_id, &secret).await? {
+        return Err(already_enabled());
+    }
+
+    let data = TotpEnrollData {
+        otpauth_uri: totp::otpauth_uri(&settings.auth.totp_issuer, &user.username, &secret),
+        secret,
+    };
+    let json = serde_json::to_vec(&JsonBody::new(data))?;
+
+    audit_ctx
+        .record_own(
+            &state,
+            AuditAction::TotpEnrollmentStarted,
+            user_id,
+            serde_json::json!({}),
+        )
+        .await;
+
+    info!("Started TOTP enrollment of user_id={}", user_id);
+    Ok(Response::new(Body::from(json)))
+}
+
+/// Enables TOTP once the user proves their authenticator app works, handing out recovery codes.
+#[debug_handler]
+pub(crate) async fn confirm_totp(
+    state: StateExtension,
+    audit_ctx: AuditContext,
+    auth_user: AuthUser,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    Path(user_id): Path<Uuid>,
+    Json(TotpCodeRaw { code }): Json<TotpCodeRaw>,
+) -> Result<Response<Body>, ServiceError> {
+    let settings = &state.settings;
+    let ip = connect_info.map(|ConnectInfo(addr)| addr.ip());
+
+    debug!(
+        "confirm_totp called, port={} db_name={} user_id={}",
+        settings.app.port, settings.database.name, user_id,
+    );
+
+    auth_user.ensure_self(&user_id)?;
+    auth_user.ensure_session()?;
+
+    let user = get_totp_state(&state.db_pool, &user_id)
+        .await?
+        .ok_or(ServiceError::NotFound)?;
+
+    if user.totp_enabled_at.is_some() {
+        return Err(already_enabled());
+    }
+
+    let secret = user.totp_secret.ok_or_else(|| {
+        ServiceError::Validation(vec![FieldError::new(
+            "code",
+            "not_enrolled",
+            "TOTP enrollment has to be started first.",
+        )])
+    })?;
+
+    // Like at login, so that a stolen session can't guess its way through the codes.
+    let throttle_key = user_id.to_string();
+    state.login_throttle.check(&throttle_key, ip)?;
+
+    let step = match totp::verify(&secret, &code, Utc::now().timestamp()) {
+        Some(step) => step,
+        None => {
+            error!(
+                "Failed TOTP confirmation for user_id={} ip={:?}",
+                user_id, ip
+            );
+            state.login_throttle.record_failure(&throttle_key, ip);
+            return Err(invalid_code());
+        }
+    };
+    state.login_throttle.record_success(&throttle_key);
+
+    let recovery_codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| generate_recovery_code())
+        .collect();
+    let hashes: Vec<String> = recovery_codes
+        .iter()
+        .map(|code| hash_token(&normalize_recovery_code(code)))
+        .collect();
+
+    if !enable_totp(&state.db_pool, &user_id, step, &hashes).await? {
+        return Err(already_enabled());
+    }
+
+    let json = serde_json::to_vec(&JsonBody::new(RecoveryCodesData { recovery_codes }))?;
+
+    audit_ctx
+        .record_own(
+            &state,
+            AuditAction::TotpEnabled,
+            user_id,
+            serde_json::json!({}),
+        )
+        .await;
+
+    info!("Enabled TOTP for user_id={}", user_id);
+    Ok(Response::new(Body::from(json)))
+}
+
+/// Turns TOTP off again, which requires a current TOTP or recovery code.
+#[debug_handler]
+pub(crate) async fn disable_totp(
+    state: StateExtension,
+    audit_ctx: AuditContext,
+    auth_user: AuthUser,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    Path(user_id): Path<Uuid>,
+    Json(TotpCodeRaw { code }): Json<TotpCodeRaw>,
+) -> Result<impl IntoResponse, ServiceError> {
+    let settings = &state.settings;
+    let ip = connect_info.map(|ConnectInfo(addr)| addr.ip());
+
+    debug!(
+        "disable_totp called, port={} db_name={} user_id={}",
+        settings.app.port, settings.database.name, user_id,
+    );
+
+    auth_user.ensure_self(&user_id)?;
+    auth_user.ensure_session()?;
+
+    let throttle_key = user_id.to_string();
+    state.login_throttle.check(&throttle_key, ip)?;
+
+    if !verify_second_factor(&state, &user_id, &code).await? {
+        error!(
+            "Failed TOTP attempt to disable it for user_id={} ip={:?}",
+            user_id, ip
+        );
+        state.login_throttle.record_failure(&throttle_key, ip);
+        return Err(invalid_code());
+    }
+    state.login_throttle.record_success(&throttle_key);
+
+    clear_totp(&state.db_pool, &user_id).await?;
+
+    audit_ctx
+        .record_own(
+            &state,
+            AuditAction::TotpDisabled,
+            user_id,
+            serde_json::json!({}),
+        )
+        .await;
+
+    info!("Disabled TOTP for user_id={}", user_id);
+    Ok(())
+}
+
+/// Checks a TOTP or recovery code of a user with TOTP enabled. Both can only be used once.
+pub(crate) async fn verify_second_factor(
+    state: &State,
+    user_id: &Uuid,
+    code: &str,
+) -> Result<bool, ServiceError> {
+    let user = get_totp_state(&state.db_pool, user_id)
+        .await?
+        .ok_or(ServiceError::NotFound)?;
+
+    let secret = match (user.totp_enabled_at, user.totp_secret) {
+        (Some(_), Some(secret)) => secret,
+        _ => {
+            error!("user_id={} doesn't have TOTP enabled", user_id);
+            return Ok(false);
+        }
+    };
+
+    if let Some(step) = totp::verify(&secret, code, Utc::now().timestamp()) {
+        let unused = use_totp_step(&state.db_pool, user_id, step).await?;
+        if !unused {
+            error!("TOTP code of user_id={} has been used before", user_id);
+        }
+        return Ok(unused);
+    }
+
+    let code_hash = hash_token(&normalize_recovery_code(code));
+    let used = use_recovery_code(&state.db_pool, user_id, &code_hash).await?;
+    if used {
+        info!("Used a recovery code of user_id={}", user_id);
+    }
+
+    Ok(used)
+}
+
+/// A random code like `k3j5x-a9q2m`, which is easy to write down.
+fn generate_recovery_code() -> String {
+    let mut bytes = [0u8; 7];
+    thread_rng().fill_bytes(&mut bytes);
+
+    let code = base32::encode(base32::Alphabet::RFC4648 { padding: false }, &bytes).to_lowercase();
+    format!("{}-{}", &code[..5], &code[5..10])
+}
+
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+fn already_enabled() -> ServiceError {
+    ServiceError::Conflict(FieldError::new(
+        "totp",
+        "already_enabled",
+        "TOTP is already enabled.",
+    ))
+}
+
+fn invalid_code() -> ServiceError {
+    ServiceError::Validation(vec![FieldError::new(
+        "code",
+        "invalid",
+        "The code is invalid or has already been used.",
+    )])
+}
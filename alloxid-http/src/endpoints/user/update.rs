@@ -1,17 +1,23 @@
 use axum::body::Body;
-use axum::extract::{Json, Path};
 use axum_macros::debug_handler;
 use http::Response;
 use tracing::{debug, debug_span, info, Instrument};
 use uuid::Uuid;
 
+use crate::audit::AuditContext;
+use crate::auth::{AuthUser, Scope};
+use crate::database::update_username;
 use crate::error::ServiceError;
-use crate::model::user::{UserData, UserUpdateRaw};
+use crate::extract::{Json, Path};
+use crate::model::audit::AuditAction;
+use crate::model::user::UserUpdateRaw;
 use crate::{JsonBody, StateExtension};
 
 #[debug_handler]
 pub(crate) async fn update(
     state: StateExtension,
+    audit_ctx: AuditContext,
+    auth_user: AuthUser,
     Path(user_id): Path<Uuid>,
     Json(UserUpdateRaw { username }): Json<UserUpdateRaw>,
 ) -> Result<Response<Body>, ServiceError> {
@@ -23,21 +29,26 @@ pub(crate) async fn update(
         settings.app.port, settings.database.name, user_id,
     );
 
+    auth_user.ensure_scope(Scope::UserWrite)?;
+    auth_user.ensure_self_or_admin(&user_id)?;
+    // Only replaces the username, which is a credential, see `patch`.
+    auth_user.ensure_session()?;
+    let username = state.validator.username(&username)?;
+
     let query_span = debug_span!("query_span");
-    let updated_user = sqlx::query_as!(
-        UserData,
-        r#"
-            update users
-            set username = $2
-            where id = $1
-            returning id, username
-        "#,
-        user_id,
-        username,
-    )
-    .fetch_one(&pool)
-    .instrument(query_span)
-    .await?;
+    let updated_user = update_username(&pool, &user_id, &username)
+        .instrument(query_span)
+        .await?;
+
+    audit_ctx
+        .record(
+            &state,
+            AuditAction::UsernameChanged,
+            Some(auth_user.user_id.take()),
+            Some(user_id),
+            serde_json::json!({ "username": updated_user.username }),
+        )
+        .await;
 
     let json = serde_json::to_vec(&JsonBody::new(updated_user))?;
 
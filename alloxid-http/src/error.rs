@@ -1,6 +1,14 @@
 use axum::body;
+use axum::extract::rejection::{FormRejection, JsonRejection, PathRejection, QueryRejection};
 use axum::response::{IntoResponse, Response};
+use http::header::{CACHE_CONTROL, CONTENT_TYPE, RETRY_AFTER};
 use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+pub use alloxid_core::error::FieldError;
+
+use crate::request_id;
 
 #[derive(Clone, Debug, thiserror::Error)]
 pub enum ServiceError {
@@ -11,59 +19,339 @@ pub enum ServiceError {
     #[error("Insufficient permissions")]
     TokenPermissionError,
 
+    #[error("{0}")]
+    BadRequest(String),
+
     #[error("Forbidden")]
     Forbidden,
 
+    #[error("Not found")]
+    NotFound,
+
     #[error("Unauthorized")]
     Unauthorized,
 
-    #[error("Library error")]
-    LibError(String),
+    #[error("Validation failed")]
+    Validation(Vec<FieldError>),
+
+    #[error("Conflict")]
+    Conflict(FieldError),
+
+    #[error("Too many requests")]
+    TooManyRequests { retry_after_seconds: u64 },
+
+    #[error("Service unavailable: {0}")]
+    Unavailable(String),
+
+    #[error("Configuration error: {0}")]
+    Config(String),
+    #[error("Database error: {0}")]
+    Database(String),
+    #[error("Hashing error: {0}")]
+    Hashing(String),
+    #[error("Serialization error: {0}")]
+    Serialization(String),
+    #[error("Token library error: {0}")]
+    Jwt(String),
+    #[error("Internal error: {0}")]
+    Internal(String),
+}
+
+/// The body of every error response, the counterpart to `JsonBody`.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct JsonError {
+    pub error: ErrorBody,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ErrorBody {
+    // Stable, machine readable error code, e.g. `not_found`.
+    pub code: String,
+    pub message: String,
+    pub details: Vec<FieldError>,
+    // The id of the request, also found in the logs and the `x-request-id` header.
+    pub request_id: Option<String>,
+}
+
+impl ServiceError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ServiceError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ServiceError::Unauthorized | ServiceError::TokenExtractionError => {
+                StatusCode::UNAUTHORIZED
+            }
+            ServiceError::Forbidden | ServiceError::TokenPermissionError => StatusCode::FORBIDDEN,
+            ServiceError::NotFound => StatusCode::NOT_FOUND,
+            ServiceError::Conflict(_) => StatusCode::CONFLICT,
+            ServiceError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ServiceError::TooManyRequests { .. } => StatusCode::TOO_MANY_REQUESTS,
+            ServiceError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ServiceError::TokenCreationError
+            | ServiceError::Config(_)
+            | ServiceError::Database(_)
+            | ServiceError::Hashing(_)
+            | ServiceError::Serialization(_)
+            | ServiceError::Jwt(_)
+            | ServiceError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ServiceError::BadRequest(_) => "bad_request",
+            ServiceError::Unauthorized => "unauthorized",
+            ServiceError::TokenExtractionError => "invalid_token",
+            ServiceError::Forbidden => "forbidden",
+            ServiceError::TokenPermissionError => "insufficient_permissions",
+            ServiceError::NotFound => "not_found",
+            ServiceError::Conflict(_) => "conflict",
+            ServiceError::Validation(_) => "validation_failed",
+            ServiceError::TooManyRequests { .. } => "too_many_requests",
+            ServiceError::Unavailable(_) => "service_unavailable",
+            _ => "internal_error",
+        }
+    }
+
+    /// Turns a violated unique constraint into a `Conflict` on the given field.
+    pub fn from_unique_violation(err: sqlx::Error, field: &str) -> Self {
+        alloxid_core::Error::from_unique_violation(err, field).into()
+    }
 }
 
 impl IntoResponse for ServiceError {
     fn into_response(self) -> Response {
-        let status = match self {
-            ServiceError::Unauthorized => StatusCode::UNAUTHORIZED,
-            ServiceError::Forbidden => StatusCode::FORBIDDEN,
-            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        let status = self.status();
+
+        // Internal details end up in the logs, not in the response.
+        let message = if status.is_server_error() {
+            error!("Responding with {}: {}", status, self);
+            status
+                .canonical_reason()
+                .unwrap_or("Internal Server Error")
+                .to_string()
+        } else {
+            self.to_string()
         };
 
-        let msg = self.to_string();
+        let retry_after = match &self {
+            ServiceError::TooManyRequests {
+                retry_after_seconds,
+            } => Some(*retry_after_seconds),
+            _ => None,
+        };
 
-        Response::builder()
+        let code = self.code().to_string();
+        let details = match self {
+            ServiceError::Validation(errors) => errors,
+            ServiceError::Conflict(error) => vec![error],
+            _ => Vec::new(),
+        };
+
+        let json = JsonError {
+            error: ErrorBody {
+                code,
+                message,
+                details,
+                request_id: request_id::current().map(|id| id.to_string()),
+            },
+        };
+        let json = serde_json::to_vec(&json).expect("Failed to serialize error.");
+
+        let mut res = Response::builder()
             .status(status)
-            .body(body::boxed(body::Full::from(msg)))
+            .header(CONTENT_TYPE, "application/json");
+        if let Some(seconds) = retry_after {
+            res = res.header(RETRY_AFTER, seconds);
+        }
+
+        res.body(body::boxed(body::Full::from(json))).unwrap()
+    }
+}
+
+/// An error of the OAuth token endpoint, which has its own format, see RFC 6749, section 5.2.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct OAuthError {
+    // One of the error codes of the spec, e.g. `invalid_grant`.
+    pub error: String,
+    pub error_description: String,
+}
+
+impl OAuthError {
+    pub fn new(error: &str, description: impl Into<String>) -> Self {
+        Self {
+            error: error.to_string(),
+            error_description: description.into(),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self.error.as_str() {
+            "invalid_client" => StatusCode::UNAUTHORIZED,
+            "server_error" => StatusCode::INTERNAL_SERVER_ERROR,
+            "temporarily_unavailable" => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+impl IntoResponse for OAuthError {
+    fn into_response(self) -> Response {
+        let json = serde_json::to_vec(&self).expect("Failed to serialize error.");
+
+        Response::builder()
+            .status(self.status())
+            .header(CONTENT_TYPE, "application/json")
+            .header(CACHE_CONTROL, "no-store")
+            .body(body::boxed(body::Full::from(json)))
             .unwrap()
     }
 }
 
-impl From<argonautica::Error> for ServiceError {
-    fn from(err: argonautica::Error) -> Self {
-        Self::LibError(err.to_string())
+impl From<ServiceError> for OAuthError {
+    fn from(err: ServiceError) -> Self {
+        match err.status() {
+            StatusCode::SERVICE_UNAVAILABLE => {
+                error!("Responding with temporarily_unavailable: {}", err);
+                Self::new("temporarily_unavailable", "Service Unavailable")
+            }
+            status if status.is_server_error() => {
+                error!("Responding with server_error: {}", err);
+                Self::new("server_error", "Internal Server Error")
+            }
+            _ => Self::new("invalid_request", err.to_string()),
+        }
+    }
+}
+
+impl From<alloxid_core::Error> for OAuthError {
+    fn from(err: alloxid_core::Error) -> Self {
+        ServiceError::from(err).into()
+    }
+}
+
+impl From<sqlx::Error> for OAuthError {
+    fn from(err: sqlx::Error) -> Self {
+        ServiceError::from(err).into()
+    }
+}
+
+impl From<alloxid_core::Error> for ServiceError {
+    fn from(err: alloxid_core::Error) -> Self {
+        use alloxid_core::Error;
+
+        match err {
+            Error::TokenCreationError => Self::TokenCreationError,
+            Error::TokenExtractionError => Self::TokenExtractionError,
+            Error::NotFound => Self::NotFound,
+            Error::Unauthorized => Self::Unauthorized,
+            Error::Forbidden(_) => Self::Forbidden,
+            Error::Validation(errors) => Self::Validation(errors),
+            Error::Conflict(error) => Self::Conflict(error),
+            Error::TooManyRequests {
+                retry_after_seconds,
+            } => Self::TooManyRequests {
+                retry_after_seconds,
+            },
+            Error::Unavailable(msg) => Self::Unavailable(msg),
+            Error::Config(msg) => Self::Config(msg),
+            Error::Database(msg) => Self::Database(msg),
+            Error::Hashing(msg) => Self::Hashing(msg),
+            Error::Jwt(msg) => Self::Jwt(msg),
+        }
     }
 }
 
 impl From<config::ConfigError> for ServiceError {
     fn from(err: config::ConfigError) -> Self {
-        Self::LibError(err.to_string())
+        Self::Config(err.to_string())
+    }
+}
+
+impl From<jsonwebtoken::errors::Error> for ServiceError {
+    fn from(err: jsonwebtoken::errors::Error) -> Self {
+        Self::Jwt(err.to_string())
     }
 }
 
 impl From<serde_json::Error> for ServiceError {
     fn from(err: serde_json::Error) -> Self {
-        Self::LibError(err.to_string())
+        Self::Serialization(err.to_string())
     }
 }
 
 impl From<sqlx::Error> for ServiceError {
     fn from(err: sqlx::Error) -> Self {
-        Self::LibError(err.to_string())
+        match err {
+            sqlx::Error::RowNotFound => Self::NotFound,
+            sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed | sqlx::Error::Io(_) => {
+                Self::Unavailable(err.to_string())
+            }
+            _ => Self::Database(err.to_string()),
+        }
+    }
+}
+
+/// Maps the status of a failed call to `alloxid-grpc` onto the closest HTTP error.
+impl From<tonic::Status> for ServiceError {
+    fn from(status: tonic::Status) -> Self {
+        use tonic::Code;
+
+        let message = status.message().to_string();
+        match status.code() {
+            Code::InvalidArgument | Code::FailedPrecondition | Code::OutOfRange => {
+                Self::BadRequest(message)
+            }
+            Code::Unauthenticated => Self::Unauthorized,
+            Code::PermissionDenied => Self::Forbidden,
+            Code::NotFound => Self::NotFound,
+            // `alloxid-grpc` reports conflicts as "field: message".
+            Code::AlreadyExists => {
+                let (field, message) = message.split_once(": ").unwrap_or(("", &message));
+                Self::Conflict(FieldError::new(field, "taken", message))
+            }
+            Code::ResourceExhausted => Self::TooManyRequests {
+                retry_after_seconds: status
+                    .metadata()
+                    .get("retry-after")
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.parse().ok())
+                    .unwrap_or(1),
+            },
+            // Timeouts and connection errors of the channel end up here as well.
+            Code::Unavailable | Code::DeadlineExceeded | Code::Cancelled => {
+                Self::Unavailable(format!("gRPC call failed: {}", status))
+            }
+            _ => Self::Internal(format!("gRPC call failed: {}", status)),
+        }
+    }
+}
+
+impl From<JsonRejection> for ServiceError {
+    fn from(rejection: JsonRejection) -> Self {
+        match rejection {
+            // The body is valid JSON, but doesn't match the expected shape.
+            JsonRejection::JsonDataError(err) => {
+                Self::Validation(vec![FieldError::new("body", "invalid", err.to_string())])
+            }
+            rejection => Self::BadRequest(rejection.to_string()),
+        }
+    }
+}
+
+impl From<PathRejection> for ServiceError {
+    fn from(rejection: PathRejection) -> Self {
+        Self::BadRequest(rejection.to_string())
+    }
+}
+
+impl From<QueryRejection> for ServiceError {
+    fn from(rejection: QueryRejection) -> Self {
+        Self::BadRequest(rejection.to_string())
     }
 }
 
-impl From<tonic::transport::Error> for ServiceError {
-    fn from(err: tonic::transport::Error) -> Self {
-        Self::LibError(err.to_string())
+impl From<FormRejection> for ServiceError {
+    fn from(rejection: FormRejection) -> Self {
+        Self::BadRequest(rejection.to_string())
     }
 }
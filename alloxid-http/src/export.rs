@@ -0,0 +1,63 @@
+use async_std::task;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::database::{
+    complete_data_export, fail_data_export, fail_stale_data_exports, get_user_export,
+};
+use crate::error::ServiceError;
+use crate::settings::Export;
+
+/// Pending exports started before this are given up on.
+pub(crate) fn stale_before(settings: &Export) -> DateTime<Utc> {
+    Utc::now() - Duration::minutes(settings.pending_minutes)
+}
+
+/// Marks the exports left pending by an earlier run of the app as failed. Exports of other
+/// instances sharing the database are only touched once they're stale as well.
+pub(crate) async fn fail_stale(pool: &PgPool, settings: &Export) -> Result<(), ServiceError> {
+    let failed = fail_stale_data_exports(pool, None, stale_before(settings)).await?;
+    if failed > 0 {
+        info!("Marked {} stale data exports as failed", failed);
+    }
+
+    Ok(())
+}
+
+/// Spawns the task that collects the data of a pending export and stores it for download.
+pub(crate) fn spawn(pool: PgPool, export_id: Uuid, user_id: Uuid, ttl: Duration) {
+    task::spawn(async move {
+        match run(&pool, &export_id, &user_id, ttl).await {
+            Ok(()) => info!(
+                "Finished data_export_id={} of user_id={}",
+                export_id, user_id
+            ),
+            Err(err) => {
+                error!("Failed data_export_id={}: {:?}", export_id, err);
+                if let Err(err) = fail_data_export(&pool, &export_id).await {
+                    error!(
+                        "Failed to mark data_export_id={} as failed: {:?}",
+                        export_id, err
+                    );
+                }
+            }
+        }
+    });
+}
+
+async fn run(
+    pool: &PgPool,
+    export_id: &Uuid,
+    user_id: &Uuid,
+    ttl: Duration,
+) -> Result<(), ServiceError> {
+    let export = get_user_export(pool, user_id)
+        .await?
+        .ok_or(ServiceError::NotFound)?;
+
+    complete_data_export(pool, export_id, serde_json::to_value(&export)?, ttl).await?;
+
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use axum::extract::{self, FromRequest, RequestParts};
+use axum::BoxError;
+use serde::de::DeserializeOwned;
+
+use crate::error::ServiceError;
+
+/// Like axum's `Json`, but rejects with a `ServiceError` so that malformed requests get the same
+/// error body as every other error. The same goes for `Path`, `Query` and `Form` below.
+pub(crate) struct Json<T>(pub T);
+
+#[async_trait::async_trait]
+impl<T, B> FromRequest<B> for Json<T>
+where
+    T: DeserializeOwned,
+    B: axum::body::HttpBody + Send,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+{
+    type Rejection = ServiceError;
+
+    async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        let extract::Json(value) = extract::Json::<T>::from_request(req).await?;
+        Ok(Self(value))
+    }
+}
+
+pub(crate) struct Path<T>(pub T);
+
+#[async_trait::async_trait]
+impl<T, B> FromRequest<B> for Path<T>
+where
+    T: DeserializeOwned + Send,
+    B: Send,
+{
+    type Rejection = ServiceError;
+
+    async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        let extract::Path(value) = extract::Path::<T>::from_request(req).await?;
+        Ok(Self(value))
+    }
+}
+
+pub(crate) struct Query<T>(pub T);
+
+#[async_trait::async_trait]
+impl<T, B> FromRequest<B> for Query<T>
+where
+    T: DeserializeOwned,
+    B: Send,
+{
+    type Rejection = ServiceError;
+
+    async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        let extract::Query(value) = extract::Query::<T>::from_request(req).await?;
+        Ok(Self(value))
+    }
+}
+
+pub(crate) struct Form<T>(pub T);
+
+#[async_trait::async_trait]
+impl<T, B> FromRequest<B> for Form<T>
+where
+    T: DeserializeOwned,
+    B: axum::body::HttpBody + Send,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+{
+    type Rejection = ServiceError;
+
+    async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        let extract::Form(value) = extract::Form::<T>::from_request(req).await?;
+        Ok(Self(value))
+    }
+}
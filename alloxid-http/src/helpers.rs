@@ -1,13 +1,9 @@
-use argonautica::Verifier;
+use crate::settings::Settings;
 
-use crate::error::ServiceError;
-
-pub fn verify_password(hash: &str, password: &str, secret: &str) -> Result<bool, ServiceError> {
-    let mut verifier = Verifier::default();
-    verifier
-        .with_hash(&hash)
-        .with_password(password)
-        .with_secret_key(secret)
-        .verify()
-        .map_err(ServiceError::from)
+/// The base URL of links sent to users, defaults to the address the app listens on.
+pub(crate) fn link_base_url(settings: &Settings) -> String {
+    match &settings.email.link_base_url {
+        Some(url) => url.trim_end_matches('/').to_string(),
+        None => format!("http://{}:{}", settings.app.host, settings.app.port),
+    }
 }
@@ -1,33 +1,50 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use axum::body::Body;
 use axum::handler::Handler;
 use axum::response::IntoResponse;
-use axum::routing::{get, post};
-use axum::{Extension, Router};
-use http::{Method, Request, StatusCode};
+use axum::routing::{delete, get, post, put};
+use axum::{middleware, Extension, Router};
+use http::header::{HeaderName, AUTHORIZATION, CONTENT_TYPE};
+use http::{Method, Request};
 use serde::{Deserialize, Serialize};
 use sqlx::postgres::PgPool;
+use tonic::transport::Channel;
 use tower::ServiceBuilder;
 use tower_http::cors::{CorsLayer, Origin};
 use tower_http::trace::TraceLayer;
-use tracing::debug;
-use uuid::Uuid;
+use tracing::error;
 
 pub mod error;
-pub mod model;
-pub mod settings;
+pub mod mailer;
+pub mod request_id;
 pub mod telemetry;
+pub mod totp;
 
+mod audit;
 mod auth;
-mod database;
 mod endpoints;
+mod export;
+mod extract;
 mod helpers;
+mod purge;
 
+use alloxid_core::events::UserEvents;
+use alloxid_core::{database, validation};
+pub use alloxid_core::{model, password, settings};
+
+use auth::{JwtKeys, LoginThrottle, RevocationCache};
+use endpoints::admin;
 use endpoints::grpc;
+use endpoints::oauth;
 use endpoints::user;
 use error::*;
+use mailer::Mailer;
+use password::{Argon2Hasher, DummyHash, PasswordHasher};
+use request_id::RequestId;
 use settings::Settings;
+use validation::Validator;
 
 pub type Result<T, E = ServiceError> = std::result::Result<T, E>;
 pub type StateExtension = Extension<Arc<State>>;
@@ -46,7 +63,19 @@ impl<T> JsonBody<T> {
 #[derive(Clone, Debug)]
 pub struct State {
     pub db_pool: PgPool,
+    pub dummy_hash: DummyHash,
+    // The user events of this instance, streamed by the embedded `alloxid-grpc` server.
+    pub events: UserEvents,
+    // Shared by all clients of `alloxid-grpc`, connects on the first call.
+    pub grpc: Channel,
+    pub hasher: Arc<dyn PasswordHasher>,
+    pub keys: JwtKeys,
+    pub login_throttle: Arc<LoginThrottle>,
+    pub mailer: Arc<dyn Mailer>,
+    pub password_reset_throttle: Arc<LoginThrottle>,
+    pub revocations: Arc<RevocationCache>,
     pub settings: Settings,
+    pub validator: Arc<Validator>,
 }
 
 async fn health_check() -> &'static str {
@@ -55,15 +84,25 @@ async fn health_check() -> &'static str {
 }
 
 async fn handle_404() -> impl IntoResponse {
-    (
-        StatusCode::NOT_FOUND,
-        r#"Nothing to see here.
-
-Navigate to /health-check to receive a 200 response.
+    ServiceError::NotFound.into_response()
+}
 
-Run tests to see the app in action.
-"#,
-    )
+/// Serves `alloxid-grpc` alongside the app, sharing its user events and login throttle. Like the
+/// app itself, it runs until the process exits.
+fn spawn_grpc(
+    db_pool: PgPool,
+    settings: Settings,
+    events: UserEvents,
+    login_throttle: Arc<LoginThrottle>,
+) {
+    tokio::spawn(async move {
+        let shutdown = std::future::pending();
+        if let Err(err) =
+            alloxid_grpc::serve(db_pool, settings, events, login_throttle, shutdown).await
+        {
+            error!("Embedded gRPC server failed: {:?}", err);
+        }
+    });
 }
 
 pub async fn configure_app(db_pool: PgPool, settings: Settings) -> Result<axum::Router> {
@@ -75,32 +114,151 @@ pub async fn configure_app(db_pool: PgPool, settings: Settings) -> Result<axum::
                 .parse()
                 .expect("Failed to parse frontend url"),
         ))
-        .allow_methods(vec![Method::GET, Method::POST]);
+        .allow_methods(vec![
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+        ])
+        .allow_headers(vec![
+            AUTHORIZATION,
+            CONTENT_TYPE,
+            HeaderName::from_static(auth::CSRF_HEADER),
+        ])
+        // Browsers only send the session cookie along if we allow credentials.
+        .allow_credentials(settings.auth.session_cookie.enabled);
+
+    settings.validate()?;
+    let keys = JwtKeys::from_settings(&settings.auth)?;
+    let revocations = Arc::new(RevocationCache::new(
+        settings.auth.revocation_cache_capacity,
+        Duration::from_secs(settings.auth.revocation_cache_seconds),
+    ));
+    let validator = Arc::new(Validator::from_settings(&settings.validation)?);
+    let mailer = mailer::from_settings(&settings.mailer, &settings.email.from)?;
+    let login_throttle = Arc::new(LoginThrottle::new(settings.auth.login_throttle.clone()));
+    let password_reset_throttle = Arc::new(LoginThrottle::new(
+        settings.auth.password_reset_throttle.clone(),
+    ));
+    let grpc = alloxid_grpc::transport::channel(&settings.grpc)?;
+
+    let hasher: Arc<dyn PasswordHasher> = Arc::new(Argon2Hasher::from_settings(&settings)?);
+    let dummy_hash = DummyHash::default();
+    dummy_hash.prepare(hasher.clone());
+
+    export::fail_stale(&db_pool, &settings.export).await?;
+    purge::spawn(db_pool.clone(), settings.deletion.clone());
 
-    let state = Arc::new(State { db_pool, settings });
+    let events = UserEvents::new(settings.grpc.event_capacity);
+    if settings.grpc.embedded {
+        spawn_grpc(
+            db_pool.clone(),
+            settings.clone(),
+            events.clone(),
+            login_throttle.clone(),
+        );
+    }
+
+    let state = Arc::new(State {
+        db_pool,
+        dummy_hash,
+        events,
+        grpc,
+        hasher,
+        keys,
+        login_throttle,
+        mailer,
+        password_reset_throttle,
+        revocations,
+        settings,
+        validator,
+    });
 
     let service = ServiceBuilder::new()
         .layer(Extension(state))
-        .layer(TraceLayer::new_for_http().make_span_with(
-            |_req: &Request<Body>| tracing::debug_span!("request", req_id = %Uuid::new_v4()),
-        ))
+        .layer(middleware::from_fn(request_id::assign_request_id))
+        .layer(
+            TraceLayer::new_for_http().make_span_with(|req: &Request<Body>| {
+                let req_id = req.extensions().get::<RequestId>().map(|id| id.0);
+                tracing::debug_span!("request", req_id = ?req_id)
+            }),
+        )
         .layer(cors);
 
     let grpc_routes = Router::new().route("/hello", get(grpc::hello));
 
+    let admin_routes = Router::new()
+        .route("/audit", get(admin::list_audit))
+        .route("/users", get(admin::list_users))
+        .route(
+            "/users/:id",
+            get(admin::get_user).delete(admin::delete_user),
+        )
+        .route("/users/:id/password-reset", post(admin::reset_password))
+        .route("/users/:id/disable", post(admin::disable_user))
+        .route("/users/:id/enable", post(admin::enable_user))
+        .route("/users/:id/role", put(admin::set_role))
+        .route(
+            "/oauth/clients",
+            get(admin::list_oauth_clients).post(admin::create_oauth_client),
+        )
+        .route(
+            "/oauth/clients/:client_id",
+            delete(admin::delete_oauth_client),
+        );
+
+    let oauth_routes = Router::new()
+        .route("/authorize", get(oauth::authorize))
+        .route("/token", post(oauth::token))
+        .route("/userinfo", get(oauth::userinfo))
+        .route("/jwks", get(oauth::jwks));
+
     let app = Router::new()
         // .route("/", get(root))
         .route("/health-check", get(health_check))
         .route("/user", post(user::create))
         .route("/user/login", post(user::login))
+        .route("/user/login/mfa", post(user::login_mfa))
+        .route("/user/logout", post(user::logout))
+        .route("/user/restore", post(user::restore))
+        .route("/user/token/refresh", post(user::refresh))
+        .route("/user/password/forgot", post(user::forgot_password))
+        .route("/user/password/reset", post(user::reset_forgotten_password))
+        .route("/user/verify", get(user::verify_email))
+        .route("/user/export/download", get(user::download_export))
         .route(
             "/user/:id",
-            get(user::get).put(user::update).delete(user::delete),
+            get(user::get)
+                .put(user::update)
+                .patch(user::patch)
+                .delete(user::delete),
+        )
+        .route("/user/:id/password", put(user::change_password))
+        .route("/user/:id/export", get(user::export))
+        .route("/user/:id/export/:export_id", get(user::get_export))
+        .route(
+            "/user/:id/email/verify",
+            post(user::resend_verification_email),
+        )
+        .route("/user/:id/totp", delete(user::disable_totp))
+        .route("/user/:id/totp/enroll", post(user::enroll_totp))
+        .route("/user/:id/totp/confirm", post(user::confirm_totp))
+        .route(
+            "/user/:id/api-keys",
+            get(user::list_api_keys).post(user::create_api_key),
         )
+        .route("/user/:id/api-keys/:key_id", delete(user::revoke_api_key))
+        .route(
+            "/.well-known/openid-configuration",
+            get(oauth::openid_configuration),
+        )
+        .nest("/admin", admin_routes)
+        .nest("/oauth", oauth_routes)
         .nest("/grpc", grpc_routes)
+        // Before the layers, so that unknown routes get a request id as well.
+        .fallback(handle_404.into_service())
         .layer(service);
 
-    let app = app.fallback(handle_404.into_service());
-
     Ok(app)
 }
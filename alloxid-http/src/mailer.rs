@@ -0,0 +1,172 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use async_std::fs::OpenOptions;
+use async_std::io::WriteExt;
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::error::ServiceError;
+use crate::settings;
+
+/// A plain text mail. The sender is the same for all mails, see `Settings.email.from`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Delivers mail to users. Implementations are picked in `Settings.mailer`.
+#[async_trait::async_trait]
+pub trait Mailer: fmt::Debug + Send + Sync {
+    async fn send(&self, email: &Email) -> Result<(), ServiceError>;
+}
+
+pub fn from_settings(
+    settings: &settings::Mailer,
+    from: &str,
+) -> Result<Arc<dyn Mailer>, ServiceError> {
+    let mailer: Arc<dyn Mailer> = match settings {
+        settings::Mailer::Log => Arc::new(LogMailer),
+        settings::Mailer::File { path } => Arc::new(FileMailer {
+            path: settings::crate_root().join(path),
+        }),
+        settings::Mailer::Smtp { .. } => Arc::new(SmtpMailer::from_settings(settings, from)?),
+    };
+
+    Ok(mailer)
+}
+
+/// Drops all mail. Bodies carry verification links, so only the recipient and subject are logged.
+#[derive(Debug)]
+pub struct LogMailer;
+
+#[async_trait::async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, email: &Email) -> Result<(), ServiceError> {
+        warn!(
+            "LogMailer is meant for development only, dropped mail to={} subject={:?}",
+            email.to, email.subject
+        );
+        Ok(())
+    }
+}
+
+/// Appends every mail as a line of JSON to a file, so that tests can read them.
+#[derive(Debug)]
+pub struct FileMailer {
+    path: PathBuf,
+}
+
+#[async_trait::async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: &Email) -> Result<(), ServiceError> {
+        let mut line = serde_json::to_vec(email)?;
+        line.push(b'\n');
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .map_err(|err| ServiceError::Internal(err.to_string()))?;
+
+        // A single write per line, so that concurrent mails don't interleave.
+        file.write_all(&line)
+            .await
+            .map_err(|err| ServiceError::Internal(err.to_string()))?;
+
+        info!("Wrote mail to {}", self.path.display());
+        Ok(())
+    }
+}
+
+pub struct SmtpMailer {
+    from: Mailbox,
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpMailer {
+    fn from_settings(settings: &settings::Mailer, from: &str) -> Result<Self, ServiceError> {
+        let (host, port, starttls, username, password) = match settings {
+            settings::Mailer::Smtp {
+                host,
+                port,
+                starttls,
+                username,
+                password,
+            } => (host, *port, *starttls, username, password),
+            _ => unreachable!("SmtpMailer is only built from SMTP settings"),
+        };
+
+        let from = from
+            .parse()
+            .map_err(|err| ServiceError::Config(format!("Invalid sender {}: {}", from, err)))?;
+
+        let builder = if starttls {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+        } else {
+            AsyncSmtpTransport::<Tokio1Executor>::relay(host)
+        }
+        .map_err(|err| ServiceError::Config(format!("Invalid SMTP host {}: {}", host, err)))?
+        .port(port);
+
+        let builder = match (username, password) {
+            (Some(username), Some(password)) => {
+                builder.credentials(Credentials::new(username.clone(), password.clone()))
+            }
+            (None, None) => builder,
+            _ => {
+                return Err(ServiceError::Config(
+                    "SMTP needs both a username and a password, or neither".to_string(),
+                ))
+            }
+        };
+
+        Ok(Self {
+            from,
+            transport: builder.build(),
+        })
+    }
+}
+
+// The transport holds the credentials, so it's left out.
+impl fmt::Debug for SmtpMailer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SmtpMailer")
+            .field("from", &self.from)
+            .finish()
+    }
+}
+
+#[async_trait::async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: &Email) -> Result<(), ServiceError> {
+        let to: Mailbox = email
+            .to
+            .parse()
+            .map_err(|err| ServiceError::Internal(format!("Invalid recipient: {}", err)))?;
+
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(&email.subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(email.body.clone())
+            .map_err(|err| ServiceError::Internal(format!("Failed to build mail: {}", err)))?;
+
+        self.transport
+            .send(message)
+            .await
+            .map_err(|err| ServiceError::Unavailable(format!("Failed to send mail: {}", err)))?;
+
+        info!("Sent mail via SMTP");
+        Ok(())
+    }
+}
@@ -1,3 +1,5 @@
+use std::net::SocketAddr;
+
 use alloxid_http::settings::Settings;
 use alloxid_http::{configure_app, Result};
 use sqlx::postgres::PgPool;
@@ -23,7 +25,7 @@ async fn main() -> Result<()> {
     );
 
     axum::Server::bind(&address.parse().expect("Failed to parse app address."))
-        .serve(app.into_make_service())
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
         .await
         .unwrap();
 
@@ -0,0 +1,44 @@
+use std::time::Duration;
+
+use async_std::task;
+use chrono::Utc;
+use sqlx::PgPool;
+use tracing::{debug, error, info};
+
+use crate::database::{
+    delete_expired_data_exports, delete_expired_mfa_tokens, purge_deleted_users,
+};
+use crate::settings::Deletion;
+
+/// Spawns the task that deletes accounts for good once their grace period has passed, along with
+/// expired data exports and used MFA tokens.
+pub(crate) fn spawn(pool: PgPool, settings: Deletion) {
+    task::spawn(async move {
+        let interval = Duration::from_secs(settings.purge_interval_seconds);
+
+        // Stops together with the app, once the pool has been closed.
+        while !pool.is_closed() {
+            let deleted_before = Utc::now() - chrono::Duration::days(settings.grace_period_days);
+
+            match purge_deleted_users(&pool, deleted_before).await {
+                Ok(0) => debug!("No deleted users to purge"),
+                Ok(purged) => info!("Purged {} deleted users", purged),
+                Err(err) => error!("Failed to purge deleted users: {:?}", err),
+            }
+
+            match delete_expired_data_exports(&pool).await {
+                Ok(0) => debug!("No expired data exports to delete"),
+                Ok(deleted) => info!("Deleted {} expired data exports", deleted),
+                Err(err) => error!("Failed to delete expired data exports: {:?}", err),
+            }
+
+            match delete_expired_mfa_tokens(&pool).await {
+                Ok(0) => debug!("No used MFA tokens to delete"),
+                Ok(deleted) => info!("Deleted {} used MFA tokens", deleted),
+                Err(err) => error!("Failed to delete used MFA tokens: {:?}", err),
+            }
+
+            task::sleep(interval).await;
+        }
+    });
+}
@@ -0,0 +1,41 @@
+use std::fmt;
+
+use axum::middleware::Next;
+use axum::response::Response;
+use http::{HeaderValue, Request};
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+tokio::task_local! {
+    static REQUEST_ID: RequestId;
+}
+
+/// Identifies a single request across logs and error responses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RequestId(pub Uuid);
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// The id of the request currently being handled, if any.
+pub fn current() -> Option<RequestId> {
+    REQUEST_ID.try_with(|id| *id).ok()
+}
+
+/// Middleware assigning every request an id, which is available from the request extensions,
+/// through `current` while the request is handled, and in the `x-request-id` response header.
+pub(crate) async fn assign_request_id<B>(mut req: Request<B>, next: Next<B>) -> Response {
+    let id = RequestId(Uuid::new_v4());
+    req.extensions_mut().insert(id);
+
+    let mut res = REQUEST_ID.scope(id, next.run(req)).await;
+
+    let value = HeaderValue::from_str(&id.to_string()).expect("Uuid is a valid header value");
+    res.headers_mut().insert(REQUEST_ID_HEADER, value);
+
+    res
+}
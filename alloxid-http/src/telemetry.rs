@@ -4,6 +4,7 @@ use tracing_log::LogTracer;
 use tracing_subscriber::EnvFilter;
 use uuid::Uuid;
 
+use crate::request_id;
 use crate::StateExtension;
 
 pub struct LogInfo {
@@ -14,7 +15,9 @@ pub struct LogInfo {
 
 impl LogInfo {
     pub fn from_req(state: StateExtension) -> Self {
-        let req_id = Uuid::new_v4();
+        let req_id = request_id::current()
+            .map(|id| id.0)
+            .unwrap_or_else(Uuid::new_v4);
         Self {
             app_port: state.settings.app.port,
             req_id,
@@ -0,0 +1,80 @@
+use hmac::{Hmac, Mac};
+use rand::{thread_rng, RngCore};
+use sha1::Sha1;
+
+const ALPHABET: base32::Alphabet = base32::Alphabet::RFC4648 { padding: false };
+
+/// Codes are valid for 30 seconds, the default of authenticator apps.
+pub const STEP_SECONDS: i64 = 30;
+
+const DIGITS: u32 = 6;
+
+/// Generates a new base32 encoded secret of 160 bits, as recommended by RFC 4226.
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; 20];
+    thread_rng().fill_bytes(&mut bytes);
+
+    base32::encode(ALPHABET, &bytes)
+}
+
+/// The URI authenticator apps read from a QR code.
+pub fn otpauth_uri(issuer: &str, account: &str, secret: &str) -> String {
+    format!(
+        "otpauth://totp/{issuer}:{account}?secret={secret}&issuer={issuer}&algorithm=SHA1&digits={digits}&period={period}",
+        issuer = percent_encode(issuer),
+        account = percent_encode(account),
+        secret = secret,
+        digits = DIGITS,
+        period = STEP_SECONDS,
+    )
+}
+
+/// The time step a unix timestamp falls into.
+pub fn step_at(timestamp: i64) -> i64 {
+    timestamp.div_euclid(STEP_SECONDS)
+}
+
+/// The code for the given time step, or `None` if the secret isn't valid base32.
+pub fn code_at(secret: &str, step: i64) -> Option<String> {
+    let key = base32::decode(ALPHABET, secret)?;
+
+    let mut mac = Hmac::<Sha1>::new_from_slice(&key).ok()?;
+    mac.update(&step.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    // Dynamic truncation, see RFC 4226 section 5.3.
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+
+    Some(format!(
+        "{:0width$}",
+        binary % 10u32.pow(DIGITS),
+        width = DIGITS as usize
+    ))
+}
+
+/// Checks the code against the current time step and its neighbours, to allow for clock drift.
+///
+/// Returns the matching time step, so that callers can reject codes that have been used before.
+pub fn verify(secret: &str, code: &str, timestamp: i64) -> Option<i64> {
+    let code = code.trim();
+    let current = step_at(timestamp);
+
+    (current - 1..=current + 1).find(|step| code_at(secret, *step).as_deref() == Some(code))
+}
+
+fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
@@ -0,0 +1,223 @@
+#![allow(clippy::expect_fun_call)]
+
+use tracing::{info, instrument};
+use uuid::Uuid;
+
+use alloxid_http::model::admin::{AdminUserData, Page};
+use alloxid_http::model::user::UserAuthData;
+use alloxid_http::JsonBody;
+
+mod helpers;
+use helpers::{create_admin, create_user, get_user, login, spawn_test_app, TestApp, PASSWORD};
+
+fn admin_request(
+    app: &TestApp,
+    method: reqwest::Method,
+    route: &str,
+    token: &str,
+) -> reqwest::RequestBuilder {
+    reqwest::Client::new()
+        .request(method, format!("{}{}", app.address, route))
+        .header("Authorization", format!("Bearer {}", token))
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn admin_routes_without_admin_role_return_403() {
+    let app = spawn_test_app().await;
+    info!(
+        "admin_routes_without_admin_role_return_403: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let user = create_user(&app, "synul").await;
+
+    let res = admin_request(&app, reqwest::Method::GET, "/admin/users", &user.token)
+        .send()
+        .await
+        .expect("Failed to execute GET request at /admin/users");
+    dbg!(&res);
+    assert_eq!(res.status(), 403);
+
+    let res = reqwest::get(format!("{}/admin/users", app.address))
+        .await
+        .expect("Failed to execute GET request at /admin/users");
+    assert_eq!(res.status(), 401);
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn admin_lists_and_gets_users() {
+    let app = spawn_test_app().await;
+    info!(
+        "admin_lists_and_gets_users: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let admin = create_admin(&app).await;
+    let user = create_user(&app, "synul").await;
+
+    let route = "/admin/users?page=2&per_page=1";
+    let res = admin_request(&app, reqwest::Method::GET, route, &admin.token)
+        .send()
+        .await
+        .expect(&format!("Failed to execute GET request at {}", route));
+    dbg!(&res);
+    assert_eq!(res.status(), 200);
+
+    let body: JsonBody<Page<AdminUserData>> = res.json().await.unwrap();
+    let page = body.data;
+    assert_eq!(page.total, 2);
+    assert_eq!(page.page, 2);
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(page.items[0].id, user.id);
+
+    let route = "/admin/users?page=9223372036854775807";
+    let res = admin_request(&app, reqwest::Method::GET, route, &admin.token)
+        .send()
+        .await
+        .expect(&format!("Failed to execute GET request at {}", route));
+    assert_eq!(res.status(), 400);
+
+    let route = format!("/admin/users/{}", user.id);
+    let res = admin_request(&app, reqwest::Method::GET, &route, &admin.token)
+        .send()
+        .await
+        .expect(&format!("Failed to execute GET request at {}", route));
+    assert_eq!(res.status(), 200);
+
+    let body: JsonBody<AdminUserData> = res.json().await.unwrap();
+    assert_eq!(body.data.username, "synul");
+    assert_eq!(body.data.role, "User");
+
+    // Admins may also use the regular user routes for any user.
+    let res = get_user(&app, user.id, &admin.token).await;
+    assert_eq!(res.status(), 200);
+
+    let route = format!("/admin/users/{}", Uuid::new_v4());
+    let res = admin_request(&app, reqwest::Method::GET, &route, &admin.token)
+        .send()
+        .await
+        .expect(&format!("Failed to execute GET request at {}", route));
+    assert_eq!(res.status(), 404);
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn admin_disables_and_enables_user() {
+    let app = spawn_test_app().await;
+    info!(
+        "admin_disables_and_enables_user: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let admin = create_admin(&app).await;
+    let user = create_user(&app, "synul").await;
+
+    let route = format!("/admin/users/{}/disable", user.id);
+    let res = admin_request(&app, reqwest::Method::POST, &route, &admin.token)
+        .send()
+        .await
+        .expect(&format!("Failed to execute POST request at {}", route));
+    assert_eq!(res.status(), 200);
+
+    let body: JsonBody<AdminUserData> = res.json().await.unwrap();
+    assert!(body.data.disabled_at.is_some());
+
+    // Existing sessions are gone and new ones can't be created.
+    let res = get_user(&app, user.id, &user.token).await;
+    assert_eq!(res.status(), 401);
+    let res = login(&app, "synul", PASSWORD).await;
+    assert_eq!(res.status(), 403);
+
+    let route = format!("/admin/users/{}/enable", user.id);
+    let res = admin_request(&app, reqwest::Method::POST, &route, &admin.token)
+        .send()
+        .await
+        .expect(&format!("Failed to execute POST request at {}", route));
+    assert_eq!(res.status(), 200);
+
+    let res = login(&app, "synul", PASSWORD).await;
+    assert_eq!(res.status(), 200);
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn admin_promotes_user() {
+    let app = spawn_test_app().await;
+    info!(
+        "admin_promotes_user: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let admin = create_admin(&app).await;
+    let user = create_user(&app, "synul").await;
+
+    let route = format!("/admin/users/{}/role", user.id);
+    let res = admin_request(&app, reqwest::Method::PUT, &route, &admin.token)
+        .json(&serde_json::json!({ "role": "Admin" }))
+        .send()
+        .await
+        .expect(&format!("Failed to execute PUT request at {}", route));
+    assert_eq!(res.status(), 200);
+
+    let body: JsonBody<AdminUserData> = res.json().await.unwrap();
+    assert_eq!(body.data.role, "Admin");
+
+    // The old token still carries the old role, so it has been revoked.
+    let res = get_user(&app, user.id, &user.token).await;
+    assert_eq!(res.status(), 401);
+
+    let res = login(&app, "synul", PASSWORD).await;
+    let body: JsonBody<UserAuthData> = res.json().await.unwrap();
+    let promoted = body.data;
+
+    let res = admin_request(&app, reqwest::Method::GET, "/admin/users", &promoted.token)
+        .send()
+        .await
+        .expect("Failed to execute GET request at /admin/users");
+    assert_eq!(res.status(), 200);
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn admin_forces_password_reset_and_deletes_user() {
+    let app = spawn_test_app().await;
+    info!(
+        "admin_forces_password_reset_and_deletes_user: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let admin = create_admin(&app).await;
+    let user = create_user(&app, "synul").await;
+
+    let route = format!("/admin/users/{}/password-reset", user.id);
+    let res = admin_request(&app, reqwest::Method::POST, &route, &admin.token)
+        .send()
+        .await
+        .expect(&format!("Failed to execute POST request at {}", route));
+    assert_eq!(res.status(), 200);
+
+    let res = get_user(&app, user.id, &user.token).await;
+    assert_eq!(res.status(), 401);
+    let res = login(&app, "synul", PASSWORD).await;
+    assert_eq!(res.status(), 403);
+
+    let route = format!("/admin/users/{}", user.id);
+    let res = admin_request(&app, reqwest::Method::DELETE, &route, &admin.token)
+        .send()
+        .await
+        .expect(&format!("Failed to execute DELETE request at {}", route));
+    assert_eq!(res.status(), 200);
+
+    let res = admin_request(&app, reqwest::Method::GET, &route, &admin.token)
+        .send()
+        .await
+        .expect(&format!("Failed to execute GET request at {}", route));
+    assert_eq!(res.status(), 404);
+}
@@ -0,0 +1,199 @@
+#![allow(clippy::expect_fun_call)]
+
+use tracing::{info, instrument};
+use uuid::Uuid;
+
+use alloxid_http::model::api_key::{ApiKeyCreatedData, ApiKeyData};
+use alloxid_http::model::user::UserAuthData;
+use alloxid_http::JsonBody;
+
+mod helpers;
+use helpers::{create_user, spawn_test_app, TestApp, PASSWORD};
+
+async fn create_api_key(
+    app: &TestApp,
+    user: &UserAuthData,
+    scopes: serde_json::Value,
+) -> reqwest::Response {
+    let route = format!("/user/{}/api-keys", user.id);
+
+    reqwest::Client::new()
+        .post(format!("{}{}", app.address, &route))
+        .header("Authorization", format!("Bearer {}", user.token))
+        .json(&serde_json::json!({ "name": "nightly export", "scopes": scopes }))
+        .send()
+        .await
+        .expect(&format!("Failed to execute POST request at {}", &route))
+}
+
+async fn get_user(app: &TestApp, user_id: Uuid, key: &str) -> reqwest::Response {
+    let route = format!("/user/{}", user_id);
+
+    reqwest::Client::new()
+        .get(format!("{}{}", app.address, &route))
+        .header("X-Api-Key", key)
+        .send()
+        .await
+        .expect(&format!("Failed to execute GET request at {}", &route))
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn api_key_is_limited_to_its_scopes_until_revoked() {
+    let app = spawn_test_app().await;
+    info!(
+        "api_key_is_limited_to_its_scopes_until_revoked: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let user = create_user(&app, "synul").await;
+
+    let res = create_api_key(&app, &user, serde_json::json!(["user:read"])).await;
+    dbg!(&res);
+    assert_eq!(res.status(), 201);
+    let body: JsonBody<ApiKeyCreatedData> = res.json().await.unwrap();
+    let created = body.data;
+    assert!(created.key.starts_with(&created.api_key.prefix));
+
+    // The key itself is never shown again.
+    let res = reqwest::Client::new()
+        .get(format!("{}/user/{}/api-keys", app.address, user.id))
+        .header("Authorization", format!("Bearer {}", user.token))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    let body: serde_json::Value = res.json().await.unwrap();
+    assert!(!body.to_string().contains(&created.key));
+    let keys: Vec<ApiKeyData> = serde_json::from_value(body["data"].clone()).unwrap();
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0].id, created.api_key.id);
+
+    let res = get_user(&app, user.id, &created.key).await;
+    assert_eq!(res.status(), 200);
+
+    let res = get_user(&app, user.id, "alx_not-a-key").await;
+    assert_eq!(res.status(), 401);
+
+    // Writing needs the `user:write` scope.
+    let res = reqwest::Client::new()
+        .put(format!("{}/user/{}", app.address, user.id))
+        .header("X-Api-Key", &created.key)
+        .json(&serde_json::json!({ "username": "synul2" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 403);
+
+    // Even with `user:write`, keys may edit the profile but not the credentials.
+    let res = create_api_key(&app, &user, serde_json::json!(["user:write"])).await;
+    assert_eq!(res.status(), 201);
+    let writer = res
+        .json::<JsonBody<ApiKeyCreatedData>>()
+        .await
+        .unwrap()
+        .data;
+    for (patch, status) in [
+        (serde_json::json!({ "display_name": "Synul" }), 200),
+        (serde_json::json!({ "username": "synul2" }), 403),
+        (serde_json::json!({ "email": "synul@example.com" }), 403),
+    ] {
+        let res = reqwest::Client::new()
+            .patch(format!("{}/user/{}", app.address, user.id))
+            .header("X-Api-Key", &writer.key)
+            .json(&patch)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), status, "Unexpected status for {}", patch);
+    }
+
+    // Neither through the older route that only replaces the username.
+    let res = reqwest::Client::new()
+        .put(format!("{}/user/{}", app.address, user.id))
+        .header("X-Api-Key", &writer.key)
+        .json(&serde_json::json!({ "username": "synul2" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 403);
+
+    // Keys can't be used to create more keys.
+    let res = reqwest::Client::new()
+        .post(format!("{}/user/{}/api-keys", app.address, user.id))
+        .header("X-Api-Key", &created.key)
+        .json(&serde_json::json!({ "name": "copy", "scopes": ["user:write"] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 403);
+
+    let res = reqwest::Client::new()
+        .delete(format!(
+            "{}/user/{}/api-keys/{}",
+            app.address, user.id, created.api_key.id
+        ))
+        .header("Authorization", format!("Bearer {}", user.token))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+
+    let res = get_user(&app, user.id, &created.key).await;
+    assert_eq!(res.status(), 401);
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn admin_scope_is_required_for_admin_routes() {
+    let app = spawn_test_app().await;
+    info!(
+        "admin_scope_is_required_for_admin_routes: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let user = create_user(&app, "synul").await;
+    let res = create_api_key(&app, &user, serde_json::json!(["admin"])).await;
+    assert_eq!(res.status(), 422);
+
+    let admin = create_user(&app, "moderator").await;
+    sqlx::query("update users set role = 'Admin' where id = $1")
+        .bind(admin.id)
+        .execute(&app.test_db.pool())
+        .await
+        .expect("Failed to promote user to admin.");
+
+    // The role is part of the token, so log in again as admin.
+    let res = reqwest::Client::new()
+        .post(format!("{}/user/login", app.address))
+        .json(&serde_json::json!({ "username": "moderator", "password": PASSWORD }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    let body: JsonBody<UserAuthData> = res.json().await.unwrap();
+    let admin = body.data;
+
+    let list_users = |key: String| {
+        reqwest::Client::new()
+            .get(format!("{}/admin/users", app.address))
+            .header("X-Api-Key", key)
+            .send()
+    };
+
+    let res = create_api_key(&app, &admin, serde_json::json!(["user:read"])).await;
+    let body: JsonBody<ApiKeyCreatedData> = res.json().await.unwrap();
+    let res = list_users(body.data.key.clone()).await.unwrap();
+    assert_eq!(res.status(), 403);
+    // Nor does it allow reading other users.
+    let res = get_user(&app, user.id, &body.data.key).await;
+    assert_eq!(res.status(), 403);
+
+    let res = create_api_key(&app, &admin, serde_json::json!(["admin"])).await;
+    assert_eq!(res.status(), 201);
+    let body: JsonBody<ApiKeyCreatedData> = res.json().await.unwrap();
+    let res = list_users(body.data.key).await.unwrap();
+    assert_eq!(res.status(), 200);
+}
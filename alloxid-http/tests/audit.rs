@@ -0,0 +1,124 @@
+#![allow(clippy::expect_fun_call)]
+
+use tracing::{info, instrument};
+
+use alloxid_http::model::audit::AuditPage;
+use alloxid_http::JsonBody;
+
+mod helpers;
+use helpers::{create_admin, create_user, spawn_test_app, TestApp, PASSWORD};
+
+async fn login(app: &TestApp, username: &str, password: &str) -> reqwest::Response {
+    let route = "/user/login";
+
+    reqwest::Client::new()
+        .post(format!("{}{}", app.address, route))
+        .header("User-Agent", "audit-test")
+        .json(&serde_json::json!({ "username": username, "password": password }))
+        .send()
+        .await
+        .expect(&format!("Failed to execute POST request at {}", route))
+}
+
+async fn list_audit(app: &TestApp, query: &str, token: &str) -> reqwest::Response {
+    let route = format!("/admin/audit?{}", query);
+
+    reqwest::Client::new()
+        .get(format!("{}{}", app.address, route))
+        .bearer_auth(token)
+        .send()
+        .await
+        .expect(&format!("Failed to execute GET request at {}", route))
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn logins_are_recorded_and_filtered() {
+    let app = spawn_test_app().await;
+    info!(
+        "logins_are_recorded_and_filtered: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let admin = create_admin(&app).await;
+    let user = create_user(&app, "synul").await;
+
+    let res = login(&app, "synul", "wrong password").await;
+    assert_eq!(res.status(), 401);
+    let res = login(&app, "synul", PASSWORD).await;
+    assert_eq!(res.status(), 200);
+
+    let res = list_audit(&app, "", &user.token).await;
+    assert_eq!(res.status(), 403);
+
+    let query = format!("user_id={}&action=user.login_failed", user.id);
+    let res = list_audit(&app, &query, &admin.token).await;
+    assert_eq!(res.status(), 200);
+    let page = res.json::<JsonBody<AuditPage>>().await.unwrap().data;
+    assert_eq!(page.items.len(), 1);
+    assert!(page.next_cursor.is_none());
+
+    let event = &page.items[0];
+    assert_eq!(event.action, "user.login_failed");
+    assert_eq!(event.actor_id, None);
+    assert_eq!(event.subject_id, Some(user.id));
+    assert_eq!(event.details["reason"], "invalid_credentials");
+    assert_eq!(event.user_agent.as_deref(), Some("audit-test"));
+    assert!(event.request_id.is_some());
+
+    let query = format!("user_id={}&action=user.login", user.id);
+    let res = list_audit(&app, &query, &admin.token).await;
+    let page = res.json::<JsonBody<AuditPage>>().await.unwrap().data;
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(page.items[0].actor_id, Some(user.id));
+
+    // Creating the user is recorded as well.
+    let query = format!("user_id={}", user.id);
+    let res = list_audit(&app, &query, &admin.token).await;
+    let page = res.json::<JsonBody<AuditPage>>().await.unwrap().data;
+    let actions: Vec<&str> = page.items.iter().map(|e| e.action.as_str()).collect();
+    assert_eq!(
+        actions,
+        vec!["user.login", "user.login_failed", "user.created"]
+    );
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn audit_log_is_paginated_with_cursor() {
+    let app = spawn_test_app().await;
+    info!(
+        "audit_log_is_paginated_with_cursor: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let admin = create_admin(&app).await;
+    let user = create_user(&app, "synul").await;
+    for _ in 0..2 {
+        let res = login(&app, "synul", PASSWORD).await;
+        assert_eq!(res.status(), 200);
+    }
+
+    let mut ids = Vec::new();
+    let mut query = format!("user_id={}&limit=1", user.id);
+    loop {
+        let res = list_audit(&app, &query, &admin.token).await;
+        assert_eq!(res.status(), 200);
+        let page = res.json::<JsonBody<AuditPage>>().await.unwrap().data;
+        assert_eq!(page.items.len(), 1);
+        ids.push(page.items[0].id);
+
+        match page.next_cursor {
+            Some(cursor) => query = format!("user_id={}&limit=1&cursor={}", user.id, cursor),
+            None => break,
+        }
+    }
+    assert_eq!(ids.len(), 3);
+    ids.dedup();
+    assert_eq!(ids.len(), 3);
+
+    let res = list_audit(&app, "cursor=garbage", &admin.token).await;
+    assert_eq!(res.status(), 400);
+}
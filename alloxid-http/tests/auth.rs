@@ -0,0 +1,377 @@
+#![allow(clippy::expect_fun_call)]
+
+use jsonwebtoken::{dangerous_insecure_decode, encode, Algorithm, EncodingKey, Header};
+use serde::{Deserialize, Serialize};
+use tracing::{info, instrument};
+use uuid::Uuid;
+
+use alloxid_http::error::JsonError;
+use alloxid_http::model::user::UserAuthData;
+use alloxid_http::password::{Argon2Hasher, PasswordHasher};
+use alloxid_http::settings::{JwtKey, Settings};
+use alloxid_http::JsonBody;
+
+mod helpers;
+use helpers::{
+    create_user, get_user, login, spawn_test_app, spawn_test_app_with_settings, TestApp, PASSWORD,
+};
+
+#[derive(Deserialize, Serialize)]
+struct TestClaims {
+    sub: Uuid,
+    role: String,
+    exp: usize,
+    jti: Uuid,
+}
+
+// Signs a token for the same user and session as the given token, but with the given key.
+fn forge_token(token: &str, kid: &str, secret: &str) -> String {
+    let valid_claims = dangerous_insecure_decode::<TestClaims>(token)
+        .expect("Failed to decode token")
+        .claims;
+
+    let exp = chrono::Utc::now() + chrono::Duration::minutes(5);
+    let claims = TestClaims {
+        exp: exp.timestamp() as usize,
+        ..valid_claims
+    };
+
+    let mut header = Header::new(Algorithm::HS512);
+    header.kid = Some(kid.to_string());
+
+    encode(
+        &header,
+        &claims,
+        &EncodingKey::from_secret(secret.as_bytes()),
+    )
+    .expect("Failed to encode token")
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn token_with_unknown_key_returns_401() {
+    let app = spawn_test_app().await;
+    info!(
+        "token_with_unknown_key_returns_401: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let user = create_user(&app, "synul").await;
+
+    // Right key id, wrong secret.
+    let token = forge_token(&user.token, "dev", "totally secret");
+    let res = get_user(&app, user.id, &token).await;
+    dbg!(&res);
+    assert_eq!(res.status(), 401);
+
+    // Key id that isn't configured at all.
+    let token = forge_token(&user.token, "unknown", "totally secret");
+    let res = get_user(&app, user.id, &token).await;
+    dbg!(&res);
+    assert_eq!(res.status(), 401);
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn token_signed_with_rotated_key_is_accepted() {
+    let mut settings = Settings::new_for_test().expect("Failed to load configuration.");
+    settings.auth.signing_key = JwtKey::hmac("new", Algorithm::HS512, "new secret");
+    settings.auth.verification_keys = vec![JwtKey::hmac("old", Algorithm::HS512, "old secret")];
+
+    let app = spawn_test_app_with_settings(settings).await;
+    info!(
+        "token_signed_with_rotated_key_is_accepted: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let user = create_user(&app, "synul").await;
+
+    // Tokens issued by the app are signed with the new key.
+    let res = get_user(&app, user.id, &user.token).await;
+    dbg!(&res);
+    assert_eq!(res.status(), 200);
+
+    // Tokens signed with the old key are still valid.
+    let token = forge_token(&user.token, "old", "old secret");
+    let res = get_user(&app, user.id, &token).await;
+    dbg!(&res);
+    assert_eq!(res.status(), 200);
+}
+
+async fn refresh(app: &TestApp, refresh_token: &str) -> reqwest::Response {
+    let route = "/user/token/refresh";
+
+    reqwest::Client::new()
+        .post(format!("{}{}", app.address, route))
+        .json(&serde_json::json!({ "refresh_token": refresh_token }))
+        .send()
+        .await
+        .expect(&format!("Failed to execute POST request at {}", route))
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn refresh_rotates_token_and_detects_reuse() {
+    let app = spawn_test_app().await;
+    info!(
+        "refresh_rotates_token_and_detects_reuse: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let user = create_user(&app, "synul").await;
+
+    let res = refresh(&app, &user.refresh_token).await;
+    dbg!(&res);
+    assert_eq!(res.status(), 200);
+
+    let body: JsonBody<UserAuthData> = res.json().await.unwrap();
+    let rotated = body.data;
+    assert_eq!(rotated.id, user.id);
+    assert_ne!(rotated.refresh_token, user.refresh_token);
+
+    let res = get_user(&app, user.id, &rotated.token).await;
+    assert_eq!(res.status(), 200);
+
+    // Reusing the old refresh token is rejected...
+    let res = refresh(&app, &user.refresh_token).await;
+    dbg!(&res);
+    assert_eq!(res.status(), 401);
+
+    // ...and revokes the token that replaced it as well.
+    let res = refresh(&app, &rotated.refresh_token).await;
+    dbg!(&res);
+    assert_eq!(res.status(), 401);
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn logout_revokes_refresh_token() {
+    let app = spawn_test_app().await;
+    info!(
+        "logout_revokes_refresh_token: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let user = create_user(&app, "synul").await;
+
+    let route = "/user/logout";
+    let res = reqwest::Client::new()
+        .post(format!("{}{}", app.address, route))
+        .json(&serde_json::json!({ "refresh_token": user.refresh_token }))
+        .send()
+        .await
+        .expect(&format!("Failed to execute POST request at {}", route));
+    dbg!(&res);
+    assert_eq!(res.status(), 200);
+
+    let res = refresh(&app, &user.refresh_token).await;
+    dbg!(&res);
+    assert_eq!(res.status(), 401);
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn logout_revokes_access_token() {
+    let app = spawn_test_app().await;
+    info!(
+        "logout_revokes_access_token: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let user = create_user(&app, "synul").await;
+
+    let res = get_user(&app, user.id, &user.token).await;
+    assert_eq!(res.status(), 200);
+
+    let route = "/user/logout";
+    let res = reqwest::Client::new()
+        .post(format!("{}{}", app.address, route))
+        .json(&serde_json::json!({ "refresh_token": user.refresh_token }))
+        .send()
+        .await
+        .expect(&format!("Failed to execute POST request at {}", route));
+    assert_eq!(res.status(), 200);
+
+    // The access token hasn't expired yet, but its session is gone.
+    let res = get_user(&app, user.id, &user.token).await;
+    dbg!(&res);
+    assert_eq!(res.status(), 401);
+}
+
+fn retry_after(res: &reqwest::Response) -> u64 {
+    res.headers()
+        .get("Retry-After")
+        .expect("Missing Retry-After header")
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap()
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn repeated_failed_logins_lock_out_username() {
+    let mut settings = Settings::new_for_test().expect("Failed to load configuration.");
+    settings.auth.login_throttle.username_free_attempts = 2;
+    settings.auth.login_throttle.ip_free_attempts = 100;
+    settings.auth.login_throttle.backoff_seconds = 60;
+
+    let app = spawn_test_app_with_settings(settings).await;
+    info!(
+        "repeated_failed_logins_lock_out_username: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    create_user(&app, "synul").await;
+
+    for _ in 0..2 {
+        let res = login(&app, "synul", "wrong password").await;
+        assert_eq!(res.status(), 401);
+    }
+
+    // Even the right password is rejected during the lockout.
+    let res = login(&app, "synul", PASSWORD).await;
+    dbg!(&res);
+    assert_eq!(res.status(), 429);
+    let seconds = retry_after(&res);
+    assert!(seconds > 0 && seconds <= 60);
+
+    let body: JsonError = res.json().await.unwrap();
+    assert_eq!(body.error.code, "too_many_requests");
+
+    // Unknown usernames behave the same, so they can't be told apart from existing ones.
+    for _ in 0..2 {
+        let res = login(&app, "nobody", "wrong password").await;
+        assert_eq!(res.status(), 401);
+    }
+    let res = login(&app, "nobody", "wrong password").await;
+    assert_eq!(res.status(), 429);
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn full_login_throttle_keeps_counting_failures() {
+    let mut settings = Settings::new_for_test().expect("Failed to load configuration.");
+    settings.auth.login_throttle.username_free_attempts = 2;
+    settings.auth.login_throttle.ip_free_attempts = 100;
+    settings.auth.login_throttle.backoff_seconds = 60;
+    settings.auth.login_throttle.capacity = 3;
+
+    let app = spawn_test_app_with_settings(settings).await;
+    info!(
+        "full_login_throttle_keeps_counting_failures: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    create_user(&app, "synul").await;
+    for _ in 0..2 {
+        let res = login(&app, "synul", "wrong password").await;
+        assert_eq!(res.status(), 401);
+    }
+
+    // Spraying usernames fills the throttle, but neither lifts the lockout nor stops counting.
+    for username in ["alpha", "beta", "gamma", "delta"] {
+        let res = login(&app, username, "wrong password").await;
+        assert_eq!(res.status(), 401);
+    }
+    for _ in 0..2 {
+        let res = login(&app, "nobody", "wrong password").await;
+        assert_eq!(res.status(), 401);
+    }
+
+    let res = login(&app, "nobody", "wrong password").await;
+    assert_eq!(res.status(), 429);
+    let res = login(&app, "synul", PASSWORD).await;
+    assert_eq!(res.status(), 429);
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn repeated_failed_logins_lock_out_ip() {
+    let mut settings = Settings::new_for_test().expect("Failed to load configuration.");
+    settings.auth.login_throttle.username_free_attempts = 100;
+    settings.auth.login_throttle.ip_free_attempts = 3;
+    settings.auth.login_throttle.backoff_seconds = 60;
+
+    let app = spawn_test_app_with_settings(settings).await;
+    info!(
+        "repeated_failed_logins_lock_out_ip: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    create_user(&app, "synul").await;
+
+    for username in ["alice", "bob", "carol"] {
+        let res = login(&app, username, "wrong password").await;
+        assert_eq!(res.status(), 401);
+    }
+
+    let res = login(&app, "synul", PASSWORD).await;
+    dbg!(&res);
+    assert_eq!(res.status(), 429);
+    assert!(retry_after(&res) > 0);
+}
+
+async fn stored_hash(app: &TestApp, user_id: Uuid) -> String {
+    sqlx::query_scalar("select hashed_password from users where id = $1")
+        .bind(user_id)
+        .fetch_one(&app.test_db.pool())
+        .await
+        .expect("Failed to fetch password hash.")
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn outdated_password_hash_is_upgraded_on_login() {
+    let settings = Settings::new_for_test().expect("Failed to load configuration.");
+
+    let mut outdated = settings.clone();
+    outdated.password_hashing.iterations = 1;
+    outdated.password_hashing.memory_kib = 4096;
+
+    let app = spawn_test_app_with_settings(settings.clone()).await;
+    info!(
+        "outdated_password_hash_is_upgraded_on_login: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let user = create_user(&app, "synul").await;
+
+    // Pretend the user signed up before the parameters were raised.
+    let old_hash = Argon2Hasher::from_settings(&outdated)
+        .expect("Failed to create hasher.")
+        .hash(PASSWORD)
+        .expect("Failed to hash password.");
+    assert!(old_hash.contains("m=4096,t=1,"));
+    sqlx::query("update users set hashed_password = $2 where id = $1")
+        .bind(user.id)
+        .bind(&old_hash)
+        .execute(&app.test_db.pool())
+        .await
+        .expect("Failed to replace password hash.");
+
+    let res = login(&app, "synul", PASSWORD).await;
+    assert_eq!(res.status(), 200);
+
+    let new_hash = stored_hash(&app, user.id).await;
+    assert_ne!(new_hash, old_hash);
+    let params = &settings.password_hashing;
+    assert!(new_hash.contains(&format!(
+        "m={},t={},p={}",
+        params.memory_kib, params.iterations, params.parallelism
+    )));
+
+    // The upgraded hash is used from now on.
+    let res = login(&app, "synul", PASSWORD).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(stored_hash(&app, user.id).await, new_hash);
+}
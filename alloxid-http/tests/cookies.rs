@@ -0,0 +1,235 @@
+#![allow(clippy::expect_fun_call)]
+
+use std::collections::HashMap;
+
+use tracing::{info, instrument};
+
+use alloxid_http::model::user::UserAuthData;
+use alloxid_http::settings::Settings;
+use alloxid_http::JsonBody;
+
+mod helpers;
+use helpers::{create_user, login, spawn_test_app_with_settings, TestApp, PASSWORD};
+
+fn cookie_settings() -> Settings {
+    let mut settings = Settings::new_for_test().expect("Failed to load configuration.");
+    settings.auth.session_cookie.enabled = true;
+
+    settings
+}
+
+// Name to the full `Set-Cookie` header, as reqwest is built without cookie support.
+fn set_cookies(res: &reqwest::Response) -> HashMap<String, String> {
+    res.headers()
+        .get_all("set-cookie")
+        .iter()
+        .map(|header| header.to_str().unwrap().to_string())
+        .map(|cookie| (cookie.split('=').next().unwrap().to_string(), cookie))
+        .collect()
+}
+
+fn cookie_value(set_cookie: &str) -> &str {
+    set_cookie
+        .split(';')
+        .next()
+        .and_then(|pair| pair.split_once('='))
+        .unwrap()
+        .1
+}
+
+async fn create_user_and_login(app: &TestApp) -> reqwest::Response {
+    create_user(app, "synul").await;
+
+    let res = login(app, "synul", PASSWORD).await;
+    assert_eq!(res.status(), 200);
+
+    res
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn session_cookie_authenticates_and_requires_csrf_token() {
+    let app = spawn_test_app_with_settings(cookie_settings()).await;
+    info!(
+        "session_cookie_authenticates_and_requires_csrf_token: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let res = create_user_and_login(&app).await;
+    let cookies = set_cookies(&res);
+    let body: JsonBody<UserAuthData> = res.json().await.unwrap();
+    let user = body.data;
+
+    let session = &cookies["alloxid_session"];
+    assert!(session.contains("HttpOnly"));
+    assert!(session.contains("Secure"));
+    assert!(session.contains("SameSite=Lax"));
+    assert!(session.contains("Path=/;"));
+    assert!(cookies["alloxid_refresh"].contains("Path=/user;"));
+    assert!(cookies["alloxid_refresh"].contains("HttpOnly"));
+    // The frontend has to be able to read the CSRF token.
+    assert!(!cookies["alloxid_csrf"].contains("HttpOnly"));
+    assert_eq!(cookie_value(session), user.token);
+
+    let cookie_header = format!(
+        "alloxid_session={}; alloxid_csrf={}",
+        cookie_value(session),
+        cookie_value(&cookies["alloxid_csrf"])
+    );
+    let csrf = cookie_value(&cookies["alloxid_csrf"]);
+    let route = format!("/user/{}", user.id);
+    let client = reqwest::Client::new();
+
+    let res = client
+        .get(format!("{}{}", app.address, &route))
+        .header("Cookie", &cookie_header)
+        .send()
+        .await
+        .expect(&format!("Failed to execute GET request at {}", &route));
+    assert_eq!(res.status(), 200);
+
+    let json = serde_json::json!({ "username": "synul-renamed" });
+    let res = client
+        .put(format!("{}{}", app.address, &route))
+        .header("Cookie", &cookie_header)
+        .json(&json)
+        .send()
+        .await
+        .expect(&format!("Failed to execute PUT request at {}", &route));
+    assert_eq!(res.status(), 403);
+
+    let res = client
+        .put(format!("{}{}", app.address, &route))
+        .header("Cookie", &cookie_header)
+        .header("X-CSRF-Token", "forged")
+        .json(&json)
+        .send()
+        .await
+        .expect(&format!("Failed to execute PUT request at {}", &route));
+    assert_eq!(res.status(), 403);
+
+    let res = client
+        .put(format!("{}{}", app.address, &route))
+        .header("Cookie", &cookie_header)
+        .header("X-CSRF-Token", csrf)
+        .json(&json)
+        .send()
+        .await
+        .expect(&format!("Failed to execute PUT request at {}", &route));
+    assert_eq!(res.status(), 200);
+
+    // Clients using the header are unaffected.
+    let res = client
+        .put(format!("{}{}", app.address, &route))
+        .header("Authorization", format!("Bearer {}", user.token))
+        .json(&serde_json::json!({ "username": "synul" }))
+        .send()
+        .await
+        .expect(&format!("Failed to execute PUT request at {}", &route));
+    assert_eq!(res.status(), 200);
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn refresh_cookie_rotates_and_logout_clears_cookies() {
+    let app = spawn_test_app_with_settings(cookie_settings()).await;
+    info!(
+        "refresh_cookie_rotates_and_logout_clears_cookies: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let res = create_user_and_login(&app).await;
+    let cookies = set_cookies(&res);
+    let refresh_cookie = format!(
+        "alloxid_refresh={}",
+        cookie_value(&cookies["alloxid_refresh"])
+    );
+    let csrf = cookie_value(&cookies["alloxid_csrf"]);
+    let client = reqwest::Client::new();
+
+    // A forged cross-site request only carries the cookie, not the CSRF token.
+    let res = client
+        .post(format!("{}/user/token/refresh", app.address))
+        .header("Cookie", &refresh_cookie)
+        .send()
+        .await
+        .expect("Failed to send refresh request.");
+    assert_eq!(res.status(), 403);
+
+    let res = client
+        .post(format!("{}/user/token/refresh", app.address))
+        .header("Cookie", &refresh_cookie)
+        .header("X-CSRF-Token", csrf)
+        .send()
+        .await
+        .expect("Failed to send refresh request.");
+    assert_eq!(res.status(), 200);
+    let rotated = set_cookies(&res);
+    assert_ne!(
+        cookie_value(&rotated["alloxid_refresh"]),
+        cookie_value(&cookies["alloxid_refresh"])
+    );
+
+    // The old refresh token has been rotated away.
+    let res = client
+        .post(format!("{}/user/token/refresh", app.address))
+        .header("Cookie", &refresh_cookie)
+        .header("X-CSRF-Token", csrf)
+        .send()
+        .await
+        .expect("Failed to send refresh request.");
+    assert_eq!(res.status(), 401);
+
+    // Reusing the old token revoked the whole session, so start a new one.
+    let res = client
+        .post(format!("{}/user/login", app.address))
+        .json(&serde_json::json!({ "username": "synul", "password": "correct horse battery" }))
+        .send()
+        .await
+        .expect("Failed to send login request.");
+    let cookies = set_cookies(&res);
+    let body: JsonBody<UserAuthData> = res.json().await.unwrap();
+    let refresh_cookie = format!(
+        "alloxid_refresh={}",
+        cookie_value(&cookies["alloxid_refresh"])
+    );
+
+    let res = client
+        .post(format!("{}/user/logout", app.address))
+        .header("Cookie", &refresh_cookie)
+        .send()
+        .await
+        .expect("Failed to send logout request.");
+    assert_eq!(res.status(), 403);
+
+    let res = client
+        .post(format!("{}/user/logout", app.address))
+        .header("Cookie", &refresh_cookie)
+        .header("X-CSRF-Token", cookie_value(&cookies["alloxid_csrf"]))
+        .send()
+        .await
+        .expect("Failed to send logout request.");
+    assert_eq!(res.status(), 200);
+
+    let cleared = set_cookies(&res);
+    for name in ["alloxid_session", "alloxid_refresh", "alloxid_csrf"] {
+        assert!(cleared[name].contains("Max-Age=0"));
+        assert_eq!(cookie_value(&cleared[name]), "");
+    }
+
+    let res = client
+        .get(format!("{}/user/{}", app.address, body.data.id))
+        .header(
+            "Cookie",
+            format!(
+                "alloxid_session={}",
+                cookie_value(&cookies["alloxid_session"])
+            ),
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 401);
+}
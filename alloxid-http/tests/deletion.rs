@@ -0,0 +1,178 @@
+#![allow(clippy::expect_fun_call)]
+
+use std::time::Duration;
+
+use tracing::{info, instrument};
+
+use alloxid_http::model::user::{UserAuthData, UserData};
+use alloxid_http::settings::Settings;
+use alloxid_http::JsonBody;
+
+mod helpers;
+use helpers::{create_user, spawn_test_app, spawn_test_app_with_settings, TestApp, PASSWORD};
+
+async fn post(app: &TestApp, route: &str, json: serde_json::Value) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("{}{}", app.address, route))
+        .json(&json)
+        .send()
+        .await
+        .expect(&format!("Failed to execute POST request at {}", route))
+}
+
+async fn delete_user(app: &TestApp, user: &UserAuthData) -> reqwest::Response {
+    let route = format!("/user/{}", user.id);
+
+    reqwest::Client::new()
+        .delete(format!("{}{}", app.address, &route))
+        .bearer_auth(&user.token)
+        .send()
+        .await
+        .expect(&format!("Failed to execute DELETE request at {}", &route))
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn deleted_user_is_restored_within_grace_period() {
+    let app = spawn_test_app().await;
+    info!(
+        "deleted_user_is_restored_within_grace_period: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let user = create_user(&app, "synul").await;
+    let credentials = serde_json::json!({ "username": "synul", "password": PASSWORD });
+
+    let res = delete_user(&app, &user).await;
+    assert_eq!(res.status(), 200);
+
+    let res = post(&app, "/user/login", credentials.clone()).await;
+    assert_eq!(res.status(), 401);
+
+    // The username stays taken until the account is purged.
+    let res = post(&app, "/user", credentials.clone()).await;
+    assert_eq!(res.status(), 409);
+
+    let res = post(
+        &app,
+        "/user/restore",
+        serde_json::json!({ "username": "synul", "password": "wrong horse battery" }),
+    )
+    .await;
+    assert_eq!(res.status(), 401);
+
+    let res = post(&app, "/user/restore", credentials.clone()).await;
+    assert_eq!(res.status(), 200);
+    let restored = res.json::<JsonBody<UserAuthData>>().await.unwrap().data;
+    assert_eq!(restored.id, user.id);
+
+    let res = reqwest::Client::new()
+        .get(format!("{}/user/{}", app.address, user.id))
+        .bearer_auth(&restored.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    let data = res.json::<JsonBody<UserData>>().await.unwrap().data;
+    assert_eq!(data.username, "synul");
+
+    // Active accounts can't be restored.
+    let res = post(&app, "/user/restore", credentials.clone()).await;
+    assert_eq!(res.status(), 401);
+
+    let res = post(&app, "/user/login", credentials.clone()).await;
+    assert_eq!(res.status(), 200);
+
+    let res = delete_user(&app, &restored).await;
+    assert_eq!(res.status(), 200);
+
+    // Pretend the grace period has passed.
+    sqlx::query("update users set deleted_at = deleted_at - interval '31 days'")
+        .execute(&app.test_db.pool())
+        .await
+        .expect("Failed to backdate deletion.");
+
+    let res = post(&app, "/user/restore", credentials).await;
+    assert_eq!(res.status(), 404);
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn restoring_keeps_a_required_password_reset() {
+    let app = spawn_test_app().await;
+    info!(
+        "restoring_keeps_a_required_password_reset: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let user = create_user(&app, "synul").await;
+    let credentials = serde_json::json!({ "username": "synul", "password": PASSWORD });
+
+    // Like `POST /admin/users/:id/password-reset`, which leaves API keys working.
+    sqlx::query("update users set password_reset_required = true where id = $1")
+        .bind(user.id)
+        .execute(&app.test_db.pool())
+        .await
+        .expect("Failed to require a password reset.");
+
+    let res = delete_user(&app, &user).await;
+    assert_eq!(res.status(), 200);
+
+    let res = post(&app, "/user/restore", credentials).await;
+    dbg!(&res);
+    assert_eq!(res.status(), 403);
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn deleted_users_are_purged_after_grace_period() {
+    let mut settings = Settings::new_for_test().expect("Failed to load configuration.");
+    settings.deletion.grace_period_days = 0;
+    settings.deletion.purge_interval_seconds = 1;
+
+    let app = spawn_test_app_with_settings(settings).await;
+    info!(
+        "deleted_users_are_purged_after_grace_period: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let user = create_user(&app, "synul").await;
+    let res = delete_user(&app, &user).await;
+    assert_eq!(res.status(), 200);
+
+    // Wait for the next run of the purge task.
+    async_std::task::sleep(Duration::from_millis(1500)).await;
+
+    let row: (i64, i64) =
+        sqlx::query_as("select (select count(*) from users), (select count(*) from auth_tokens)")
+            .fetch_one(&app.test_db.pool())
+            .await
+            .expect("Failed to count rows.");
+    assert_eq!(row, (0, 0));
+
+    // The audit events are kept, without the username and email address.
+    let details: Vec<(serde_json::Value,)> =
+        sqlx::query_as("select details from audit_events where subject_id = $1")
+            .bind(user.id)
+            .fetch_all(&app.test_db.pool())
+            .await
+            .expect("Failed to fetch audit events.");
+    assert!(!details.is_empty());
+    for (details,) in details {
+        assert!(!details.to_string().contains("synul"), "{}", details);
+    }
+
+    let res = post(
+        &app,
+        "/user/restore",
+        serde_json::json!({ "username": "synul", "password": PASSWORD }),
+    )
+    .await;
+    assert_eq!(res.status(), 401);
+
+    // The username is free again.
+    create_user(&app, "synul").await;
+}
@@ -0,0 +1,222 @@
+#![allow(clippy::expect_fun_call)]
+
+use std::path::Path;
+
+use tracing::{info, instrument};
+use uuid::Uuid;
+
+use alloxid_http::error::JsonError;
+use alloxid_http::mailer::Email;
+use alloxid_http::model::user::{UserAuthData, UserData};
+use alloxid_http::settings::{Mailer, Settings};
+use alloxid_http::JsonBody;
+
+mod helpers;
+use helpers::{spawn_test_app_with_settings, TestApp};
+
+const PASSWORD: &str = "correct horse battery";
+
+fn mail_settings(mails: &Path) -> Settings {
+    let mut settings = Settings::new_for_test().expect("Failed to load configuration.");
+    settings.mailer = Mailer::File {
+        path: mails.to_string_lossy().to_string(),
+    };
+
+    settings
+}
+
+fn read_mails(path: &Path) -> Vec<Email> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => contents
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("Failed to parse mail"))
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+// The route and query of the link in the last mail.
+fn verification_link(mails: &Path) -> String {
+    let mail = read_mails(mails).pop().expect("No verification mail");
+    let start = mail.body.find("/user/verify?token=").expect("No link");
+    let end = mail.body[start..].find('\n').unwrap();
+
+    mail.body[start..start + end].to_string()
+}
+
+async fn post(app: &TestApp, route: &str, json: serde_json::Value) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("{}{}", app.address, route))
+        .json(&json)
+        .send()
+        .await
+        .expect(&format!("Failed to execute POST request at {}", route))
+}
+
+async fn get(app: &TestApp, route: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .get(format!("{}{}", app.address, route))
+        .send()
+        .await
+        .expect(&format!("Failed to execute GET request at {}", route))
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn email_is_verified_once_and_then_allows_login() {
+    let mails = std::env::temp_dir().join(format!("alloxid-{}.jsonl", Uuid::new_v4()));
+    let app = spawn_test_app_with_settings(mail_settings(&mails)).await;
+    info!(
+        "email_is_verified_once_and_then_allows_login: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let res = post(
+        &app,
+        "/user",
+        serde_json::json!({ "username": "synul", "password": PASSWORD, "email": " Synul@Example.com " }),
+    )
+    .await;
+    assert_eq!(res.status(), 201);
+    let user = res.json::<JsonBody<UserAuthData>>().await.unwrap().data;
+
+    let sent = read_mails(&mails);
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].to, "Synul@Example.com");
+
+    // Unverified addresses can't be used to log in.
+    let login = serde_json::json!({ "email": "synul@example.com", "password": PASSWORD });
+    let res = post(&app, "/user/login", login.clone()).await;
+    assert_eq!(res.status(), 401);
+
+    let res = reqwest::Client::new()
+        .get(format!("{}/user/{}", app.address, user.id))
+        .bearer_auth(&user.token)
+        .send()
+        .await
+        .unwrap();
+    let data = res.json::<JsonBody<UserData>>().await.unwrap().data;
+    assert_eq!(data.email.as_deref(), Some("Synul@Example.com"));
+    assert!(!data.email_verified);
+
+    let link = verification_link(&mails);
+    let res = get(&app, &link).await;
+    assert_eq!(res.status(), 200);
+    let data = res.json::<JsonBody<UserData>>().await.unwrap().data;
+    assert_eq!(data.id, user.id);
+    assert!(data.email_verified);
+
+    // Links are single-use.
+    let res = get(&app, &link).await;
+    assert_eq!(res.status(), 401);
+
+    let res = get(&app, "/user/verify?token=forged").await;
+    assert_eq!(res.status(), 401);
+
+    let res = post(&app, "/user/login", login).await;
+    assert_eq!(res.status(), 200);
+    let body: JsonBody<UserAuthData> = res.json().await.unwrap();
+    assert_eq!(body.data.id, user.id);
+
+    // Logging in by username still works.
+    let res = post(
+        &app,
+        "/user/login",
+        serde_json::json!({ "username": "synul", "password": PASSWORD }),
+    )
+    .await;
+    assert_eq!(res.status(), 200);
+
+    // Signing up with a taken address neither fails nor claims it.
+    let res = post(
+        &app,
+        "/user",
+        serde_json::json!({ "username": "other", "password": PASSWORD, "email": "SYNUL@example.com" }),
+    )
+    .await;
+    assert_eq!(res.status(), 201);
+
+    // Verified addresses are unique regardless of case.
+    let res = get(&app, &verification_link(&mails)).await;
+    assert_eq!(res.status(), 409);
+    let body: JsonError = res.json().await.unwrap();
+    assert_eq!(body.error.details[0].field, "email");
+
+    let res = post(
+        &app,
+        "/user",
+        serde_json::json!({ "username": "other", "password": PASSWORD, "email": "not-an-address" }),
+    )
+    .await;
+    assert_eq!(res.status(), 422);
+
+    let _ = std::fs::remove_file(&mails);
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn verification_mail_resend_is_rate_limited() {
+    let mails = std::env::temp_dir().join(format!("alloxid-{}.jsonl", Uuid::new_v4()));
+    let app = spawn_test_app_with_settings(mail_settings(&mails)).await;
+    info!(
+        "verification_mail_resend_is_rate_limited: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let res = post(
+        &app,
+        "/user",
+        serde_json::json!({ "username": "synul", "password": PASSWORD, "email": "synul@example.com" }),
+    )
+    .await;
+    assert_eq!(res.status(), 201);
+    let user = res.json::<JsonBody<UserAuthData>>().await.unwrap().data;
+    let first_link = verification_link(&mails);
+
+    let resend = || {
+        reqwest::Client::new()
+            .post(format!("{}/user/{}/email/verify", app.address, user.id))
+            .bearer_auth(&user.token)
+            .send()
+    };
+
+    let res = resend().await.unwrap();
+    assert_eq!(res.status(), 429);
+    assert!(res.headers().contains_key("retry-after"));
+
+    // Pretend the interval has passed.
+    sqlx::query("update email_verifications set created_at = created_at - interval '1 hour'")
+        .execute(&app.test_db.pool())
+        .await
+        .expect("Failed to backdate email verification.");
+
+    let res = resend().await.unwrap();
+    assert_eq!(res.status(), 200);
+    assert_eq!(read_mails(&mails).len(), 2);
+
+    // Only the latest link is valid.
+    let res = get(&app, &first_link).await;
+    assert_eq!(res.status(), 401);
+    let res = get(&app, &verification_link(&mails)).await;
+    assert_eq!(res.status(), 200);
+
+    let res = resend().await.unwrap();
+    assert_eq!(res.status(), 409);
+
+    // A new address has to be verified again.
+    let res = reqwest::Client::new()
+        .patch(format!("{}/user/{}", app.address, user.id))
+        .bearer_auth(&user.token)
+        .json(&serde_json::json!({ "email": "synul@example.org" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    let data = res.json::<JsonBody<UserData>>().await.unwrap().data;
+    assert!(!data.email_verified);
+    assert_eq!(read_mails(&mails).pop().unwrap().to, "synul@example.org");
+
+    let _ = std::fs::remove_file(&mails);
+}
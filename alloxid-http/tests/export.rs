@@ -0,0 +1,199 @@
+#![allow(clippy::expect_fun_call)]
+
+use std::time::Duration;
+
+use tracing::{info, instrument};
+use uuid::Uuid;
+
+use alloxid_http::model::export::{DataExportData, DataExportStatus, UserExport};
+use alloxid_http::settings::Settings;
+use alloxid_http::JsonBody;
+
+mod helpers;
+use helpers::{create_user, spawn_test_app, spawn_test_app_with_settings};
+
+async fn get(url: &str, token: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .get(url)
+        .bearer_auth(token)
+        .send()
+        .await
+        .expect(&format!("Failed to execute GET request at {}", url))
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn small_export_is_returned_as_file() {
+    let app = spawn_test_app().await;
+    info!(
+        "small_export_is_returned_as_file: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let user = create_user(&app, "synul").await;
+    let other = create_user(&app, "other").await;
+
+    let res = reqwest::Client::new()
+        .post(format!("{}/user/{}/api-keys", app.address, user.id))
+        .bearer_auth(&user.token)
+        .json(&serde_json::json!({ "name": "ci", "scopes": ["user:read"] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 201);
+
+    // Somebody else trying the password of the user.
+    let res = reqwest::Client::new()
+        .post(format!("{}/user/login", app.address))
+        .json(&serde_json::json!({ "username": "synul", "password": "wrong" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 401);
+
+    let route = format!("{}/user/{}/export", app.address, user.id);
+
+    let res = get(&route, &other.token).await;
+    assert_eq!(res.status(), 403);
+
+    let res = get(&route, &user.token).await;
+    assert_eq!(res.status(), 200);
+    let disposition = res.headers()["content-disposition"].to_str().unwrap();
+    assert!(disposition.starts_with("attachment;"));
+
+    let raw: serde_json::Value = res.json().await.unwrap();
+    assert!(raw["user"].get("hashed_password").is_none());
+    assert!(raw["user"].get("totp_secret").is_none());
+
+    let export: UserExport = serde_json::from_value(raw).unwrap();
+    assert_eq!(export.user.id, user.id);
+    assert_eq!(export.user.username, "synul");
+    assert_eq!(export.sessions.len(), 1);
+    assert_eq!(export.api_keys.len(), 1);
+    assert_eq!(export.api_keys[0].name, "ci");
+
+    // Only events of the user themselves tell where they came from.
+    let created = export
+        .audit_events
+        .iter()
+        .find(|event| event.action == "user.created");
+    assert!(created.unwrap().ip.is_some());
+    let failed = export
+        .audit_events
+        .iter()
+        .find(|event| event.action == "user.login_failed");
+    let failed = failed.unwrap();
+    assert!(failed.ip.is_none());
+    assert!(failed.user_agent.is_none());
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn large_export_runs_in_background_with_download_link() {
+    let mut settings = Settings::new_for_test().expect("Failed to load configuration.");
+    settings.export.inline_max_records = 0;
+
+    let app = spawn_test_app_with_settings(settings).await;
+    info!(
+        "large_export_runs_in_background_with_download_link: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let user = create_user(&app, "synul").await;
+
+    let res = get(
+        &format!("{}/user/{}/export", app.address, user.id),
+        &user.token,
+    )
+    .await;
+    assert_eq!(res.status(), 202);
+    let status_url = res.headers()["location"].to_str().unwrap().to_string();
+    let export = res.json::<JsonBody<DataExportData>>().await.unwrap().data;
+    assert_eq!(export.status, DataExportStatus::Pending);
+    assert!(export.download_url.is_none());
+
+    let mut export = export;
+    for _ in 0..50 {
+        if export.status != DataExportStatus::Pending {
+            break;
+        }
+        async_std::task::sleep(Duration::from_millis(100)).await;
+
+        let res = get(&status_url, &user.token).await;
+        assert_eq!(res.status(), 200);
+        export = res.json::<JsonBody<DataExportData>>().await.unwrap().data;
+    }
+    assert_eq!(export.status, DataExportStatus::Ready);
+
+    // The link works without a session.
+    let download_url = export.download_url.expect("No download link");
+    let res = reqwest::get(&download_url).await.unwrap();
+    assert_eq!(res.status(), 200);
+    assert!(res.headers().contains_key("content-disposition"));
+    let data: UserExport = res.json().await.unwrap();
+    assert_eq!(data.user.id, user.id);
+
+    let res = reqwest::get(format!("{}/user/export/download?token=forged", app.address))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 401);
+
+    // Links expire together with the export.
+    sqlx::query("update data_exports set expires_at = now() - interval '1 second'")
+        .execute(&app.test_db.pool())
+        .await
+        .expect("Failed to expire data export.");
+
+    let res = reqwest::get(&download_url).await.unwrap();
+    assert_eq!(res.status(), 404);
+    let res = get(&status_url, &user.token).await;
+    assert_eq!(res.status(), 404);
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn stale_pending_export_is_failed_and_replaced() {
+    let mut settings = Settings::new_for_test().expect("Failed to load configuration.");
+    settings.export.inline_max_records = 0;
+
+    let app = spawn_test_app_with_settings(settings).await;
+    info!(
+        "stale_pending_export_is_failed_and_replaced: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let user = create_user(&app, "synul").await;
+
+    // Like an export whose task has been lost by a restart.
+    let stale_id = Uuid::new_v4();
+    sqlx::query(
+        "insert into data_exports (id, user_id, status, created_at, expires_at) \
+         values ($1, $2, 'pending', now() - interval '1 hour', now() + interval '23 hours')",
+    )
+    .bind(stale_id)
+    .bind(user.id)
+    .execute(&app.test_db.pool())
+    .await
+    .expect("Failed to insert stale data export.");
+
+    let res = get(
+        &format!("{}/user/{}/export", app.address, user.id),
+        &user.token,
+    )
+    .await;
+    assert_eq!(res.status(), 202);
+    let export = res.json::<JsonBody<DataExportData>>().await.unwrap().data;
+    assert_ne!(export.id, stale_id);
+
+    let res = get(
+        &format!("{}/user/{}/export/{}", app.address, user.id, stale_id),
+        &user.token,
+    )
+    .await;
+    assert_eq!(res.status(), 200);
+    let stale = res.json::<JsonBody<DataExportData>>().await.unwrap().data;
+    assert_eq!(stale.status, DataExportStatus::Failed);
+}
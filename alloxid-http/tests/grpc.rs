@@ -0,0 +1,268 @@
+#![allow(clippy::expect_fun_call)]
+
+use std::net::{SocketAddr, TcpListener};
+
+use sqlx::PgPool;
+use tokio::time::{sleep, timeout, Duration};
+use tonic::transport::{Channel, Server};
+use tonic::{Code, Request, Response, Status};
+use tracing::{info, instrument};
+
+use alloxid_core::auth::{JwtKeys, Role};
+use alloxid_grpc::auth::{self, Authenticator};
+use alloxid_grpc::hello::greeter_server::{Greeter, GreeterServer};
+use alloxid_grpc::hello::{HelloReply, HelloRequest};
+use alloxid_grpc::user::user_event::Kind;
+use alloxid_grpc::user::user_service_client::UserServiceClient;
+use alloxid_grpc::user::{LoginRequest, WatchUserEventsRequest};
+use alloxid_http::settings::Settings;
+
+mod helpers;
+use helpers::{create_admin, create_user, spawn_test_app_with_settings, TestApp, PASSWORD};
+
+/// Greets every user by their id, or fails every call with the given code.
+#[derive(Debug)]
+struct TestGreeter {
+    fail_with: Option<Code>,
+    // The database of the app, where the sessions are looked up.
+    db_pool: PgPool,
+}
+
+#[tonic::async_trait]
+impl Greeter for TestGreeter {
+    async fn say_hello(
+        &self,
+        request: Request<HelloRequest>,
+    ) -> Result<Response<HelloReply>, Status> {
+        let user = auth::require(&request, Role::User, &self.db_pool).await?;
+
+        match self.fail_with {
+            Some(code) => Err(Status::new(code, "Failed on purpose.")),
+            None => Ok(Response::new(HelloReply {
+                message: format!("Hello, {}! ({})", request.into_inner().name, user.user_id),
+            })),
+        }
+    }
+}
+
+fn free_address() -> SocketAddr {
+    TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("Failed to find a free port.")
+}
+
+async fn spawn_greeter(addr: SocketAddr, greeter: TestGreeter) {
+    let settings = Settings::new().expect("Failed to load configuration.");
+    let keys = JwtKeys::from_settings(&settings.auth).expect("Failed to load JWT keys.");
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(GreeterServer::with_interceptor(
+                greeter,
+                Authenticator::new(keys),
+            ))
+            .serve(addr)
+            .await
+            .unwrap();
+    });
+    sleep(Duration::from_millis(100)).await;
+}
+
+async fn spawn_app_calling(grpc_addr: SocketAddr) -> TestApp {
+    let mut settings = Settings::new_for_test().expect("Failed to load configuration.");
+    settings.grpc.address = grpc_addr.to_string();
+    settings.grpc.connect_timeout_seconds = 1;
+
+    spawn_test_app_with_settings(settings).await
+}
+
+async fn hello(app: &TestApp, token: &str) -> reqwest::Response {
+    let route = "/grpc/hello";
+
+    reqwest::Client::new()
+        .get(format!("{}{}", app.address, route))
+        .bearer_auth(token)
+        .send()
+        .await
+        .expect(&format!("Failed to execute GET request at {}", route))
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn hello_reuses_the_channel() {
+    // The app only connects on the first call, by then the greeter is listening.
+    let grpc_addr = free_address();
+    let app = spawn_app_calling(grpc_addr).await;
+    let greeter = TestGreeter {
+        fail_with: None,
+        db_pool: app.test_db.pool(),
+    };
+    spawn_greeter(grpc_addr, greeter).await;
+    info!(
+        "hello_reuses_the_channel: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let user = create_user(&app, "synul").await;
+
+    for _ in 0..3 {
+        let res = hello(&app, &user.token).await;
+        assert_eq!(res.status(), 200);
+
+        // The greeter knows who's calling, so the token has been passed on.
+        let text = res.text().await.unwrap();
+        assert!(text.contains("Hello, Tonic!"));
+        assert!(text.contains(&user.id.to_string()));
+    }
+
+    let res = hello(&app, "not-a-jwt").await;
+    assert_eq!(res.status(), 401);
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn unreachable_grpc_server_is_unavailable() {
+    // Nothing listens here, the handler has to answer instead of panicking.
+    let app = spawn_app_calling(free_address()).await;
+    info!(
+        "unreachable_grpc_server_is_unavailable: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let user = create_user(&app, "synul").await;
+    let res = hello(&app, &user.token).await;
+    assert_eq!(res.status(), 503);
+
+    let body: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(body["error"]["code"], "service_unavailable");
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn grpc_status_is_mapped_to_http_status() {
+    let cases = [
+        (Code::NotFound, 404),
+        (Code::PermissionDenied, 403),
+        (Code::ResourceExhausted, 429),
+        (Code::Internal, 500),
+    ];
+
+    for (code, status) in cases {
+        let grpc_addr = free_address();
+        let app = spawn_app_calling(grpc_addr).await;
+        let greeter = TestGreeter {
+            fail_with: Some(code),
+            db_pool: app.test_db.pool(),
+        };
+        spawn_greeter(grpc_addr, greeter).await;
+        info!(
+            "grpc_status_is_mapped_to_http_status: code={:?} app_port={} db_name={}",
+            code, &app.port, &app.test_db.db_name
+        );
+
+        let user = create_user(&app, "synul").await;
+        let res = hello(&app, &user.token).await;
+        assert_eq!(res.status(), status, "Unexpected status for {:?}", code);
+    }
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn embedded_server_streams_user_events_of_the_app() {
+    let grpc_addr = free_address();
+    let mut settings = Settings::new_for_test().expect("Failed to load configuration.");
+    settings.grpc.address = grpc_addr.to_string();
+    settings.grpc.embedded = true;
+    let app = spawn_test_app_with_settings(settings).await;
+    info!(
+        "embedded_server_streams_user_events_of_the_app: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let admin = create_admin(&app).await;
+    sleep(Duration::from_millis(100)).await;
+
+    let channel = Channel::from_shared(format!("http://{}", grpc_addr))
+        .expect("Failed to parse address")
+        .connect()
+        .await
+        .expect("Failed to connect to the embedded server");
+    let mut request = Request::new(WatchUserEventsRequest {});
+    request.metadata_mut().insert(
+        "authorization",
+        format!("Bearer {}", admin.token).parse().unwrap(),
+    );
+    let mut events = UserServiceClient::new(channel)
+        .watch_user_events(request)
+        .await
+        .expect("Failed to watch user events")
+        .into_inner();
+
+    let user = create_user(&app, "synul").await;
+    let res = reqwest::Client::new()
+        .delete(format!("{}/user/{}", app.address, user.id))
+        .bearer_auth(&user.token)
+        .send()
+        .await
+        .expect("Failed to send delete user request.");
+    assert!(res.status().is_success());
+
+    for kind in [Kind::Created, Kind::Deleted] {
+        let event = timeout(Duration::from_secs(5), events.message())
+            .await
+            .expect("No user event within 5s")
+            .expect("Failed to receive user event")
+            .expect("User event stream ended");
+        assert_eq!(event.kind, kind as i32, "Unexpected event {:?}", event);
+        assert_eq!(event.user_id, user.id.to_string());
+        assert_eq!(event.actor_id, user.id.to_string());
+    }
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn embedded_server_shares_the_login_throttle() {
+    let grpc_addr = free_address();
+    let mut settings = Settings::new_for_test().expect("Failed to load configuration.");
+    settings.grpc.address = grpc_addr.to_string();
+    settings.grpc.embedded = true;
+    settings.auth.login_throttle.username_free_attempts = 2;
+    settings.auth.login_throttle.ip_free_attempts = 100;
+    settings.auth.login_throttle.backoff_seconds = 60;
+    let app = spawn_test_app_with_settings(settings).await;
+    info!(
+        "embedded_server_shares_the_login_throttle: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    create_user(&app, "synul").await;
+    for _ in 0..2 {
+        let res = reqwest::Client::new()
+            .post(format!("{}/user/login", app.address))
+            .json(&serde_json::json!({ "username": "synul", "password": "wrong password" }))
+            .send()
+            .await
+            .expect("Failed to send login request.");
+        assert_eq!(res.status(), 401);
+    }
+
+    // The failures over HTTP used up the attempts over gRPC as well.
+    let channel = Channel::from_shared(format!("http://{}", grpc_addr))
+        .expect("Failed to parse address")
+        .connect()
+        .await
+        .expect("Failed to connect to the embedded server");
+    let status = UserServiceClient::new(channel)
+        .login(LoginRequest {
+            username: "synul".to_string(),
+            password: PASSWORD.to_string(),
+        })
+        .await
+        .expect_err("Logged in during the lockout");
+    assert_eq!(status.code(), Code::ResourceExhausted);
+}
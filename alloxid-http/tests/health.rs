@@ -1,3 +1,7 @@
+#![allow(clippy::expect_fun_call)]
+
+use alloxid_http::error::JsonError;
+
 mod helpers;
 use helpers::spawn_test_app;
 
@@ -14,3 +18,21 @@ async fn health_check() {
     dbg!(&res);
     assert_eq!(res.status(), 200);
 }
+
+//#[ignore]
+#[tokio::test]
+async fn unknown_routes_return_the_json_error_body() {
+    let app = spawn_test_app().await;
+
+    let route = "/does-not-exist";
+
+    let res = reqwest::get(format!("{}{}", app.address, route))
+        .await
+        .expect(&format!("Failed to execute GET request at {}", &route));
+    assert_eq!(res.status(), 404);
+    assert!(res.headers().get("x-request-id").is_some());
+
+    let body: JsonError = res.json().await.unwrap();
+    assert_eq!(body.error.code, "not_found");
+    assert!(body.error.request_id.is_some());
+}
@@ -3,10 +3,16 @@ use std::net::SocketAddr;
 use once_cell::sync::Lazy;
 use sqlx::{Connection, Executor, PgConnection, PgPool};
 use tracing::{debug, instrument, trace};
+use uuid::Uuid;
 
 use alloxid_http::configure_app;
+use alloxid_http::model::user::UserAuthData;
 use alloxid_http::settings::Settings;
 use alloxid_http::telemetry::{get_subscriber, init_subscriber};
+use alloxid_http::JsonBody;
+
+#[allow(dead_code)]
+pub const PASSWORD: &str = "correct horse battery";
 
 static TRACING: Lazy<()> = Lazy::new(|| {
     let subscriber = get_subscriber(
@@ -22,6 +28,7 @@ pub struct TestApp {
     // We want to keep this alive until the end of the test.
     #[allow(dead_code)]
     pub test_db: TestDb,
+    #[allow(dead_code)]
     pub port: usize,
 }
 
@@ -86,10 +93,16 @@ impl Drop for TestDb {
 
 #[instrument(level = "debug")]
 pub async fn spawn_test_app() -> TestApp {
-    Lazy::force(&TRACING);
-
     let settings = Settings::new_for_test().expect("Failed to load configuration.");
 
+    spawn_test_app_with_settings(settings).await
+}
+
+#[allow(dead_code)]
+#[instrument(level = "debug", skip(settings))]
+pub async fn spawn_test_app_with_settings(settings: Settings) -> TestApp {
+    Lazy::force(&TRACING);
+
     let test_db = TestDb::new(&settings).await;
 
     let port = settings.app.port;
@@ -102,7 +115,7 @@ pub async fn spawn_test_app() -> TestApp {
 
     tokio::spawn(async move {
         axum::Server::bind(&address)
-            .serve(app.into_make_service())
+            .serve(app.into_make_service_with_connect_info::<SocketAddr>())
             .await
             .unwrap()
     });
@@ -122,6 +135,56 @@ pub async fn spawn_test_app() -> TestApp {
     }
 }
 
+#[allow(dead_code)]
+pub async fn create_user(app: &TestApp, username: &str) -> UserAuthData {
+    let res = reqwest::Client::new()
+        .post(format!("{}/user", app.address))
+        .json(&serde_json::json!({ "username": username, "password": PASSWORD }))
+        .send()
+        .await
+        .expect("Failed to send create user request.");
+    assert_eq!(res.status(), 201);
+
+    res.json::<JsonBody<UserAuthData>>().await.unwrap().data
+}
+
+#[allow(dead_code)]
+pub async fn login(app: &TestApp, username: &str, password: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("{}/user/login", app.address))
+        .json(&serde_json::json!({ "username": username, "password": password }))
+        .send()
+        .await
+        .expect("Failed to send login request.")
+}
+
+// There is no endpoint to create the first admin, so we promote a user in the database.
+#[allow(dead_code)]
+pub async fn create_admin(app: &TestApp) -> UserAuthData {
+    let user = create_user(app, "moderator").await;
+
+    sqlx::query("update users set role = 'Admin' where id = $1")
+        .bind(user.id)
+        .execute(&app.test_db.pool())
+        .await
+        .expect("Failed to promote user to admin.");
+
+    let res = login(app, "moderator", PASSWORD).await;
+    assert_eq!(res.status(), 200);
+
+    res.json::<JsonBody<UserAuthData>>().await.unwrap().data
+}
+
+#[allow(dead_code)]
+pub async fn get_user(app: &TestApp, user_id: Uuid, token: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .get(format!("{}/user/{}", app.address, user_id))
+        .header("Authorization", format!("Bearer {}", token))
+        .send()
+        .await
+        .expect("Failed to send get user request.")
+}
+
 // async fn create_db(pg_conn: &str, db_name: &str) {
 //     let mut conn = PgConnection::connect(pg_conn)
 //         .await
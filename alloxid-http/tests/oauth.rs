@@ -0,0 +1,330 @@
+#![allow(clippy::expect_fun_call)]
+
+use std::collections::HashMap;
+
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use reqwest::redirect::Policy;
+use sha2::{Digest, Sha256};
+use tracing::{info, instrument};
+use url::Url;
+
+use alloxid_http::configure_app;
+use alloxid_http::error::{OAuthError, ServiceError};
+use alloxid_http::model::oauth::{IdTokenClaims, OAuthClientCreatedData, TokenResponse};
+use alloxid_http::settings::Settings;
+use alloxid_http::JsonBody;
+
+mod helpers;
+use helpers::{create_admin, create_user, spawn_test_app_with_settings, TestApp, TestDb};
+
+const REDIRECT_URI: &str = "http://localhost:9999/callback";
+const CODE_VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXkdBjftJeZ4CVP-mB92K27";
+
+// ID tokens are only verifiable by clients if they are signed with an asymmetric key.
+fn rsa_settings() -> Settings {
+    let mut settings = Settings::new_for_test().expect("Failed to load configuration.");
+    let key = &mut settings.auth.signing_key;
+    key.kid = "test-rsa".to_string();
+    key.algorithm = Algorithm::RS256;
+    key.private_key_path = Some("tests/fixtures/rs256_private.pem".to_string());
+    key.public_key_path = Some("tests/fixtures/rs256_public.pem".to_string());
+
+    settings
+}
+
+fn client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .redirect(Policy::none())
+        .build()
+        .unwrap()
+}
+
+async fn register_client(app: &TestApp, confidential: bool) -> OAuthClientCreatedData {
+    let admin = create_admin(app).await;
+
+    let res = client()
+        .post(format!("{}/admin/oauth/clients", app.address))
+        .header("Authorization", format!("Bearer {}", admin.token))
+        .json(&serde_json::json!({
+            "name": "wiki",
+            "redirect_uris": [REDIRECT_URI],
+            "confidential": confidential,
+        }))
+        .send()
+        .await
+        .expect("Failed to register client.");
+    assert_eq!(res.status(), 201);
+
+    let body: JsonBody<OAuthClientCreatedData> = res.json().await.unwrap();
+    body.data
+}
+
+fn code_challenge(verifier: &str) -> String {
+    base64::encode_config(Sha256::digest(verifier.as_bytes()), base64::URL_SAFE_NO_PAD)
+}
+
+async fn authorize(
+    app: &TestApp,
+    token: Option<&str>,
+    query: &[(&str, &str)],
+) -> reqwest::Response {
+    let mut req = client()
+        .get(format!("{}/oauth/authorize", app.address))
+        .query(query);
+    if let Some(token) = token {
+        req = req.header("Authorization", format!("Bearer {}", token));
+    }
+
+    req.send()
+        .await
+        .expect("Failed to execute GET request at /oauth/authorize")
+}
+
+fn location_query(res: &reqwest::Response) -> (String, HashMap<String, String>) {
+    let location = res.headers()["location"].to_str().unwrap();
+    let url = Url::parse(location).unwrap();
+
+    let base = format!("{}{}", url.origin().ascii_serialization(), url.path());
+    (base, url.query_pairs().into_owned().collect())
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn authorization_code_flow_with_pkce_issues_verifiable_id_token() {
+    let app = spawn_test_app_with_settings(rsa_settings()).await;
+    info!(
+        "authorization_code_flow_with_pkce_issues_verifiable_id_token: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let registered = register_client(&app, true).await;
+    let client_id = registered.client.client_id.clone();
+    let client_secret = registered.client_secret.expect("Missing client secret");
+
+    let res = client()
+        .get(format!("{}/.well-known/openid-configuration", app.address))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    let discovery: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(discovery["issuer"], app.address);
+    assert_eq!(
+        discovery["id_token_signing_alg_values_supported"][0],
+        "RS256"
+    );
+    let token_endpoint = discovery["token_endpoint"].as_str().unwrap().to_string();
+
+    let challenge = code_challenge(CODE_VERIFIER);
+    let query = [
+        ("response_type", "code"),
+        ("client_id", client_id.as_str()),
+        ("redirect_uri", REDIRECT_URI),
+        ("scope", "openid profile"),
+        ("state", "af0ifjsldkj"),
+        ("nonce", "n-0S6_WzA2Mj"),
+        ("code_challenge", challenge.as_str()),
+        ("code_challenge_method", "S256"),
+    ];
+
+    // Without a session, users are sent to log in first.
+    let res = authorize(&app, None, &query).await;
+    assert!(res.status().is_redirection());
+    let (location, params) = location_query(&res);
+    assert_eq!(location, "http://localhost:8080/sign-in");
+    assert!(params["return_to"].starts_with(&format!("{}/oauth/authorize?", app.address)));
+
+    let user = create_user(&app, "synul").await;
+    let res = authorize(&app, Some(&user.token), &query).await;
+    dbg!(&res);
+    assert!(res.status().is_redirection());
+    let (location, params) = location_query(&res);
+    assert_eq!(location, REDIRECT_URI);
+    assert_eq!(params["state"], "af0ifjsldkj");
+    let code = params["code"].clone();
+
+    let exchange = |code_verifier: &'static str| {
+        client()
+            .post(&token_endpoint)
+            .basic_auth(&client_id, Some(&client_secret))
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code.as_str()),
+                ("redirect_uri", REDIRECT_URI),
+                ("code_verifier", code_verifier),
+            ])
+            .send()
+    };
+
+    let res = exchange(CODE_VERIFIER).await.unwrap();
+    dbg!(&res);
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["cache-control"], "no-store");
+    let tokens: TokenResponse = res.json().await.unwrap();
+    assert_eq!(tokens.token_type, "Bearer");
+    assert_eq!(tokens.scope, "openid profile");
+
+    // Codes can only be exchanged once.
+    let res = exchange(CODE_VERIFIER).await.unwrap();
+    assert_eq!(res.status(), 400);
+    let error: OAuthError = res.json().await.unwrap();
+    assert_eq!(error.error, "invalid_grant");
+
+    // The ID token can be verified with the published key.
+    let jwks: serde_json::Value = client()
+        .get(discovery["jwks_uri"].as_str().unwrap())
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let jwk = &jwks["keys"][0];
+    assert_eq!(jwk["kid"], "test-rsa");
+    let key =
+        DecodingKey::from_rsa_components(jwk["n"].as_str().unwrap(), jwk["e"].as_str().unwrap());
+    let claims = jsonwebtoken::decode::<IdTokenClaims>(
+        &tokens.id_token,
+        &key,
+        &Validation::new(Algorithm::RS256),
+    )
+    .expect("Failed to verify ID token")
+    .claims;
+    assert_eq!(claims.iss, app.address);
+    assert_eq!(claims.sub, user.id);
+    assert_eq!(claims.aud, client_id);
+    assert_eq!(claims.nonce.as_deref(), Some("n-0S6_WzA2Mj"));
+    assert_eq!(claims.preferred_username.as_deref(), Some("synul"));
+
+    let res = client()
+        .get(discovery["userinfo_endpoint"].as_str().unwrap())
+        .bearer_auth(&tokens.access_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    let userinfo: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(userinfo["sub"], user.id.to_string());
+    assert_eq!(userinfo["preferred_username"], "synul");
+
+    // Access tokens of clients are no tokens for our own API.
+    let res = client()
+        .get(format!("{}/user/{}", app.address, user.id))
+        .bearer_auth(&tokens.access_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 401);
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn invalid_authorization_requests_are_rejected() {
+    let app = spawn_test_app_with_settings(rsa_settings()).await;
+    info!(
+        "invalid_authorization_requests_are_rejected: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let registered = register_client(&app, false).await;
+    let client_id = registered.client.client_id.as_str();
+    assert!(registered.client_secret.is_none());
+    let user = create_user(&app, "synul").await;
+    let challenge = code_challenge(CODE_VERIFIER);
+
+    // Errors are only sent to registered redirect URIs.
+    let res = authorize(
+        &app,
+        Some(&user.token),
+        &[
+            ("response_type", "code"),
+            ("client_id", client_id),
+            ("redirect_uri", "http://evil.example.com/callback"),
+            ("scope", "openid"),
+        ],
+    )
+    .await;
+    assert_eq!(res.status(), 400);
+
+    let res = authorize(
+        &app,
+        Some(&user.token),
+        &[
+            ("response_type", "code"),
+            ("client_id", client_id),
+            ("redirect_uri", REDIRECT_URI),
+            ("scope", "openid"),
+            ("state", "xyz"),
+        ],
+    )
+    .await;
+    assert!(res.status().is_redirection());
+    let (_, params) = location_query(&res);
+    assert_eq!(params["error"], "invalid_request");
+    assert_eq!(params["state"], "xyz");
+
+    let res = authorize(
+        &app,
+        Some(&user.token),
+        &[
+            ("response_type", "code"),
+            ("client_id", client_id),
+            ("redirect_uri", REDIRECT_URI),
+            ("scope", "openid"),
+            ("code_challenge", challenge.as_str()),
+            ("code_challenge_method", "S256"),
+        ],
+    )
+    .await;
+    let (_, params) = location_query(&res);
+    let code = params["code"].as_str();
+
+    // Public clients only identify themselves, but have to prove they started the flow.
+    let res = client()
+        .post(format!("{}/oauth/token", app.address))
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("client_id", client_id),
+            ("code", code),
+            ("redirect_uri", REDIRECT_URI),
+            ("code_verifier", &CODE_VERIFIER.replace('d', "e")),
+        ])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 400);
+    let error: OAuthError = res.json().await.unwrap();
+    assert_eq!(error.error, "invalid_grant");
+
+    let res = client()
+        .post(format!("{}/oauth/token", app.address))
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("client_id", "unknown"),
+            ("code", code),
+        ])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 401);
+    let error: OAuthError = res.json().await.unwrap();
+    assert_eq!(error.error, "invalid_client");
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn invalid_login_url_fails_at_startup() {
+    let mut settings = Settings::new_for_test().expect("Failed to load configuration.");
+    settings.oauth.login_url = "".to_string();
+    let test_db = TestDb::new(&settings).await;
+    info!(
+        "invalid_login_url_fails_at_startup: db_name={}",
+        &test_db.db_name
+    );
+
+    let result = configure_app(test_db.pool(), settings).await;
+    assert!(matches!(result, Err(ServiceError::Config(_))));
+}
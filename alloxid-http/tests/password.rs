@@ -0,0 +1,278 @@
+#![allow(clippy::expect_fun_call)]
+
+use std::path::PathBuf;
+
+use tracing::{info, instrument};
+use uuid::Uuid;
+
+use alloxid_http::mailer::Email;
+use alloxid_http::model::user::UserAuthData;
+use alloxid_http::settings::{Mailer, Settings};
+use alloxid_http::JsonBody;
+
+mod helpers;
+use helpers::{
+    create_user, get_user, login, spawn_test_app, spawn_test_app_with_settings, TestApp, PASSWORD,
+};
+
+const NEW_PASSWORD: &str = "staple tuba elephant";
+
+async fn post(app: &TestApp, route: &str, json: serde_json::Value) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("{}{}", app.address, route))
+        .json(&json)
+        .send()
+        .await
+        .expect(&format!("Failed to execute POST request at {}", route))
+}
+
+fn read_mails(path: &PathBuf) -> Vec<Email> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => contents
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("Failed to parse mail"))
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Reset tokens are sent after the response, so they take a moment to show up.
+async fn wait_for_mails(path: &PathBuf) -> Vec<Email> {
+    for _ in 0..50 {
+        let mails = read_mails(path);
+        if !mails.is_empty() {
+            return mails;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+
+    Vec::new()
+}
+
+// The token stands on its own line, after the first paragraph.
+fn reset_token(mail: &Email) -> String {
+    let token = mail.body.split("\n\n").nth(1).expect("No token");
+    token.trim().to_string()
+}
+
+async fn verify_email(app: &TestApp, user: &UserAuthData, email: &str) {
+    sqlx::query("update users set email = $2, email_verified_at = now() where id = $1")
+        .bind(user.id)
+        .bind(email)
+        .execute(&app.test_db.pool())
+        .await
+        .expect("Failed to verify email address.");
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn change_password_revokes_other_sessions() {
+    let app = spawn_test_app().await;
+    info!(
+        "change_password_revokes_other_sessions: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let user = create_user(&app, "synul").await;
+    let res = login(&app, "synul", PASSWORD).await;
+    let body: JsonBody<UserAuthData> = res.json().await.unwrap();
+    let other_session = body.data;
+
+    let route = format!("/user/{}/password", user.id);
+    let change = |current_password: &str| {
+        reqwest::Client::new()
+            .put(format!("{}{}", app.address, &route))
+            .header("Authorization", format!("Bearer {}", user.token))
+            .json(&serde_json::json!({
+                "current_password": current_password,
+                "new_password": NEW_PASSWORD,
+            }))
+            .send()
+    };
+
+    let res = change("not my password").await.unwrap();
+    dbg!(&res);
+    assert_eq!(res.status(), 422);
+
+    let res = change(PASSWORD).await.unwrap();
+    dbg!(&res);
+    assert_eq!(res.status(), 200);
+
+    // The session that changed the password is still valid, all others are gone.
+    let res = get_user(&app, user.id, &user.token).await;
+    assert_eq!(res.status(), 200);
+    let res = get_user(&app, user.id, &other_session.token).await;
+    assert_eq!(res.status(), 401);
+
+    let res = login(&app, "synul", PASSWORD).await;
+    assert_eq!(res.status(), 401);
+    let res = login(&app, "synul", NEW_PASSWORD).await;
+    assert_eq!(res.status(), 200);
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn guessing_the_current_password_is_throttled() {
+    let mut settings = Settings::new_for_test().expect("Failed to load configuration.");
+    settings.auth.login_throttle.username_free_attempts = 2;
+    settings.auth.login_throttle.ip_free_attempts = 100;
+    settings.auth.login_throttle.backoff_seconds = 60;
+
+    let app = spawn_test_app_with_settings(settings).await;
+    info!(
+        "guessing_the_current_password_is_throttled: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let user = create_user(&app, "synul").await;
+
+    let route = format!("/user/{}/password", user.id);
+    let change = |current_password: &str| {
+        reqwest::Client::new()
+            .put(format!("{}{}", app.address, &route))
+            .header("Authorization", format!("Bearer {}", user.token))
+            .json(&serde_json::json!({
+                "current_password": current_password,
+                "new_password": NEW_PASSWORD,
+            }))
+            .send()
+    };
+
+    for _ in 0..2 {
+        let res = change("not my password").await.unwrap();
+        assert_eq!(res.status(), 422);
+    }
+
+    // Even the right password is rejected during the lockout.
+    let res = change(PASSWORD).await.unwrap();
+    dbg!(&res);
+    assert_eq!(res.status(), 429);
+    assert!(res.headers().contains_key("retry-after"));
+
+    let res = login(&app, "synul", PASSWORD).await;
+    assert_eq!(res.status(), 200);
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn forgotten_password_can_be_reset_once() {
+    let mails = std::env::temp_dir().join(format!("alloxid-{}.jsonl", Uuid::new_v4()));
+
+    let mut settings = Settings::new_for_test().expect("Failed to load configuration.");
+    settings.mailer = Mailer::File {
+        path: mails.to_string_lossy().to_string(),
+    };
+
+    let app = spawn_test_app_with_settings(settings).await;
+    info!(
+        "forgotten_password_can_be_reset_once: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let user = create_user(&app, "synul").await;
+    verify_email(&app, &user, "synul@example.com").await;
+    create_user(&app, "unverified").await;
+
+    // Unknown users and users without a verified address get the same response, but no mail.
+    for username in ["nobody", "unverified"] {
+        let res = post(
+            &app,
+            "/user/password/forgot",
+            serde_json::json!({ "username": username }),
+        )
+        .await;
+        assert_eq!(res.status(), 200);
+    }
+
+    // Like at login, the address works as well.
+    let res = post(
+        &app,
+        "/user/password/forgot",
+        serde_json::json!({ "username": "Synul@example.com" }),
+    )
+    .await;
+    dbg!(&res);
+    assert_eq!(res.status(), 200);
+
+    let sent = wait_for_mails(&mails).await;
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].to, "synul@example.com");
+    let token = reset_token(&sent[0]);
+
+    let res = post(
+        &app,
+        "/user/password/reset",
+        serde_json::json!({ "token": token, "new_password": "password" }),
+    )
+    .await;
+    assert_eq!(res.status(), 422);
+
+    let res = post(
+        &app,
+        "/user/password/reset",
+        serde_json::json!({ "token": token, "new_password": NEW_PASSWORD }),
+    )
+    .await;
+    dbg!(&res);
+    assert_eq!(res.status(), 200);
+
+    // All sessions are gone and the token can't be used again.
+    let res = get_user(&app, user.id, &user.token).await;
+    assert_eq!(res.status(), 401);
+
+    let res = post(
+        &app,
+        "/user/password/reset",
+        serde_json::json!({ "token": token, "new_password": "another fine password" }),
+    )
+    .await;
+    assert_eq!(res.status(), 401);
+
+    let res = login(&app, "synul", NEW_PASSWORD).await;
+    assert_eq!(res.status(), 200);
+
+    let _ = std::fs::remove_file(&mails);
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn password_reset_requests_are_rate_limited() {
+    let mut settings = Settings::new_for_test().expect("Failed to load configuration.");
+    settings.auth.password_reset_throttle.username_free_attempts = 2;
+    settings.auth.password_reset_throttle.ip_free_attempts = 100;
+
+    let app = spawn_test_app_with_settings(settings).await;
+    info!(
+        "password_reset_requests_are_rate_limited: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    create_user(&app, "synul").await;
+
+    // Known and unknown usernames are limited alike.
+    for username in ["synul", "nobody"] {
+        for _ in 0..2 {
+            let res = post(
+                &app,
+                "/user/password/forgot",
+                serde_json::json!({ "username": username }),
+            )
+            .await;
+            assert_eq!(res.status(), 200);
+        }
+
+        let res = post(
+            &app,
+            "/user/password/forgot",
+            serde_json::json!({ "username": username }),
+        )
+        .await;
+        dbg!(&res);
+        assert_eq!(res.status(), 429);
+        assert!(res.headers().contains_key("retry-after"));
+    }
+}
@@ -0,0 +1,217 @@
+#![allow(clippy::expect_fun_call)]
+
+use chrono::Utc;
+use tracing::{info, instrument};
+
+use alloxid_http::model::user::{MfaPendingData, RecoveryCodesData, TotpEnrollData, UserAuthData};
+use alloxid_http::settings::Settings;
+use alloxid_http::totp::{code_at, step_at};
+use alloxid_http::JsonBody;
+
+mod helpers;
+use helpers::{
+    create_user, get_user, login, spawn_test_app, spawn_test_app_with_settings, TestApp, PASSWORD,
+};
+
+async fn login_mfa(app: &TestApp, mfa_token: &str, code: &str) -> reqwest::Response {
+    post(
+        app,
+        "/user/login/mfa",
+        None,
+        serde_json::json!({ "mfa_token": mfa_token, "code": code }),
+    )
+    .await
+}
+
+async fn post(
+    app: &TestApp,
+    route: &str,
+    token: Option<&str>,
+    json: serde_json::Value,
+) -> reqwest::Response {
+    let mut req = reqwest::Client::new()
+        .post(format!("{}{}", app.address, route))
+        .json(&json);
+    if let Some(token) = token {
+        req = req.header("Authorization", format!("Bearer {}", token));
+    }
+
+    req.send()
+        .await
+        .expect(&format!("Failed to execute POST request at {}", route))
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn totp_is_required_at_login_once_enabled() {
+    let app = spawn_test_app().await;
+    info!(
+        "totp_is_required_at_login_once_enabled: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let user = create_user(&app, "synul").await;
+
+    let route = format!("/user/{}/totp/enroll", user.id);
+    let res = post(&app, &route, Some(&user.token), serde_json::json!({})).await;
+    dbg!(&res);
+    assert_eq!(res.status(), 200);
+    let enrollment: JsonBody<TotpEnrollData> = res.json().await.unwrap();
+    let secret = enrollment.data.secret;
+    assert!(enrollment.data.otpauth_uri.starts_with("otpauth://totp/"));
+
+    let step = step_at(Utc::now().timestamp());
+    let code = |step| code_at(&secret, step).expect("Invalid secret");
+
+    let route = format!("/user/{}/totp/confirm", user.id);
+    let res = post(
+        &app,
+        &route,
+        Some(&user.token),
+        serde_json::json!({ "code": "000000" }),
+    )
+    .await;
+    assert_eq!(res.status(), 422);
+
+    let res = post(
+        &app,
+        &route,
+        Some(&user.token),
+        serde_json::json!({ "code": code(step) }),
+    )
+    .await;
+    dbg!(&res);
+    assert_eq!(res.status(), 200);
+    let body: JsonBody<RecoveryCodesData> = res.json().await.unwrap();
+    let recovery_codes = body.data.recovery_codes;
+    assert_eq!(recovery_codes.len(), 10);
+
+    // The password alone only yields a token for the second step, which can't be used elsewhere.
+    let res = login(&app, "synul", PASSWORD).await;
+    assert_eq!(res.status(), 200);
+    let body: JsonBody<MfaPendingData> = res.json().await.unwrap();
+    let mfa_token = body.data.mfa_token;
+
+    let res = get_user(&app, user.id, &mfa_token).await;
+    assert_eq!(res.status(), 401);
+
+    // Codes of the adjacent steps are accepted, but every step only once.
+    let res = login_mfa(&app, &mfa_token, &code(step + 1)).await;
+    dbg!(&res);
+    assert_eq!(res.status(), 200);
+    let body: JsonBody<UserAuthData> = res.json().await.unwrap();
+    let res = get_user(&app, user.id, &body.data.token).await;
+    assert_eq!(res.status(), 200);
+
+    let res = login_mfa(&app, &mfa_token, &code(step + 1)).await;
+    assert_eq!(res.status(), 401);
+
+    // The token is used up, even with a valid code.
+    let res = login_mfa(&app, &mfa_token, &recovery_codes[0]).await;
+    assert_eq!(res.status(), 401);
+
+    let res = login(&app, "synul", PASSWORD).await;
+    let body: JsonBody<MfaPendingData> = res.json().await.unwrap();
+    let mfa_token = body.data.mfa_token;
+    let res = login_mfa(&app, &mfa_token, &recovery_codes[0].to_uppercase()).await;
+    assert_eq!(res.status(), 200);
+
+    let res = login(&app, "synul", PASSWORD).await;
+    let body: JsonBody<MfaPendingData> = res.json().await.unwrap();
+    let mfa_token = body.data.mfa_token;
+    let res = login_mfa(&app, &mfa_token, &recovery_codes[0]).await;
+    assert_eq!(res.status(), 401);
+
+    // The account is checked again after the second factor.
+    sqlx::query("update users set password_reset_required = true where id = $1")
+        .bind(user.id)
+        .execute(&app.test_db.pool())
+        .await
+        .expect("Failed to require a password reset.");
+    let res = login_mfa(&app, &mfa_token, &recovery_codes[2]).await;
+    assert_eq!(res.status(), 403);
+    sqlx::query("update users set password_reset_required = false where id = $1")
+        .bind(user.id)
+        .execute(&app.test_db.pool())
+        .await
+        .expect("Failed to lift the password reset.");
+
+    let res = reqwest::Client::new()
+        .delete(format!("{}/user/{}/totp", app.address, user.id))
+        .header("Authorization", format!("Bearer {}", user.token))
+        .json(&serde_json::json!({ "code": recovery_codes[1] }))
+        .send()
+        .await
+        .unwrap();
+    dbg!(&res);
+    assert_eq!(res.status(), 200);
+
+    let res = login(&app, "synul", PASSWORD).await;
+    assert_eq!(res.status(), 200);
+    let body: JsonBody<UserAuthData> = res.json().await.unwrap();
+    assert!(!body.data.token.is_empty());
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn guessing_totp_codes_is_throttled() {
+    let mut settings = Settings::new_for_test().expect("Failed to load configuration.");
+    settings.auth.login_throttle.username_free_attempts = 2;
+    settings.auth.login_throttle.ip_free_attempts = 100;
+    settings.auth.login_throttle.backoff_seconds = 60;
+
+    let app = spawn_test_app_with_settings(settings).await;
+    info!(
+        "guessing_totp_codes_is_throttled: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let user = create_user(&app, "synul").await;
+
+    let route = format!("/user/{}/totp/enroll", user.id);
+    let res = post(&app, &route, Some(&user.token), serde_json::json!({})).await;
+    let enrollment: JsonBody<TotpEnrollData> = res.json().await.unwrap();
+    let code =
+        code_at(&enrollment.data.secret, step_at(Utc::now().timestamp())).expect("Invalid secret");
+
+    // A success forgets the earlier failure.
+    let route = format!("/user/{}/totp/confirm", user.id);
+    let res = post(
+        &app,
+        &route,
+        Some(&user.token),
+        serde_json::json!({ "code": "000000" }),
+    )
+    .await;
+    assert_eq!(res.status(), 422);
+    let res = post(
+        &app,
+        &route,
+        Some(&user.token),
+        serde_json::json!({ "code": code }),
+    )
+    .await;
+    assert_eq!(res.status(), 200);
+    let body: JsonBody<RecoveryCodesData> = res.json().await.unwrap();
+
+    let disable = |code: &str| {
+        reqwest::Client::new()
+            .delete(format!("{}/user/{}/totp", app.address, user.id))
+            .header("Authorization", format!("Bearer {}", user.token))
+            .json(&serde_json::json!({ "code": code }))
+            .send()
+    };
+
+    for _ in 0..2 {
+        let res = disable("000000").await.unwrap();
+        assert_eq!(res.status(), 422);
+    }
+
+    // Even a valid recovery code is rejected during the lockout.
+    let res = disable(&body.data.recovery_codes[0]).await.unwrap();
+    dbg!(&res);
+    assert_eq!(res.status(), 429);
+    assert!(res.headers().contains_key("retry-after"));
+}
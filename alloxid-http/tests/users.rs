@@ -1,6 +1,9 @@
+#![allow(clippy::expect_fun_call)]
+
 use serde::{Deserialize, Serialize};
 use tracing::{info, instrument};
 
+use alloxid_http::error::JsonError;
 use alloxid_http::model::user::{UserAuthData, UserData};
 use alloxid_http::JsonBody;
 
@@ -14,11 +17,15 @@ struct TestUser {
 }
 
 async fn create_user(app: &TestApp) -> (reqwest::Response, TestUser) {
+    create_named_user(app, "synul").await
+}
+
+async fn create_named_user(app: &TestApp, username: &'static str) -> (reqwest::Response, TestUser) {
     let route = "/user";
 
     let user_data = TestUser {
-        username: "synul",
-        password: "my-pw",
+        username,
+        password: "correct horse battery",
     };
     let json = serde_json::json!(user_data);
 
@@ -73,9 +80,15 @@ async fn create_user_and_login() {
     assert_eq!(res.status(), 200);
 
     let body: JsonBody<UserAuthData> = res.json().await.unwrap();
-    let UserAuthData { id, token } = body.data;
+    let UserAuthData {
+        id,
+        token,
+        refresh_token,
+        ..
+    } = body.data;
     dbg!(&id, &token);
     assert!(!token.is_empty());
+    assert!(!refresh_token.is_empty());
     assert_eq!(user.id, id);
 
     let route = format!("/user/{}", user.id);
@@ -125,6 +138,68 @@ async fn create_user_with_malformatted_data_returns_422() {
     assert_eq!(res.status(), 422);
 }
 
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn create_user_with_invalid_data_returns_422_with_field_errors() {
+    let app = spawn_test_app().await;
+    info!(
+        "create_user_with_invalid_data_returns_422_with_field_errors: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let route = "/user";
+
+    let json = serde_json::json!({
+        "username": "Admin",
+        "password": "password",
+    });
+
+    let res = reqwest::Client::new()
+        .post(format!("{}{}", app.address, &route))
+        .json(&json)
+        .send()
+        .await
+        .expect("Failed to send create user request.");
+    dbg!(&res);
+    assert_eq!(res.status(), 422);
+
+    let body: JsonError = res.json().await.unwrap();
+    assert_eq!(body.error.code, "validation_failed");
+    let errors: Vec<(&str, &str)> = body
+        .error
+        .details
+        .iter()
+        .map(|err| (err.field.as_str(), err.code.as_str()))
+        .collect();
+    assert_eq!(
+        errors,
+        vec![("username", "reserved"), ("password", "too_common")]
+    );
+
+    let json = serde_json::json!({
+        "username": "a b",
+        "password": "short",
+    });
+
+    let res = reqwest::Client::new()
+        .post(format!("{}{}", app.address, &route))
+        .json(&json)
+        .send()
+        .await
+        .expect("Failed to send create user request.");
+    assert_eq!(res.status(), 422);
+
+    let body: JsonError = res.json().await.unwrap();
+    let codes: Vec<&str> = body
+        .error
+        .details
+        .iter()
+        .map(|err| err.code.as_str())
+        .collect();
+    assert_eq!(codes, vec!["invalid_characters", "too_short"]);
+}
+
 // #[ignore]
 #[instrument]
 #[tokio::test]
@@ -204,7 +279,7 @@ async fn get_user_with_malformed_token_returns_401() {
     let client = reqwest::Client::new();
     let res = client
         .get(format!("{}{}", app.address, &route))
-        .header("Authorization", format!("{}", "thisisnotatoken"))
+        .header("Authorization", "thisisnotatoken")
         .send()
         .await
         .expect(&format!("Failed to execute GET request at {}", &route));
@@ -255,13 +330,128 @@ async fn put_user_data_returns_200() {
     assert_eq!(user.username, new_username);
 }
 
+async fn patch_user(app: &TestApp, route: &str, token: &str, body: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .patch(format!("{}{}", app.address, route))
+        .header("Authorization", format!("Bearer {}", token))
+        .header("Content-Type", "application/merge-patch+json")
+        .body(body.to_string())
+        .send()
+        .await
+        .expect(&format!("Failed to execute PATCH request at {}", route))
+}
+
 // #[ignore]
 #[instrument]
 #[tokio::test]
-async fn delete_user_returns_200_then_403() {
+async fn patch_user_merges_profile_fields() {
     let app = spawn_test_app().await;
     info!(
-        "delete_user_returns_200_then_403: app_port={} db_name={}",
+        "patch_user_merges_profile_fields: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let (res, _) = create_user(&app).await;
+    let body: JsonBody<UserAuthData> = res.json().await.unwrap();
+    let token = body.data.token;
+    let route = format!("/user/{}", body.data.id);
+
+    let res = patch_user(
+        &app,
+        &route,
+        &token,
+        r#"{
+            "display_name": " Synul ",
+            "bio": "Likes horses.\nAnd batteries.",
+            "avatar_url": "https://example.com/synul.png",
+            "locale": "de-CH",
+            "timezone": "Europe/Zurich"
+        }"#,
+    )
+    .await;
+    assert_eq!(res.status(), 200);
+    let user = res.json::<JsonBody<UserData>>().await.unwrap().data;
+    assert_eq!(user.username, "synul");
+    assert_eq!(user.display_name.as_deref(), Some("Synul"));
+    assert_eq!(user.bio.as_deref(), Some("Likes horses.\nAnd batteries."));
+    assert_eq!(user.locale.as_deref(), Some("de-CH"));
+
+    // Missing fields are left alone, null clears them.
+    let res = patch_user(
+        &app,
+        &route,
+        &token,
+        r#"{ "username": "synul-renamed", "bio": null }"#,
+    )
+    .await;
+    assert_eq!(res.status(), 200);
+    let user = res.json::<JsonBody<UserData>>().await.unwrap().data;
+    assert_eq!(user.username, "synul-renamed");
+    assert_eq!(user.bio, None);
+    assert_eq!(user.display_name.as_deref(), Some("Synul"));
+    assert_eq!(user.timezone.as_deref(), Some("Europe/Zurich"));
+
+    let res = reqwest::Client::new()
+        .get(format!("{}{}", app.address, &route))
+        .header("Authorization", format!("Bearer {}", token))
+        .send()
+        .await
+        .expect(&format!("Failed to execute GET request at {}", &route));
+    let user = res.json::<JsonBody<UserData>>().await.unwrap().data;
+    assert_eq!(
+        user.avatar_url.as_deref(),
+        Some("https://example.com/synul.png")
+    );
+    assert_eq!(user.bio, None);
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn patch_user_with_invalid_fields_returns_422() {
+    let app = spawn_test_app().await;
+    info!(
+        "patch_user_with_invalid_fields_returns_422: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let (res, _) = create_user(&app).await;
+    let body: JsonBody<UserAuthData> = res.json().await.unwrap();
+    let route = format!("/user/{}", body.data.id);
+
+    let res = patch_user(
+        &app,
+        &route,
+        &body.data.token,
+        r#"{
+            "username": null,
+            "avatar_url": "javascript:alert(1)",
+            "locale": "not a locale",
+            "timezone": "Mars/Olympus_Mons",
+            "display_name": "Nice"
+        }"#,
+    )
+    .await;
+    assert_eq!(res.status(), 422);
+
+    let body: JsonError = res.json().await.unwrap();
+    let mut fields: Vec<_> = body
+        .error
+        .details
+        .iter()
+        .map(|d| d.field.as_str())
+        .collect();
+    fields.sort_unstable();
+    assert_eq!(fields, ["avatar_url", "locale", "timezone", "username"]);
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn delete_user_returns_200_then_401() {
+    let app = spawn_test_app().await;
+    info!(
+        "delete_user_returns_200_then_401: app_port={} db_name={}",
         &app.port, &app.test_db.db_name
     );
 
@@ -282,7 +472,7 @@ async fn delete_user_returns_200_then_403() {
     dbg!(&res);
     assert_eq!(res.status(), 200);
 
-    // Trying to retrieve the user after deletion should return 403.
+    // The token of a deleted user is rejected when authenticating.
     let res = client
         .get(format!("{}{}", app.address, &route))
         .header("Authorization", format!("Bearer {}", token))
@@ -290,5 +480,209 @@ async fn delete_user_returns_200_then_403() {
         .await
         .expect(&format!("Failed to execute GET request at {}", &route));
     dbg!(&res);
+    assert_eq!(res.status(), 401);
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn acting_on_other_user_returns_403() {
+    let app = spawn_test_app().await;
+    info!(
+        "acting_on_other_user_returns_403: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let (res, _) = create_named_user(&app, "synul").await;
+    let body: JsonBody<UserAuthData> = res.json().await.unwrap();
+    let victim = body.data;
+
+    let (res, _) = create_named_user(&app, "mallory").await;
+    let body: JsonBody<UserAuthData> = res.json().await.unwrap();
+    let attacker = body.data;
+
+    let route = format!("/user/{}", victim.id);
+    let auth_header = format!("Bearer {}", attacker.token);
+    let client = reqwest::Client::new();
+
+    let res = client
+        .get(format!("{}{}", app.address, &route))
+        .header("Authorization", &auth_header)
+        .send()
+        .await
+        .expect(&format!("Failed to execute GET request at {}", &route));
+    dbg!(&res);
     assert_eq!(res.status(), 403);
+
+    let res = client
+        .put(format!("{}{}", app.address, &route))
+        .header("Authorization", &auth_header)
+        .json(&serde_json::json!({ "username": "pwned" }))
+        .send()
+        .await
+        .expect(&format!("Failed to execute PUT request at {}", &route));
+    dbg!(&res);
+    assert_eq!(res.status(), 403);
+
+    let res = client
+        .delete(format!("{}{}", app.address, &route))
+        .header("Authorization", &auth_header)
+        .send()
+        .await
+        .expect(&format!("Failed to execute DELETE request at {}", &route));
+    dbg!(&res);
+    assert_eq!(res.status(), 403);
+
+    // The victim is unharmed.
+    let res = client
+        .get(format!("{}{}", app.address, &route))
+        .header("Authorization", format!("Bearer {}", victim.token))
+        .send()
+        .await
+        .expect(&format!("Failed to execute GET request at {}", &route));
+    assert_eq!(res.status(), 200);
+
+    let body: JsonBody<UserData> = res.json().await.unwrap();
+    assert_eq!(body.data.username, "synul");
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn put_user_data_without_token_returns_401() {
+    let app = spawn_test_app().await;
+    info!(
+        "put_user_data_without_token_returns_401: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let (res, _) = create_user(&app).await;
+    let body: JsonBody<UserAuthData> = res.json().await.unwrap();
+    let user = body.data;
+
+    let route = format!("/user/{}", user.id);
+
+    let client = reqwest::Client::new();
+    let res = client
+        .put(format!("{}{}", app.address, &route))
+        .json(&serde_json::json!({ "username": "pwned" }))
+        .send()
+        .await
+        .expect(&format!("Failed to execute PUT request at {}", &route));
+    dbg!(&res);
+    assert_eq!(res.status(), 401);
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn duplicate_username_returns_409() {
+    let app = spawn_test_app().await;
+    info!(
+        "duplicate_username_returns_409: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let (res, _) = create_named_user(&app, "synul").await;
+    assert_eq!(res.status(), 201);
+
+    // Usernames are compared case-insensitively.
+    let (res, _) = create_named_user(&app, "Synul").await;
+    dbg!(&res);
+    assert_eq!(res.status(), 409);
+
+    let body: JsonError = res.json().await.unwrap();
+    assert_eq!(body.error.code, "conflict");
+    assert_eq!(body.error.details[0].field, "username");
+
+    let (res, _) = create_named_user(&app, "mallory").await;
+    let body: JsonBody<UserAuthData> = res.json().await.unwrap();
+    let user = body.data;
+
+    let route = format!("/user/{}", user.id);
+    let res = reqwest::Client::new()
+        .put(format!("{}{}", app.address, &route))
+        .header("Authorization", format!("Bearer {}", user.token))
+        .json(&serde_json::json!({ "username": "SYNUL" }))
+        .send()
+        .await
+        .expect(&format!("Failed to execute PUT request at {}", &route));
+    dbg!(&res);
+    assert_eq!(res.status(), 409);
+
+    let body: JsonError = res.json().await.unwrap();
+    assert_eq!(body.error.code, "conflict");
+    assert_eq!(body.error.details[0].field, "username");
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn errors_are_returned_as_json_with_request_id() {
+    let app = spawn_test_app().await;
+    info!(
+        "errors_are_returned_as_json_with_request_id: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    let route = format!("/user/{}", uuid::Uuid::new_v4());
+    let res = reqwest::Client::new()
+        .get(format!("{}{}", app.address, route))
+        .send()
+        .await
+        .expect(&format!("Failed to execute GET request at {}", route));
+    dbg!(&res);
+    assert_eq!(res.status(), 401);
+
+    let request_id = res
+        .headers()
+        .get("x-request-id")
+        .expect("Missing x-request-id header")
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    let body: JsonError = res.json().await.unwrap();
+    assert_eq!(body.error.code, "unauthorized");
+    assert_eq!(body.error.request_id, Some(request_id));
+
+    let route = "/user/login";
+    let res = reqwest::Client::new()
+        .post(format!("{}{}", app.address, route))
+        .header("Content-Type", "application/json")
+        .body("{ not json")
+        .send()
+        .await
+        .expect(&format!("Failed to execute POST request at {}", route));
+    assert_eq!(res.status(), 400);
+
+    let body: JsonError = res.json().await.unwrap();
+    assert_eq!(body.error.code, "bad_request");
+    assert!(body.error.request_id.is_some());
+}
+
+// #[ignore]
+#[instrument]
+#[tokio::test]
+async fn login_normalizes_the_username_like_signup() {
+    let app = spawn_test_app().await;
+    info!(
+        "login_normalizes_the_username_like_signup: app_port={} db_name={}",
+        &app.port, &app.test_db.db_name
+    );
+
+    // The ligature is stored as "fishmonger".
+    let (res, _) = create_named_user(&app, "\u{FB01}shmonger").await;
+    assert_eq!(res.status(), 201);
+
+    let route = "/user/login";
+    for username in ["\u{FB01}shmonger", " fishmonger "] {
+        let res = reqwest::Client::new()
+            .post(format!("{}{}", app.address, &route))
+            .json(&serde_json::json!({ "username": username, "password": "correct horse battery" }))
+            .send()
+            .await
+            .expect(&format!("Failed to execute POST request at {}", &route));
+        assert_eq!(res.status(), 200, "Failed to log in as {:?}", username);
+    }
 }